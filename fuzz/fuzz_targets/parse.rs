@@ -0,0 +1,20 @@
+#![no_main]
+
+// cargo +nightly fuzz run parse fuzz/corpus/parse fonts
+// the bundled fonts seed the corpus, crashes land in fuzz/artifacts/parse and belong in tests/fuzz_regressions
+
+use font_render::font_loader::FontFile;
+use font_render::shaping::{shape, Direction, DEFAULT_SCRIPT};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+	let Ok(mut font_file) = FontFile::parse(data) else {
+		return;
+	};
+	// shaping walks the cmap, GSUB, GPOS and kern data the parser just accepted
+	let features = font_file.get_feature_settings();
+	shape(&font_file, "fi Ta\u{301}", DEFAULT_SCRIPT, None, Direction::LeftToRight, &features);
+	shape(&font_file, "AV", DEFAULT_SCRIPT, None, Direction::RightToLeft, &features);
+	// --hinting runs the font's fpgm and prep, then every glyph program as the glyphs load again
+	let _ = font_file.set_hinting(Some(12));
+});
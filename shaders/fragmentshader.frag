@@ -7,10 +7,16 @@ in vec2 relativePos;
 uniform vec2 u_resolution;
 uniform float u_scale;
 
+// segment kinds, must match the SEGMENT_* constants in utils.rs
+const int SEGMENT_NONE = 0;
+const int SEGMENT_LINE = 1;
+const int SEGMENT_QUADRATIC = 2;
+const int SEGMENT_CUBIC = 3;
+
 struct Point {
     int x;
     int y;
-    int flag;
+    int segment;
 };
 
 struct Glyph {
@@ -41,15 +47,63 @@ bool is_inbound(float x, float y, float xmin, float ymin, float xmax, float ymax
     return x >= xmin && x <= xmax && y >= ymin && y <= ymax;
 }
 
-bool bit_set(int value, int bit) {
-    return (value & (1 << bit)) != 0;
-}
-
 float evaluate_bezier_x(float t, float x0, float x1, float x2) {
     float mt = 1.0 - t;
     return mt * mt * x0 + 2.0 * mt * t * x1 + t * t * x2;
 }
 
+float evaluate_cubic_x(float t, float x0, float x1, float x2, float x3) {
+    float mt = 1.0 - t;
+    return mt * mt * mt * x0 + 3.0 * mt * mt * t * x1 + 3.0 * mt * t * t * x2 + t * t * t * x3;
+}
+
+float cube_root(float value) {
+    return sign(value) * pow(abs(value), 1.0 / 3.0);
+}
+
+// real roots of a*t^3 + b*t^2 + c*t + d = 0, returns how many were written to roots
+int solve_cubic(float a, float b, float c, float d, out float roots[3]) {
+    if (abs(a) < 0.0001) {
+        if (abs(b) < 0.0001) {
+            if (abs(c) < 0.0001) return 0;
+            roots[0] = -d / c;
+            return 1;
+        }
+        float discriminant = c * c - 4.0 * b * d;
+        if (discriminant < 0.0) return 0;
+        roots[0] = (-c + sqrt(discriminant)) / (2.0 * b);
+        roots[1] = (-c - sqrt(discriminant)) / (2.0 * b);
+        return 2;
+    }
+
+    // substitute t = u - b / 3a to get the depressed cubic u^3 + p*u + q = 0
+    float b_a = b / a;
+    float c_a = c / a;
+    float d_a = d / a;
+    float p = c_a - b_a * b_a / 3.0;
+    float q = 2.0 * b_a * b_a * b_a / 27.0 - b_a * c_a / 3.0 + d_a;
+    float shift = -b_a / 3.0;
+    float discriminant = q * q / 4.0 + p * p * p / 27.0;
+
+    if (discriminant > 0.0) {
+        float root = sqrt(discriminant);
+        roots[0] = cube_root(-q / 2.0 + root) + cube_root(-q / 2.0 - root) + shift;
+        return 1;
+    }
+    if (abs(p) < 0.0001) {
+        roots[0] = shift;
+        return 1;
+    }
+
+    // three real roots, use the trigonometric form
+    float m = 2.0 * sqrt(-p / 3.0);
+    float theta = acos(clamp(3.0 * q / (p * m), -1.0, 1.0)) / 3.0;
+    for (int k = 0; k < 3; k++) {
+        roots[k] = m * cos(theta - 2.0 * 3.14159265 * float(k) / 3.0) + shift;
+    }
+    return 3;
+}
+
 void main() {
     Glyph g = glyphs[vInstanceID];
     vec2 position = vec2(relativePos.x/u_scale, relativePos.y/u_scale);
@@ -70,26 +124,25 @@ void main() {
         if (startIdx >= endIdx) continue;
         
         for (uint j = startIdx; j <= endIdx; j++) {
-            uint currIdx = j;
+            // each segment starts at an on-curve point, control points carry SEGMENT_NONE
             uint nextIdx = (j == endIdx) ? startIdx : j + 1;
-            
-            Point p1 = points[pointBase + currIdx];
-            Point p2 = points[pointBase + nextIdx];
-            
-            if (bit_set(p1.flag, 0) && bit_set(p2.flag, 0)) {
-                if (((p1.y <= position.y && p2.y > position.y) || 
-                     (p1.y > position.y && p2.y <= position.y)) &&
-                    (position.x < (p2.x - p1.x) * (position.y - p1.y) / float(p2.y - p1.y) + p1.x)) {
+            uint secondIdx = (nextIdx == endIdx) ? startIdx : nextIdx + 1;
+            uint thirdIdx = (secondIdx == endIdx) ? startIdx : secondIdx + 1;
+
+            Point p0 = points[pointBase + j];
+            Point p1 = points[pointBase + nextIdx];
+
+            if (p0.segment == SEGMENT_LINE) {
+                if (((p0.y <= position.y && p1.y > position.y) || 
+                     (p0.y > position.y && p1.y <= position.y)) &&
+                    (position.x < (p1.x - p0.x) * (position.y - p0.y) / float(p1.y - p0.y) + p0.x)) {
                     winding_number++;
                 }
                 continue;
             }
             
-            if (currIdx != endIdx && !bit_set(p1.flag, 0) && bit_set(p2.flag, 0)) {
-                uint prevIdx = (currIdx == startIdx) ? endIdx : currIdx - 1;
-                Point p0 = points[pointBase + prevIdx];
-                
-                if (!bit_set(p0.flag, 0)) continue;
+            if (p0.segment == SEGMENT_QUADRATIC) {
+                Point p2 = points[pointBase + secondIdx];
                 
                 float y0 = float(p0.y);
                 float y1 = float(p1.y);
@@ -138,6 +191,37 @@ void main() {
                         if (position.x < bezier_x) winding_number++;
                     }
                 }
+                continue;
+            }
+
+            if (p0.segment == SEGMENT_CUBIC) {
+                Point p2 = points[pointBase + secondIdx];
+                Point p3 = points[pointBase + thirdIdx];
+
+                float y0 = float(p0.y);
+                float y1 = float(p1.y);
+                float y2 = float(p2.y);
+                float y3 = float(p3.y);
+
+                if ((y0 < position.y && y1 < position.y && y2 < position.y && y3 < position.y) || 
+                    (y0 > position.y && y1 > position.y && y2 > position.y && y3 > position.y)) {
+                    continue;
+                }
+
+                // Solve for t where cubic(t).y = position.y, end points count once like the line case
+                float roots[3];
+                int root_count = solve_cubic(-y0 + 3.0 * y1 - 3.0 * y2 + y3,
+                                             3.0 * y0 - 6.0 * y1 + 3.0 * y2,
+                                             3.0 * (y1 - y0),
+                                             y0 - position.y,
+                                             roots);
+                for (int k = 0; k < root_count; k++) {
+                    float t = roots[k];
+                    if (t >= 0.0 && t < 1.0) {
+                        float bezier_x = evaluate_cubic_x(t, float(p0.x), float(p1.x), float(p2.x), float(p3.x));
+                        if (position.x < bezier_x) winding_number++;
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,1074 @@
+use std::collections::HashMap;
+
+use crate::font_error::FontError;
+use crate::font_loader::{GlyphDescription, Point};
+use crate::reader::Reader;
+use crate::variations::ItemVariationStore;
+
+// top and font dict operators, two byte operators are stored as 1200 + second byte
+const CHARSET: u16 = 15;
+const CHAR_STRINGS: u16 = 17;
+const PRIVATE: u16 = 18;
+const SUBRS: u16 = 19;
+const VSINDEX: u16 = 22;
+const VARIATION_STORE: u16 = 24;
+const CHARSTRING_TYPE: u16 = 1206;
+const ROS: u16 = 1230;
+const FD_ARRAY: u16 = 1236;
+const FD_SELECT: u16 = 1237;
+
+const CFF_MAX_STACK_SIZE: usize = 48;
+const CFF2_MAX_STACK_SIZE: usize = 513;
+const MAX_SUBR_DEPTH: usize = 10;
+// put and get address this many slots
+const TRANSIENT_ARRAY_SIZE: usize = 32;
+
+// point flag marking a cubic control point, glyf outlines never set it
+pub const CUBIC_CONTROL_FLAG: u8 = 0x80;
+
+// StandardEncoding codes past 126 in string id order, the first of them is string id 96
+const STANDARD_ENCODING_UPPER: [u8; 54] = [
+	161, 162, 163, 164, 165, 166, 167, 168, 169, 170, 171, 172, 173, 174, 175,
+	177, 178, 179, 180, 182, 183, 184, 185, 186, 187, 188, 189, 191,
+	193, 194, 195, 196, 197, 198, 199, 200, 202, 203, 205, 206, 207, 208,
+	225, 227, 232, 233, 234, 235, 241, 245, 248, 249, 250, 251,
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct Index<'a> {
+	offsets: Vec<usize>,
+	data: &'a [u8],
+}
+
+impl<'a> Index<'a> {
+	pub fn parse(reader: &mut Reader<'a>) -> Result<Index<'a>, FontError> {
+		let count = reader.read_u16()? as usize;
+		Index::parse_with_count(reader, count)
+	}
+
+	// CFF2 widens the count to 32 bits, the rest of the structure is the same
+	pub fn parse_cff2(reader: &mut Reader<'a>) -> Result<Index<'a>, FontError> {
+		let count = reader.read_u32()? as usize;
+		Index::parse_with_count(reader, count)
+	}
+
+	fn parse_with_count(reader: &mut Reader<'a>, count: usize) -> Result<Index<'a>, FontError> {
+		if count == 0 {
+			return Ok(Index::default());
+		}
+		let off_size = reader.read_u8()? as usize;
+		if !(1..=4).contains(&off_size) {
+			return Err(FontError::Malformed("invalid CFF INDEX offset size"));
+		}
+
+		let mut offsets: Vec<usize> = vec![];
+		for _ in 0..=count {
+			let offset = reader.read_bytes(off_size)?.iter().fold(0, |offset, &byte| (offset << 8) | byte as usize);
+			offsets.push(offset);
+		}
+		// offsets count from 1, relative to the byte before the object data
+		let data_len = offsets[count].checked_sub(1).ok_or(FontError::Malformed("invalid CFF INDEX offset"))?;
+		let data = reader.read_bytes(data_len)?;
+		Ok(Index { offsets, data })
+	}
+
+	pub fn len(&self) -> usize {
+		self.offsets.len().saturating_sub(1)
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	pub fn get(&self, index: usize) -> Result<&'a [u8], FontError> {
+		match (self.offsets.get(index), self.offsets.get(index + 1)) {
+			(Some(&start), Some(&end)) if start >= 1 && start <= end => {
+				self.data.get(start - 1..end - 1).ok_or(FontError::OutOfBounds { offset: start - 1, len: end - start })
+			}
+			_ => Err(FontError::Malformed("CFF INDEX entry out of range")),
+		}
+	}
+}
+
+pub fn parse_dict(data: &[u8]) -> Result<HashMap<u16, Vec<f64>>, FontError> {
+	let mut reader = Reader::new(data);
+	let mut dict: HashMap<u16, Vec<f64>> = HashMap::new();
+	let mut operands: Vec<f64> = vec![];
+
+	while reader.remaining() > 0 {
+		let b0 = reader.read_u8()?;
+		match b0 {
+			0..=27 => {
+				let operator = if b0 == 12 {1200 + reader.read_u8()? as u16} else {b0 as u16};
+				dict.insert(operator, std::mem::take(&mut operands));
+			}
+			28 => operands.push(reader.read_i16()? as f64),
+			29 => operands.push(reader.read_i32()? as f64),
+			30 => operands.push(read_real(&mut reader)?),
+			32..=246 => operands.push(b0 as f64 - 139.0),
+			247..=250 => operands.push((b0 as f64 - 247.0) * 256.0 + reader.read_u8()? as f64 + 108.0),
+			251..=254 => operands.push(-(b0 as f64 - 251.0) * 256.0 - reader.read_u8()? as f64 - 108.0),
+			_ => return Err(FontError::Malformed("invalid CFF DICT operand")),
+		}
+	}
+	Ok(dict)
+}
+
+// reals are packed as nibbles: digits, '.', 'E', 'E-', '-' and an end marker
+fn read_real(reader: &mut Reader) -> Result<f64, FontError> {
+	let mut text = String::new();
+	loop {
+		let byte = reader.read_u8()?;
+		for nibble in [byte >> 4, byte & 0x0F] {
+			match nibble {
+				0..=9 => text.push((b'0' + nibble) as char),
+				0xA => text.push('.'),
+				0xB => text.push('E'),
+				0xC => text.push_str("E-"),
+				0xE => text.push('-'),
+				0xF => return text.parse().map_err(|_| FontError::Malformed("invalid CFF real number")),
+				_ => return Err(FontError::Malformed("invalid CFF real number")),
+			}
+		}
+	}
+}
+
+fn dict_offset(dict: &HashMap<u16, Vec<f64>>, operator: u16) -> Option<usize> {
+	dict.get(&operator).and_then(|operands| operands.first()).map(|&offset| offset as usize)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FontDict<'a> {
+	pub local_subrs: Index<'a>,
+	pub vsindex: usize,
+}
+
+// the private dict is found through [size, offset], its Subrs offset is relative to the private dict itself
+fn get_font_dict<'a>(cff: &Reader<'a>, font_dict: &HashMap<u16, Vec<f64>>, is_cff2: bool) -> Result<FontDict<'a>, FontError> {
+	let Some(&[size, offset]) = font_dict.get(&PRIVATE).map(|operands| operands.as_slice()) else {
+		return Ok(FontDict::default());
+	};
+	let (size, offset) = (size as usize, offset as usize);
+	let private_dict = parse_dict(cff.sub_reader(offset, size)?.data())?;
+	let local_subrs = match dict_offset(&private_dict, SUBRS) {
+		Some(subrs_offset) if is_cff2 => Index::parse_cff2(&mut cff.at(offset + subrs_offset)?)?,
+		Some(subrs_offset) => Index::parse(&mut cff.at(offset + subrs_offset)?)?,
+		None => Index::default(),
+	};
+	Ok(FontDict {
+		local_subrs,
+		vsindex: dict_offset(&private_dict, VSINDEX).unwrap_or(0),
+	})
+}
+
+pub struct CffFont<'a> {
+	pub global_subrs: Index<'a>,
+	pub char_strings: Index<'a>,
+	pub font_dicts: Vec<FontDict<'a>>,
+	pub fd_select: Vec<u16>,
+	// the string id of each glyph, only kept for name-keyed CFF where seac looks glyphs up by name
+	pub charset: Vec<u16>,
+	pub blend_scalars: Vec<Vec<f32>>,
+	pub is_cff2: bool,
+}
+
+impl<'a> CffFont<'a> {
+	// coordinates are the normalized axis coordinates CFF2 outlines are evaluated at, CFF ignores them
+	pub fn new(cff: Reader<'a>, coordinates: &[f32]) -> Result<CffFont<'a>, FontError> {
+		let mut reader = cff;
+		let major_version = reader.read_u8()?;
+		if major_version == 2 {
+			return CffFont::new_cff2(cff, coordinates);
+		}
+		reader.skip(1)?; //skip minor
+		let header_size = reader.read_u8()?;
+		reader.seek(header_size as usize)?;
+
+		let _name_index = Index::parse(&mut reader)?;
+		let top_dict_index = Index::parse(&mut reader)?;
+		let _string_index = Index::parse(&mut reader)?;
+		let global_subrs = Index::parse(&mut reader)?;
+
+		// only the first font of a font set is used, opentype fonts never carry more than one
+		let top_dict = parse_dict(top_dict_index.get(0)?)?;
+		let charstring_type = dict_offset(&top_dict, CHARSTRING_TYPE).unwrap_or(2);
+		if charstring_type != 2 {
+			return Err(FontError::UnsupportedFormat { table: "CFF ", format: charstring_type as u32 });
+		}
+
+		let char_strings_offset = dict_offset(&top_dict, CHAR_STRINGS).ok_or(FontError::Malformed("CFF font has no CharStrings"))?;
+		let char_strings = Index::parse(&mut cff.at(char_strings_offset)?)?;
+
+		// cid-keyed fonts pick a font dict, and with it a set of local subrs, per glyph
+		let (font_dicts, fd_select) = if top_dict.contains_key(&ROS) {
+			let fd_array_offset = dict_offset(&top_dict, FD_ARRAY).ok_or(FontError::Malformed("CID-keyed CFF font has no FDArray"))?;
+			let fd_select_offset = dict_offset(&top_dict, FD_SELECT).ok_or(FontError::Malformed("CID-keyed CFF font has no FDSelect"))?;
+			let fd_array = Index::parse(&mut cff.at(fd_array_offset)?)?;
+			let mut font_dicts: Vec<FontDict> = vec![];
+			for i in 0..fd_array.len() {
+				font_dicts.push(get_font_dict(&cff, &parse_dict(fd_array.get(i)?)?, false)?);
+			}
+			(font_dicts, parse_fd_select(cff.at(fd_select_offset)?, char_strings.len())?)
+		} else {
+			(vec![get_font_dict(&cff, &top_dict, false)?], vec![0; char_strings.len()])
+		};
+
+		// offsets 0 to 2 name the predefined charsets, the expert ones hold none of the StandardEncoding glyphs
+		// seac composes from, and a broken charset only costs seac glyphs their parts
+		let charset = match dict_offset(&top_dict, CHARSET).unwrap_or(0) {
+			_ if top_dict.contains_key(&ROS) => vec![],
+			0 => (0..char_strings.len().min(229) as u16).collect(),
+			1 | 2 => vec![],
+			charset_offset => cff.at(charset_offset).and_then(|reader| parse_charset(reader, char_strings.len())).unwrap_or_default(),
+		};
+
+		Ok(CffFont {
+			global_subrs,
+			char_strings,
+			font_dicts,
+			fd_select,
+			charset,
+			blend_scalars: vec![],
+			is_cff2: false,
+		})
+	}
+
+	fn new_cff2(cff: Reader<'a>, coordinates: &[f32]) -> Result<CffFont<'a>, FontError> {
+		let mut reader = cff;
+		reader.skip(2)?; //skip major, minor
+		let header_size = reader.read_u8()?;
+		let top_dict_length = reader.read_u16()?;
+
+		// no name or string index, the top dict is stored directly after the header
+		let top_dict = parse_dict(cff.sub_reader(header_size as usize, top_dict_length as usize)?.data())?;
+		let global_subrs = Index::parse_cff2(&mut cff.at(header_size as usize + top_dict_length as usize)?)?;
+
+		let char_strings_offset = dict_offset(&top_dict, CHAR_STRINGS).ok_or(FontError::Malformed("CFF2 font has no CharStrings"))?;
+		let char_strings = Index::parse_cff2(&mut cff.at(char_strings_offset)?)?;
+
+		let fd_array_offset = dict_offset(&top_dict, FD_ARRAY).ok_or(FontError::Malformed("CFF2 font has no FDArray"))?;
+		let fd_array = Index::parse_cff2(&mut cff.at(fd_array_offset)?)?;
+		let mut font_dicts: Vec<FontDict> = vec![];
+		for i in 0..fd_array.len() {
+			font_dicts.push(get_font_dict(&cff, &parse_dict(fd_array.get(i)?)?, true)?);
+		}
+		// FDSelect is optional when there is only a single font dict
+		let fd_select = match dict_offset(&top_dict, FD_SELECT) {
+			Some(fd_select_offset) => parse_fd_select(cff.at(fd_select_offset)?, char_strings.len())?,
+			None => vec![0; char_strings.len()],
+		};
+
+		// the variation store is prefixed with its length, blend scalars only depend on the coordinates so
+		// they're worked out once per item variation data instead of per blend
+		let blend_scalars = match dict_offset(&top_dict, VARIATION_STORE) {
+			Some(variation_store_offset) => {
+				let store = ItemVariationStore::parse(cff.slice_from(variation_store_offset)?.slice_from(2)?)?;
+				let mut blend_scalars: Vec<Vec<f32>> = vec![];
+				for i in 0..store.region_indexes.len() {
+					blend_scalars.push(store.region_scalars(i, coordinates)?);
+				}
+				blend_scalars
+			}
+			None => vec![],
+		};
+
+		Ok(CffFont {
+			global_subrs,
+			char_strings,
+			font_dicts,
+			fd_select,
+			charset: vec![],
+			blend_scalars,
+			is_cff2: true,
+		})
+	}
+
+	pub fn get_glyph_description(&self, glyph_index: u16) -> Result<GlyphDescription, FontError> {
+		self.get_outline(glyph_index, true)
+	}
+
+	// the parts of a seac glyph can't be seac glyphs themselves, compose_seac reads them without it
+	fn get_outline(&self, glyph_index: u16, allow_seac: bool) -> Result<GlyphDescription, FontError> {
+		let char_string = self.char_strings.get(glyph_index as usize)?;
+		let fd_index = *self.fd_select.get(glyph_index as usize).ok_or(FontError::Malformed("glyph has no entry in FDSelect"))?;
+		let font_dict = self.font_dicts.get(fd_index as usize).ok_or(FontError::Malformed("FDSelect refers to a missing font dict"))?;
+
+		let mut interpreter = CharStringInterpreter {
+			global_subrs: &self.global_subrs,
+			local_subrs: &font_dict.local_subrs,
+			blend_scalars: &self.blend_scalars,
+			vsindex: font_dict.vsindex,
+			max_stack_size: if self.is_cff2 {CFF2_MAX_STACK_SIZE} else {CFF_MAX_STACK_SIZE},
+			stack: vec![],
+			x: 0.0,
+			y: 0.0,
+			coordinates: vec![],
+			end_pts_of_contours: vec![],
+			contour_start: 0,
+			stem_count: 0,
+			// CFF2 charstrings never carry a width
+			width_parsed: self.is_cff2,
+			finished: false,
+			seac: None,
+			transient: [0.0; TRANSIENT_ARRAY_SIZE],
+			// seeded by the glyph so random gives the same outline every time the glyph loads
+			random_state: glyph_index as u32 + 1,
+		};
+		interpreter.run(char_string, 0)?;
+		interpreter.close_contour();
+		match interpreter.seac {
+			Some(seac) if allow_seac => self.compose_seac(seac),
+			_ => outline_glyph(interpreter.coordinates, interpreter.end_pts_of_contours),
+		}
+	}
+
+	// the accent is drawn over the base glyph, moved by adx and ady
+	fn compose_seac(&self, [adx, ady, base_code, accent_code]: [f32; 4]) -> Result<GlyphDescription, FontError> {
+		let base = self.get_outline(self.standard_encoding_glyph(base_code)?, false)?;
+		let accent = self.get_outline(self.standard_encoding_glyph(accent_code)?, false)?;
+		if base.coordinates.len() + accent.coordinates.len() > u16::MAX as usize {
+			return Err(FontError::Malformed("too many points in glyph"));
+		}
+
+		let point_offset = base.coordinates.len() as u16;
+		let mut coordinates = base.coordinates;
+		let mut end_pts_of_contours = base.end_pts_of_contours;
+		coordinates.extend(accent.coordinates.iter().map(|point| Point { x: point.x + adx, y: point.y + ady, flags: point.flags }));
+		end_pts_of_contours.extend(accent.end_pts_of_contours.iter().map(|&end| end + point_offset));
+		outline_glyph(coordinates, end_pts_of_contours)
+	}
+
+	// seac names its parts by StandardEncoding code, codes 32 to 126 are string ids 1 to 95
+	fn standard_encoding_glyph(&self, code: f32) -> Result<u16, FontError> {
+		let sid = match code as i32 {
+			code @ 32..=126 => Some(code as u16 - 31),
+			code => STANDARD_ENCODING_UPPER.iter().position(|&upper| upper as i32 == code).map(|i| 96 + i as u16),
+		};
+		sid.and_then(|sid| self.charset.iter().position(|&glyph_sid| glyph_sid == sid))
+			.map(|glyph_index| glyph_index as u16)
+			.ok_or(FontError::Malformed("seac refers to a glyph the font doesn't have"))
+	}
+}
+
+// glyph 0 is always .notdef, the charset lists the string ids from glyph 1 on
+fn parse_charset(mut reader: Reader, num_glyphs: usize) -> Result<Vec<u16>, FontError> {
+	let format = reader.read_u8()?;
+	let mut charset: Vec<u16> = vec![0];
+	while charset.len() < num_glyphs {
+		match format {
+			0 => charset.push(reader.read_u16()?),
+			// ranges of consecutive string ids, the count excludes the first one
+			1 | 2 => {
+				let first = reader.read_u16()? as u32;
+				let left = if format == 1 {reader.read_u8()? as u32} else {reader.read_u16()? as u32};
+				let remaining = num_glyphs - charset.len();
+				charset.extend((first..=first + left).take(remaining).map(|sid| sid as u16));
+			}
+			_ => return Err(FontError::UnsupportedFormat { table: "CFF charset", format: format as u32 }),
+		}
+	}
+	Ok(charset)
+}
+
+fn parse_fd_select(mut reader: Reader, num_glyphs: usize) -> Result<Vec<u16>, FontError> {
+	let format = reader.read_u8()?;
+	match format {
+		0 => Ok(reader.read_bytes(num_glyphs)?.iter().map(|&fd| fd as u16).collect()),
+		// format 4 is the CFF2 variant of format 3 with wider fields
+		3 | 4 => {
+			let num_ranges = if format == 3 {reader.read_u16()? as u32} else {reader.read_u32()?};
+			let mut fd_select: Vec<u16> = vec![0; num_glyphs];
+			let mut first = if format == 3 {reader.read_u16()? as usize} else {reader.read_offset32()?};
+			for _ in 0..num_ranges {
+				let fd = if format == 3 {reader.read_u8()? as u16} else {reader.read_u16()?};
+				// each range runs up to the first glyph of the next one, the last is closed by a sentinel
+				let next = if format == 3 {reader.read_u16()? as usize} else {reader.read_offset32()?};
+				if next < first {
+					return Err(FontError::Malformed("FDSelect ranges are not in order"));
+				}
+				for glyph in fd_select.iter_mut().take(next).skip(first) {
+					*glyph = fd;
+				}
+				first = next;
+			}
+			Ok(fd_select)
+		}
+		_ => Err(FontError::UnsupportedFormat { table: "FDSelect", format: format as u32 }),
+	}
+}
+
+fn subr_bias(subrs: &Index) -> i32 {
+	match subrs.len() {
+		0..=1239 => 107,
+		1240..=33899 => 1131,
+		_ => 32768,
+	}
+}
+
+struct CharStringInterpreter<'a, 'b> {
+	global_subrs: &'b Index<'a>,
+	local_subrs: &'b Index<'a>,
+	blend_scalars: &'b [Vec<f32>],
+	vsindex: usize,
+	max_stack_size: usize,
+	stack: Vec<f32>,
+	x: f32,
+	y: f32,
+	coordinates: Vec<Point>,
+	end_pts_of_contours: Vec<u16>,
+	contour_start: usize,
+	stem_count: usize,
+	width_parsed: bool,
+	finished: bool,
+	// adx, ady and the StandardEncoding codes of the base and accent from endchar's seac form
+	seac: Option<[f32; 4]>,
+	transient: [f32; TRANSIENT_ARRAY_SIZE],
+	random_state: u32,
+}
+
+impl CharStringInterpreter<'_, '_> {
+	fn run(&mut self, char_string: &[u8], depth: usize) -> Result<(), FontError> {
+		if depth > MAX_SUBR_DEPTH {
+			return Err(FontError::Malformed("charstring subroutines nest too deep"));
+		}
+		let mut reader = Reader::new(char_string);
+
+		while reader.remaining() > 0 && !self.finished {
+			let b0 = reader.read_u8()?;
+			match b0 {
+				// hstem, vstem, hstemhm, vstemhm
+				1 | 3 | 18 | 23 => {
+					self.parse_width(self.stack.len() % 2 == 1);
+					self.stem_count += self.stack.len() / 2;
+					self.stack.clear();
+				}
+				// hintmask, cntrmask: leftover operands are an implied vstem
+				19 | 20 => {
+					self.parse_width(self.stack.len() % 2 == 1);
+					self.stem_count += self.stack.len() / 2;
+					self.stack.clear();
+					reader.skip(self.stem_count.div_ceil(8))?;
+				}
+				// rmoveto
+				21 => {
+					self.parse_width(self.stack.len() > 2);
+					let [dx, dy] = self.arguments()?;
+					self.move_to(self.x + dx, self.y + dy);
+				}
+				// hmoveto
+				22 => {
+					self.parse_width(self.stack.len() > 1);
+					let [dx] = self.arguments()?;
+					self.move_to(self.x + dx, self.y);
+				}
+				// vmoveto
+				4 => {
+					self.parse_width(self.stack.len() > 1);
+					let [dy] = self.arguments()?;
+					self.move_to(self.x, self.y + dy);
+				}
+				// rlineto
+				5 => {
+					for pair in self.take_stack().chunks_exact(2) {
+						self.line_to(self.x + pair[0], self.y + pair[1]);
+					}
+				}
+				// hlineto, vlineto: alternate horizontal and vertical lines
+				6 | 7 => {
+					let mut horizontal = b0 == 6;
+					for delta in self.take_stack() {
+						if horizontal {
+							self.line_to(self.x + delta, self.y);
+						} else {
+							self.line_to(self.x, self.y + delta);
+						}
+						horizontal = !horizontal;
+					}
+				}
+				// rrcurveto
+				8 => {
+					for curve in self.take_stack().chunks_exact(6) {
+						self.relative_curve_to(curve);
+					}
+				}
+				// rcurveline
+				24 => {
+					let stack = self.take_stack();
+					if stack.len() < 8 {
+						return Err(FontError::Malformed("rcurveline needs at least 8 operands"));
+					}
+					let (curves, line) = stack.split_at(stack.len() - 2);
+					for curve in curves.chunks_exact(6) {
+						self.relative_curve_to(curve);
+					}
+					self.line_to(self.x + line[0], self.y + line[1]);
+				}
+				// rlinecurve
+				25 => {
+					let stack = self.take_stack();
+					if stack.len() < 8 {
+						return Err(FontError::Malformed("rlinecurve needs at least 8 operands"));
+					}
+					let (lines, curve) = stack.split_at(stack.len() - 6);
+					for line in lines.chunks_exact(2) {
+						self.line_to(self.x + line[0], self.y + line[1]);
+					}
+					self.relative_curve_to(curve);
+				}
+				// vvcurveto: an odd operand count starts with dx1
+				26 => {
+					let stack = self.take_stack();
+					let (mut dx1, curves) = if stack.len() % 2 == 1 {(stack[0], &stack[1..])} else {(0.0, &stack[..])};
+					for curve in curves.chunks_exact(4) {
+						self.relative_curve_to(&[dx1, curve[0], curve[1], curve[2], 0.0, curve[3]]);
+						dx1 = 0.0;
+					}
+				}
+				// hhcurveto: an odd operand count starts with dy1
+				27 => {
+					let stack = self.take_stack();
+					let (mut dy1, curves) = if stack.len() % 2 == 1 {(stack[0], &stack[1..])} else {(0.0, &stack[..])};
+					for curve in curves.chunks_exact(4) {
+						self.relative_curve_to(&[curve[0], dy1, curve[1], curve[2], curve[3], 0.0]);
+						dy1 = 0.0;
+					}
+				}
+				// vhcurveto, hvcurveto: curves alternate between starting vertical and horizontal,
+				// the final curve may carry one extra operand for its last delta
+				30 | 31 => {
+					let stack = self.take_stack();
+					let mut horizontal = b0 == 31;
+					let mut i = 0;
+					while i + 4 <= stack.len() {
+						let last = if stack.len() - i == 5 {stack[i + 4]} else {0.0};
+						let curve = &stack[i..i + 4];
+						if horizontal {
+							self.relative_curve_to(&[curve[0], 0.0, curve[1], curve[2], last, curve[3]]);
+						} else {
+							self.relative_curve_to(&[0.0, curve[0], curve[1], curve[2], curve[3], last]);
+						}
+						horizontal = !horizontal;
+						i += 4;
+					}
+				}
+				// callsubr, callgsubr
+				10 | 29 => {
+					let subrs = if b0 == 10 {self.local_subrs} else {self.global_subrs};
+					let index = self.stack.pop().ok_or(FontError::Malformed("subroutine call without an index"))? as i32 + subr_bias(subrs);
+					let subr = subrs.get(usize::try_from(index).map_err(|_| FontError::Malformed("subroutine index out of range"))?)?;
+					self.run(subr, depth + 1)?;
+				}
+				// return
+				11 => return Ok(()),
+				// vsindex: which item variation data the following blends use
+				15 => {
+					let [vsindex] = self.arguments()?;
+					self.vsindex = vsindex as usize;
+				}
+				16 => self.blend()?,
+				// endchar, with four operands it's the deprecated seac that builds an accented glyph from two others
+				14 => {
+					self.parse_width(self.stack.len() == 1 || self.stack.len() == 5);
+					self.seac = self.take_stack().try_into().ok();
+					self.finished = true;
+				}
+				12 => {
+					let b1 = reader.read_u8()?;
+					self.run_escape(b1)?;
+				}
+				28 => self.push(reader.read_i16()? as f32)?,
+				32..=246 => self.push(b0 as f32 - 139.0)?,
+				247..=250 => self.push((b0 as f32 - 247.0) * 256.0 + reader.read_u8()? as f32 + 108.0)?,
+				251..=254 => self.push(-(b0 as f32 - 251.0) * 256.0 - reader.read_u8()? as f32 - 108.0)?,
+				255 => self.push(reader.read_fixed()?)?,
+				_ => return Err(FontError::Malformed("unsupported charstring operator")),
+			}
+		}
+		Ok(())
+	}
+
+	// the two byte operators, flex and the arithmetic and storage ones that work on the stack in place
+	fn run_escape(&mut self, operator: u8) -> Result<(), FontError> {
+		let (start_x, start_y) = (self.x, self.y);
+		match operator {
+			// dotsection, deprecated and ignored
+			0 => self.stack.clear(),
+			// and, or
+			3 | 4 => {
+				let (a, b) = (self.pop()?, self.pop()?);
+				let result = if operator == 3 {a != 0.0 && b != 0.0} else {a != 0.0 || b != 0.0};
+				self.push(result as u8 as f32)?;
+			}
+			// not
+			5 => {
+				let value = self.pop()?;
+				self.push((value == 0.0) as u8 as f32)?;
+			}
+			// abs, neg, sqrt
+			9 | 14 | 26 => {
+				let value = self.pop()?;
+				self.push(match operator {
+					9 => value.abs(),
+					14 => -value,
+					_ => value.abs().sqrt(),
+				})?;
+			}
+			// add, sub, div, eq, mul
+			10 | 11 | 12 | 15 | 24 => {
+				let b = self.pop()?;
+				let a = self.pop()?;
+				self.push(match operator {
+					10 => a + b,
+					11 => a - b,
+					12 if b == 0.0 => 0.0,
+					12 => a / b,
+					24 => a * b,
+					_ => (a == b) as u8 as f32,
+				})?;
+			}
+			// drop
+			18 => {
+				self.pop()?;
+			}
+			// put, get: the transient array, slots past its end are ignored and read as 0
+			20 => {
+				let index = self.pop()?;
+				let value = self.pop()?;
+				if let Some(slot) = self.transient_slot(index) {
+					self.transient[slot] = value;
+				}
+			}
+			21 => {
+				let index = self.pop()?;
+				let value = self.transient_slot(index).map_or(0.0, |slot| self.transient[slot]);
+				self.push(value)?;
+			}
+			// ifelse: s1 when v1 <= v2, s2 otherwise
+			22 => {
+				let v2 = self.pop()?;
+				let v1 = self.pop()?;
+				let s2 = self.pop()?;
+				let s1 = self.pop()?;
+				self.push(if v1 <= v2 {s1} else {s2})?;
+			}
+			// random: in (0, 1]
+			23 => {
+				self.random_state ^= self.random_state << 13;
+				self.random_state ^= self.random_state >> 17;
+				self.random_state ^= self.random_state << 5;
+				self.push(((self.random_state >> 16) as f32 + 1.0) / 65536.0)?;
+			}
+			// dup, exch
+			27 => {
+				let value = *self.stack.last().ok_or(FontError::Malformed("charstring argument stack underflow"))?;
+				self.push(value)?;
+			}
+			28 => {
+				let b = self.pop()?;
+				let a = self.pop()?;
+				self.stack.extend([b, a]);
+			}
+			// index: copies the element i below the top, a negative i copies the top
+			29 => {
+				let i = self.pop()?.max(0.0) as usize;
+				let position = self.stack.len().checked_sub(i + 1).ok_or(FontError::Malformed("charstring index past the stack"))?;
+				self.push(self.stack[position])?;
+			}
+			// roll: the top n elements move j places towards the top, wrapping around
+			30 => {
+				let j = self.pop()? as i64;
+				let n = self.pop()?;
+				if n < 0.0 || n as usize > self.stack.len() {
+					return Err(FontError::Malformed("charstring roll past the stack"));
+				}
+				let n = n as usize;
+				if n > 0 {
+					let start = self.stack.len() - n;
+					self.stack[start..].rotate_right(j.rem_euclid(n as i64) as usize);
+				}
+			}
+			// hflex
+			34 => {
+				let [dx1, dx2, dy2, dx3, dx4, dx5, dx6] = self.arguments()?;
+				self.relative_curve_to(&[dx1, 0.0, dx2, dy2, dx3, 0.0]);
+				self.relative_curve_to(&[dx4, 0.0, dx5, start_y - self.y, dx6, 0.0]);
+			}
+			// flex
+			35 => {
+				let [dx1, dy1, dx2, dy2, dx3, dy3, dx4, dy4, dx5, dy5, dx6, dy6, _flex_depth] = self.arguments()?;
+				self.relative_curve_to(&[dx1, dy1, dx2, dy2, dx3, dy3]);
+				self.relative_curve_to(&[dx4, dy4, dx5, dy5, dx6, dy6]);
+			}
+			// hflex1
+			36 => {
+				let [dx1, dy1, dx2, dy2, dx3, dx4, dx5, dy5, dx6] = self.arguments()?;
+				self.relative_curve_to(&[dx1, dy1, dx2, dy2, dx3, 0.0]);
+				let end_dy = start_y - (self.y + dy5);
+				self.relative_curve_to(&[dx4, 0.0, dx5, dy5, dx6, end_dy]);
+			}
+			// flex1: the last point only moves along the dominant axis of the whole flex
+			37 => {
+				let [dx1, dy1, dx2, dy2, dx3, dy3, dx4, dy4, dx5, dy5, d6] = self.arguments()?;
+				let dx = dx1 + dx2 + dx3 + dx4 + dx5;
+				let dy = dy1 + dy2 + dy3 + dy4 + dy5;
+				self.relative_curve_to(&[dx1, dy1, dx2, dy2, dx3, dy3]);
+				let (dx6, dy6) = if dx.abs() > dy.abs() {(d6, start_y - (self.y + dy4 + dy5))} else {(start_x - (self.x + dx4 + dx5), d6)};
+				self.relative_curve_to(&[dx4, dy4, dx5, dy5, dx6, dy6]);
+			}
+			_ => return Err(FontError::Malformed("unsupported charstring operator")),
+		}
+		Ok(())
+	}
+
+	fn pop(&mut self) -> Result<f32, FontError> {
+		self.stack.pop().ok_or(FontError::Malformed("charstring argument stack underflow"))
+	}
+
+	fn transient_slot(&self, index: f32) -> Option<usize> {
+		(index >= 0.0 && (index as usize) < TRANSIENT_ARRAY_SIZE).then_some(index as usize)
+	}
+
+	fn push(&mut self, value: f32) -> Result<(), FontError> {
+		if self.stack.len() >= self.max_stack_size {
+			return Err(FontError::Malformed("charstring argument stack overflow"));
+		}
+		self.stack.push(value);
+		Ok(())
+	}
+
+	// blend replaces n default values and the n * regionCount deltas after them with the interpolated values
+	fn blend(&mut self) -> Result<(), FontError> {
+		let scalars = self.blend_scalars.get(self.vsindex).ok_or(FontError::Malformed("vsindex refers to missing variation data"))?;
+		let count = self.stack.pop().ok_or(FontError::Malformed("blend without an operand count"))? as usize;
+		let region_count = scalars.len();
+		let operand_count = count.checked_mul(region_count + 1)
+			.filter(|&operand_count| operand_count <= self.stack.len())
+			.ok_or(FontError::Malformed("blend has too few operands"))?;
+
+		let base = self.stack.len() - operand_count;
+		for i in 0..count {
+			let deltas = &self.stack[base + count + i * region_count..base + count + (i + 1) * region_count];
+			let delta: f32 = deltas.iter().zip(scalars).map(|(delta, scalar)| delta * scalar).sum();
+			self.stack[base + i] += delta;
+		}
+		self.stack.truncate(base + count);
+		Ok(())
+	}
+
+	fn take_stack(&mut self) -> Vec<f32> {
+		std::mem::take(&mut self.stack)
+	}
+
+	fn arguments<const N: usize>(&mut self) -> Result<[f32; N], FontError> {
+		let stack = self.take_stack();
+		stack.try_into().map_err(|_| FontError::Malformed("wrong number of charstring operands"))
+	}
+
+	// the first stack clearing operator may carry the advance width as an extra leading operand,
+	// advances come from hmtx so it is dropped
+	fn parse_width(&mut self, has_width: bool) {
+		if !self.width_parsed && has_width {
+			self.stack.remove(0);
+		}
+		self.width_parsed = true;
+	}
+
+	fn move_to(&mut self, x: f32, y: f32) {
+		self.close_contour();
+		self.x = x;
+		self.y = y;
+		self.coordinates.push(Point { x, y, flags: 1 });
+	}
+
+	fn line_to(&mut self, x: f32, y: f32) {
+		self.start_contour();
+		self.x = x;
+		self.y = y;
+		self.coordinates.push(Point { x, y, flags: 1 });
+	}
+
+	fn relative_curve_to(&mut self, deltas: &[f32]) {
+		self.start_contour();
+		let (x1, y1) = (self.x + deltas[0], self.y + deltas[1]);
+		let (x2, y2) = (x1 + deltas[2], y1 + deltas[3]);
+		self.x = x2 + deltas[4];
+		self.y = y2 + deltas[5];
+		self.coordinates.push(Point { x: x1, y: y1, flags: CUBIC_CONTROL_FLAG });
+		self.coordinates.push(Point { x: x2, y: y2, flags: CUBIC_CONTROL_FLAG });
+		self.coordinates.push(Point { x: self.x, y: self.y, flags: 1 });
+	}
+
+	// drawing without a moveto starts from the current point
+	fn start_contour(&mut self) {
+		if self.coordinates.len() == self.contour_start {
+			self.coordinates.push(Point { x: self.x, y: self.y, flags: 1 });
+		}
+	}
+
+	// contours are closed implicitly, drop the end point when it lands back on the start
+	fn close_contour(&mut self) {
+		let contour = &self.coordinates[self.contour_start..];
+		if contour.len() > 1 {
+			let (first, last) = (contour[0], contour[contour.len() - 1]);
+			if first.x == last.x && first.y == last.y {
+				self.coordinates.pop();
+			}
+		}
+		if self.coordinates.len() - self.contour_start > 1 {
+			self.end_pts_of_contours.push((self.coordinates.len() - 1) as u16);
+		} else {
+			self.coordinates.truncate(self.contour_start);
+		}
+		self.contour_start = self.coordinates.len();
+	}
+
+}
+
+fn outline_glyph(coordinates: Vec<Point>, end_pts_of_contours: Vec<u16>) -> Result<GlyphDescription, FontError> {
+	if coordinates.len() > u16::MAX as usize || end_pts_of_contours.len() > i16::MAX as usize {
+		return Err(FontError::Malformed("too many points in glyph"));
+	}
+	let (xmin, ymin, xmax, ymax) = outline_bounds(&coordinates, &end_pts_of_contours);
+	Ok(GlyphDescription {
+		number_of_contours: end_pts_of_contours.len() as i16,
+		xmin: xmin.floor() as i16,
+		ymin: ymin.floor() as i16,
+		xmax: xmax.ceil() as i16,
+		ymax: ymax.ceil() as i16,
+		num_points: coordinates.len() as u16,
+		end_pts_of_contours,
+		coordinates,
+		..Default::default()
+	})
+}
+
+// cff has no stored bounding box, take the end points plus the extremes of every cubic segment
+fn outline_bounds(coordinates: &[Point], end_pts_of_contours: &[u16]) -> (f32, f32, f32, f32) {
+	if coordinates.is_empty() {
+		return (0.0, 0.0, 0.0, 0.0);
+	}
+	let mut bounds = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+	let mut include = |x: f32, y: f32| {
+		bounds = (bounds.0.min(x), bounds.1.min(y), bounds.2.max(x), bounds.3.max(y));
+	};
+
+	let mut start = 0;
+	for &end in end_pts_of_contours {
+		let contour = &coordinates[start..=end as usize];
+		for (i, point) in contour.iter().enumerate() {
+			if point.flags & CUBIC_CONTROL_FLAG != 0 {
+				continue;
+			}
+			include(point.x, point.y);
+			let next = |offset: usize| contour[(i + offset) % contour.len()];
+			if next(1).flags & CUBIC_CONTROL_FLAG != 0 {
+				let (p1, p2, p3) = (next(1), next(2), next(3));
+				for t in cubic_extrema(point.x, p1.x, p2.x, p3.x).into_iter().chain(cubic_extrema(point.y, p1.y, p2.y, p3.y)).flatten() {
+					include(evaluate_cubic(t, point.x, p1.x, p2.x, p3.x), evaluate_cubic(t, point.y, p1.y, p2.y, p3.y));
+				}
+			}
+		}
+		start = end as usize + 1;
+	}
+	bounds
+}
+
+fn evaluate_cubic(t: f32, p0: f32, p1: f32, p2: f32, p3: f32) -> f32 {
+	let mt = 1.0 - t;
+	mt * mt * mt * p0 + 3.0 * mt * mt * t * p1 + 3.0 * mt * t * t * p2 + t * t * t * p3
+}
+
+// parameters in (0, 1) where the derivative of one coordinate of a cubic is zero
+fn cubic_extrema(p0: f32, p1: f32, p2: f32, p3: f32) -> [Option<f32>; 2] {
+	let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+	let b = 2.0 * (p0 - 2.0 * p1 + p2);
+	let c = p1 - p0;
+	let in_range = |t: f32| (t > 0.0 && t < 1.0).then_some(t);
+
+	if a.abs() < 1e-6 {
+		return if b.abs() < 1e-6 {[None, None]} else {[in_range(-c / b), None]};
+	}
+	let discriminant = b * b - 4.0 * a * c;
+	if discriminant < 0.0 {
+		return [None, None];
+	}
+	let root = discriminant.sqrt();
+	[in_range((-b + root) / (2.0 * a)), in_range((-b - root) / (2.0 * a))]
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::font_loader::FontFile;
+	use crate::sfnt_builder::*;
+
+	const RMOVETO: u8 = 21;
+	const RLINETO: u8 = 5;
+	const RRCURVETO: u8 = 8;
+	const HSTEMHM: u8 = 18;
+	const HINTMASK: u8 = 19;
+	const CALLSUBR: u8 = 10;
+	const RETURN: u8 = 11;
+	const ENDCHAR: u8 = 14;
+
+	// operands followed by their operator
+	fn op(operands: &[i32], operator: u8) -> Vec<u8> {
+		let mut bytes: Vec<u8> = vec![];
+		for &value in operands {
+			if (-107..=107).contains(&value) {
+				bytes.push((value + 139) as u8);
+			} else {
+				bytes.push(28);
+				bytes.extend((value as i16).to_be_bytes());
+			}
+		}
+		bytes.push(operator);
+		bytes
+	}
+
+	fn outlines(char_strings: &[Vec<u8>], local_subrs: &[Vec<u8>], charset: &[u16]) -> Vec<GlyphDescription> {
+		let font = minimal_cff_font(cff_table(char_strings, local_subrs, charset), char_strings.len() as u16);
+		let font_file = FontFile::from_buffer(Arc::new(font), 0).unwrap();
+		let cff = CffFont::new(font_file.table_reader("CFF ").unwrap(), &[]).unwrap();
+		(0..char_strings.len() as u16).map(|i| cff.get_glyph_description(i).unwrap()).collect()
+	}
+
+	fn points(glyph: &GlyphDescription) -> Vec<(i32, i32, bool)> {
+		glyph.coordinates.iter().map(|point| (point.x as i32, point.y as i32, point.flags & CUBIC_CONTROL_FLAG != 0)).collect()
+	}
+
+	#[test]
+	fn lines_after_a_width() {
+		// the odd operand before rmoveto is the width, the closing line back to the start is implied
+		let glyph = [op(&[300, 10, 20], RMOVETO), op(&[50, 0, 0, 50, -50, 0, 0, -50], RLINETO), op(&[], ENDCHAR)].concat();
+		let glyphs = outlines(&[op(&[], ENDCHAR), glyph], &[], &[1]);
+		assert!(glyphs[0].coordinates.is_empty());
+		assert_eq!(points(&glyphs[1]), vec![(10, 20, false), (60, 20, false), (60, 70, false), (10, 70, false)]);
+		assert_eq!(glyphs[1].end_pts_of_contours, vec![3]);
+		assert_eq!((glyphs[1].xmin, glyphs[1].ymin, glyphs[1].xmax, glyphs[1].ymax), (10, 20, 60, 70));
+	}
+
+	#[test]
+	fn endchar_with_only_a_width() {
+		let glyphs = outlines(&[op(&[], ENDCHAR), op(&[250], ENDCHAR)], &[], &[1]);
+		assert!(glyphs[1].coordinates.is_empty());
+		assert_eq!(glyphs[1].number_of_contours, 0);
+	}
+
+	#[test]
+	fn curves_mark_their_control_points() {
+		let glyph = [op(&[0, 0], RMOVETO), op(&[10, 0, 20, 10, 0, 20], RRCURVETO), op(&[], ENDCHAR)].concat();
+		let glyphs = outlines(&[op(&[], ENDCHAR), glyph], &[], &[1]);
+		assert_eq!(points(&glyphs[1]), vec![(0, 0, false), (10, 0, true), (30, 10, true), (30, 30, false)]);
+	}
+
+	#[test]
+	fn hintmask_skips_its_mask_bytes() {
+		// two hstems after the width and one implied vstem before hintmask make three stems and one mask byte,
+		// which is endchar's byte and would end the glyph if it were read as an operator
+		let glyph = [
+			op(&[500, 0, 10, 20, 10], HSTEMHM),
+			op(&[30, 10], HINTMASK),
+			vec![ENDCHAR],
+			op(&[0, 0], RMOVETO),
+			op(&[10, 0, 0, 10], RLINETO),
+			op(&[], ENDCHAR),
+		].concat();
+		let glyphs = outlines(&[op(&[], ENDCHAR), glyph], &[], &[1]);
+		assert_eq!(points(&glyphs[1]), vec![(0, 0, false), (10, 0, false), (10, 10, false)]);
+	}
+
+	#[test]
+	fn callsubr_applies_the_bias() {
+		// with fewer than 1240 subrs the bias is 107, so subr 0 is called as -107
+		let subr = [op(&[10, 0], RLINETO), vec![RETURN]].concat();
+		let glyph = [op(&[0, 0], RMOVETO), op(&[-107], CALLSUBR), op(&[0, 10], RLINETO), op(&[], ENDCHAR)].concat();
+		let glyphs = outlines(&[op(&[], ENDCHAR), glyph], &[subr], &[1]);
+		assert_eq!(points(&glyphs[1]), vec![(0, 0, false), (10, 0, false), (10, 10, false)]);
+	}
+
+	#[test]
+	fn flex_draws_two_curves() {
+		// flex is the two byte operator 12 35
+		let mut flex = op(&[10, 10, 10, 10, 10, -10, 10, -10, 10, 10, 10, 10, 50], 12);
+		flex.push(35);
+		let glyph = [op(&[0, 0], RMOVETO), flex, op(&[], ENDCHAR)].concat();
+		let glyphs = outlines(&[op(&[], ENDCHAR), glyph], &[], &[1]);
+		assert_eq!(points(&glyphs[1]), vec![
+			(0, 0, false),
+			(10, 10, true), (20, 20, true), (30, 10, false),
+			(40, 0, true), (50, 10, true), (60, 20, false),
+		]);
+	}
+
+	#[test]
+	fn seac_composes_base_and_accent() {
+		// A and grave are string ids 34 and 124, StandardEncoding codes 65 and 193
+		let base = [op(&[0, 0], RMOVETO), op(&[100, 0, -50, 100], RLINETO), op(&[], ENDCHAR)].concat();
+		let accent = [op(&[0, 0], RMOVETO), op(&[20, 0, -10, 30], RLINETO), op(&[], ENDCHAR)].concat();
+		let composed = op(&[600, 40, 120, 65, 193], ENDCHAR);
+		let glyphs = outlines(&[op(&[], ENDCHAR), base, accent, composed], &[], &[34, 124, 174]);
+		assert_eq!(points(&glyphs[3]), vec![
+			(0, 0, false), (100, 0, false), (50, 100, false),
+			(40, 120, false), (60, 120, false), (50, 150, false),
+		]);
+		assert_eq!(glyphs[3].end_pts_of_contours, vec![2, 5]);
+		assert_eq!(glyphs[3].ymax, 150);
+	}
+
+	// a two byte operator, 12 followed by its second byte
+	fn escape(operands: &[i32], operator: u8) -> Vec<u8> {
+		let mut bytes = op(operands, 12);
+		bytes.push(operator);
+		bytes
+	}
+
+	#[test]
+	fn arithmetic_and_storage_operators() {
+		let glyph = [
+			op(&[0, 0], RMOVETO),
+			// dotsection is a no-op
+			escape(&[], 0),
+			// mul, div
+			escape(&[6, 4], 24), escape(&[50, 2], 12), op(&[], RLINETO),
+			// sqrt, abs, neg, add
+			escape(&[9], 26), escape(&[-8], 9), escape(&[2], 14), escape(&[], 10), op(&[], RLINETO),
+			// put, get, exch
+			escape(&[40, 0], 20), escape(&[5, 1], 20), escape(&[0], 21), escape(&[1], 21), escape(&[], 28), op(&[], RLINETO),
+			// roll, drop, index, dup, sub
+			escape(&[1, 2, 3, 3, 1], 30), escape(&[], 18), escape(&[1], 29), escape(&[], 10), escape(&[], 27), escape(&[], 11), op(&[], RLINETO),
+			// ifelse, eq, and, or, not
+			escape(&[7, 9, 1, 2], 22), escape(&[7, 9, 3, 2], 22), escape(&[1, 1], 15), escape(&[0], 3), escape(&[1], 4), escape(&[], 5), escape(&[], 10), op(&[], RLINETO),
+			op(&[], ENDCHAR),
+		].concat();
+		let glyphs = outlines(&[op(&[], ENDCHAR), glyph], &[], &[1]);
+		assert_eq!(points(&glyphs[1]), vec![
+			(0, 0, false), (24, 25, false), (27, 31, false), (32, 71, false), (35, 71, false), (42, 80, false),
+		]);
+	}
+
+	#[test]
+	fn random_is_between_zero_and_one() {
+		let glyph = [op(&[0, 0], RMOVETO), escape(&[], 23), escape(&[1000], 24), op(&[0], RLINETO), op(&[], ENDCHAR)].concat();
+		let glyphs = outlines(&[op(&[], ENDCHAR), glyph.clone()], &[], &[1]);
+		let x = glyphs[1].coordinates[1].x;
+		assert!(x > 0.0 && x <= 1000.0);
+		// the same glyph draws the same way every time it loads
+		assert_eq!(points(&glyphs[1]), points(&outlines(&[op(&[], ENDCHAR), glyph], &[], &[1])[1]));
+	}
+
+	#[test]
+	fn broken_charstring_loads_as_an_empty_glyph() {
+		// add with nothing on the stack underflows
+		let broken = [op(&[0, 0], RMOVETO), escape(&[], 10), op(&[], ENDCHAR)].concat();
+		let line = [op(&[0, 0], RMOVETO), op(&[50, 0, 0, 50], RLINETO), op(&[], ENDCHAR)].concat();
+		let char_strings = [op(&[], ENDCHAR), broken, line];
+		let font = minimal_cff_font(cff_table(&char_strings, &[], &[1, 2]), 3);
+		let mut font_file = FontFile::from_buffer(Arc::new(font), 0).unwrap();
+		font_file.get_glyphs().unwrap();
+		assert!(font_file.glyphs[1].coordinates.is_empty());
+		assert_eq!((font_file.glyphs[2].xmax, font_file.glyphs[2].ymax), (50, 50));
+	}
+}
@@ -0,0 +1,144 @@
+use std::path::PathBuf;
+
+use crate::font_loader::FontFile;
+use crate::image_writer::{colorize, parse_color, write_image, Color, ImageFormat};
+use crate::rasterizer::FillRule;
+use crate::text_renderer::render_text;
+
+pub const RENDER_USAGE: &str = "usage: font_render render --font <path> --text <text> -o <out.png|out.pgm|out.ppm>
+    [--face <index>] [--size <points>] [--dpi <dpi>] [--fg <#rrggbb>] [--bg <#rrggbb>] [--padding <pixels>]
+    [--features <ss01,-liga,...>] [--variation <wght=650,...>] [--fill <nonzero|evenodd>] [--hinting] [--format <png|pgm|ppm>]";
+
+// a comma separated feature list like "ss01,zero,-liga,cv01=2"
+pub fn apply_features(font_file: &mut FontFile, features: &str) {
+	for feature in features.split(',').filter(|feature| !feature.is_empty()) {
+		let (name, value) = match feature.split_once('=') {
+			Some((name, value)) => (name, value.parse().unwrap_or(1)),
+			None => match feature.strip_prefix('-') {
+				Some(name) => (name, 0),
+				None => (feature.trim_start_matches('+'), 1),
+			},
+		};
+		match <[u8; 4]>::try_from(name.as_bytes()) {
+			Ok(tag) => font_file.set_feature(tag, value),
+			Err(_) => eprintln!("Warning: ignoring feature {}, tags are four characters", feature),
+		}
+	}
+}
+
+// a variation like "wght=650,wdth=80" for variable fonts
+pub fn apply_variation(font_file: &mut FontFile, variation: &str) {
+	let axes: Vec<(&str, f32)> = variation.split(',')
+		.filter_map(|axis| axis.split_once('='))
+		.filter_map(|(tag, value)| Some((tag, value.parse().ok()?)))
+		.collect();
+	if !axes.is_empty() {
+		if let Err(e) = font_file.set_variation(&axes) {
+			eprintln!("Warning: can't apply variation {}: {}", variation, e);
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderOptions {
+	pub font: String,
+	pub face_index: usize,
+	pub text: String,
+	pub output: PathBuf,
+	pub format: ImageFormat,
+	// in points, at dpi 72 a point is a pixel
+	pub size: f32,
+	pub dpi: f32,
+	pub foreground: Color,
+	pub background: Color,
+	pub padding: u32,
+	pub features: String,
+	pub variation: String,
+	pub fill_rule: FillRule,
+	pub hinting: bool,
+}
+
+impl RenderOptions {
+	pub fn parse(args: impl Iterator<Item = String>) -> Result<RenderOptions, String> {
+		let mut args = args;
+		let (mut font, mut text, mut output, mut format) = (None, None, None, None);
+		let mut options = RenderOptions {
+			font: String::new(),
+			face_index: 0,
+			text: String::new(),
+			output: PathBuf::new(),
+			format: ImageFormat::Png,
+			size: 32.0,
+			dpi: 72.0,
+			foreground: [0, 0, 0],
+			background: [255, 255, 255],
+			padding: 0,
+			features: String::new(),
+			variation: String::new(),
+			// overlapping contours of variable fonts leave holes under even-odd
+			fill_rule: FillRule::NonZero,
+			hinting: false,
+		};
+		while let Some(arg) = args.next() {
+			if arg == "--hinting" {
+				options.hinting = true;
+				continue;
+			}
+			let value = args.next().ok_or_else(|| format!("{} needs a value", arg))?;
+			let invalid = || format!("invalid value for {}: {}", arg, value);
+			match arg.as_str() {
+				"--font" => font = Some(value),
+				"--text" => text = Some(value),
+				"-o" | "--output" => output = Some(PathBuf::from(value)),
+				"--format" => format = Some(ImageFormat::from_name(&value.to_ascii_lowercase()).ok_or_else(invalid)?),
+				"--face" => options.face_index = value.parse().map_err(|_| invalid())?,
+				"--size" => options.size = value.parse().ok().filter(|&size: &f32| size > 0.0).ok_or_else(invalid)?,
+				"--dpi" => options.dpi = value.parse().ok().filter(|&dpi: &f32| dpi > 0.0).ok_or_else(invalid)?,
+				"--fg" => options.foreground = parse_color(&value).ok_or_else(invalid)?,
+				"--bg" => options.background = parse_color(&value).ok_or_else(invalid)?,
+				"--padding" => options.padding = value.parse().map_err(|_| invalid())?,
+				"--features" => options.features = value,
+				"--variation" => options.variation = value,
+				"--fill" => options.fill_rule = match value.as_str() {
+					"evenodd" => FillRule::EvenOdd,
+					"nonzero" => FillRule::NonZero,
+					_ => return Err(invalid()),
+				},
+				_ => return Err(format!("unknown option {}", arg)),
+			}
+		}
+
+		options.font = font.ok_or("--font is required")?;
+		options.text = text.ok_or("--text is required")?;
+		options.output = output.ok_or("-o is required")?;
+		// an explicit --format wins, otherwise the extension decides and anything unknown is a png
+		options.format = format.or_else(|| ImageFormat::from_extension(&options.output)).unwrap_or(ImageFormat::Png);
+		Ok(options)
+	}
+
+	pub fn ppem(&self) -> f32 {
+		self.size * self.dpi / 72.0
+	}
+}
+
+// draws the text into an image file without opening a window
+pub fn run_render(options: &RenderOptions) -> Result<(), String> {
+	let mut font_file = FontFile::from_collection(&options.font, options.face_index)
+		.map_err(|e| format!("failed to load {}: {}", options.font, e))?;
+	apply_features(&mut font_file, &options.features);
+	apply_variation(&mut font_file, &options.variation);
+	font_file.load_layout_tables().map_err(|e| e.to_string())?;
+	font_file.get_os2_table().map_err(|e| e.to_string())?;
+
+	// hinted outlines only fit the grid at whole pixel sizes
+	let mut ppem = options.ppem();
+	if options.hinting {
+		ppem = ppem.round().max(1.0);
+		font_file.set_hinting(Some(ppem as u16)).map_err(|e| format!("can't hint: {}", e))?;
+	}
+
+	let bitmap = render_text(&font_file, &options.text, ppem, options.fill_rule, options.padding).map_err(|e| e.to_string())?;
+	let rgb = colorize(&bitmap, options.foreground, options.background);
+	write_image(&options.output, options.format, bitmap.width, bitmap.height, &rgb, options.dpi)
+		.map_err(|e| format!("failed to write {}: {}", options.output.display(), e))
+}
@@ -0,0 +1,45 @@
+use std::fmt;
+
+#[derive(Debug)]
+pub enum FontError {
+	Io(std::io::Error),
+	BadMagic(u32),
+	MissingTable(String),
+	OutOfBounds { offset: usize, len: usize },
+	UnsupportedFormat { table: &'static str, format: u32 },
+	NoSupportedCmap,
+	FaceIndexOutOfRange { index: usize, count: usize },
+	Malformed(&'static str),
+	ImageTooLarge { width: i64, height: i64 },
+}
+
+impl fmt::Display for FontError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			FontError::Io(error) => write!(f, "failed to read font file: {}", error),
+			FontError::BadMagic(magic) => write!(f, "not a font file (magic 0x{:08X})", magic),
+			FontError::MissingTable(tag) => write!(f, "missing required table '{}'", tag),
+			FontError::OutOfBounds { offset, len } => write!(f, "reading {} bytes at offset {} runs past the end of the data", len, offset),
+			FontError::UnsupportedFormat { table, format } => write!(f, "unsupported {} format {}", table, format),
+			FontError::NoSupportedCmap => write!(f, "no supported cmap subtable"),
+			FontError::FaceIndexOutOfRange { index, count } => write!(f, "face index {} out of range, the file has {} faces", index, count),
+			FontError::Malformed(reason) => write!(f, "malformed font data: {}", reason),
+			FontError::ImageTooLarge { width, height } => write!(f, "a {}x{} pixel image is too large to render", width, height),
+		}
+	}
+}
+
+impl std::error::Error for FontError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			FontError::Io(error) => Some(error),
+			_ => None,
+		}
+	}
+}
+
+impl From<std::io::Error> for FontError {
+	fn from(error: std::io::Error) -> Self {
+		FontError::Io(error)
+	}
+}
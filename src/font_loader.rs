@@ -1,6 +1,21 @@
 use std::collections::HashMap;
+use std::sync::Arc;
 
+use crate::cff::{CffFont, CUBIC_CONTROL_FLAG};
+use crate::font_error::FontError;
+use crate::gpos::Gpos;
+use crate::gsub::Gsub;
+use crate::hinting::{HintingInstance, HintingTables, MaxpLimits};
+use crate::kern_table::KernTable;
+use crate::layout::{FeatureSetting, Gdef, DEFAULT_FEATURES};
+use crate::name_table::{self, NameTable};
+use crate::os2_table::Os2Table;
+use crate::post_table::PostTable;
+use crate::rasterizer::{self, Bitmap, FillRule};
+use crate::reader::{Reader, Tag};
 use crate::utils::*;
+use crate::variations::{Avar, Fvar, Gvar, Hvar, Mvar, VariationAxis};
+use crate::woff::decode_font_container;
 
 #[derive(Debug, Clone, Copy)]
 pub struct Point {
@@ -20,7 +35,7 @@ impl Default for Point {
 	
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct GlyphDescription {
 	pub number_of_contours: i16,
 	pub xmin: i16,
@@ -32,23 +47,17 @@ pub struct GlyphDescription {
 	pub instruction_length: u16,
 	pub instructions: Vec<u8>,
 	pub coordinates: Vec<Point>,
+	pub advance_width: u16,
+	pub lsb: i16,
 }
 
-impl Default for GlyphDescription {
-	fn default() -> Self {
-		Self {
-			number_of_contours: 0,
-			xmin: 0,
-			ymin: 0,
-			xmax: 0,
-			ymax: 0,
-			end_pts_of_contours: vec![],
-			num_points: 0,
-			instruction_length: 0,
-			instructions: vec![],
-			coordinates: vec![],
-		}
-	}
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HorizontalHeader {
+	pub ascender: i16,
+	pub descender: i16,
+	pub line_gap: i16,
+	pub advance_width_max: u16,
+	pub number_of_h_metrics: u16,
 }
 
 pub struct TableDirectory {
@@ -58,94 +67,298 @@ pub struct TableDirectory {
 }
 
 pub struct FontFile {
-	pub file_buffer:				Vec<u8>,
+	pub file_buffer:				Arc<Vec<u8>>,
 	pub table_directories: 			Vec<TableDirectory>,
+	pub glyph_locations:			Vec<u32>,
 	pub glyphs:						Vec<GlyphDescription>,
-	pub unicode_to_glyph_index_map: HashMap<u16, u16>
+	pub horizontal_header:			HorizontalHeader,
+	pub unicode_to_glyph_index_map: HashMap<u32, u16>,
+	pub variation_sequences:		HashMap<(u32, u32), Option<u16>>,
+	pub normalized_coordinates:		Vec<f32>,
+	pub name_table:					NameTable,
+	pub os2_table:					Option<Os2Table>,
+	pub post_table:					PostTable,
+	pub kern_table:					KernTable,
+	pub gdef:						Gdef,
+	pub gpos:						Gpos,
+	pub gsub:						Gsub,
+	pub feature_settings:			Vec<FeatureSetting>,
+	pub fvar:						Option<Fvar>,
+	pub avar:						Option<Avar>,
+	pub gvar:						Option<Gvar>,
+	pub hvar:						Option<Hvar>,
+	pub mvar:						Option<Mvar>,
+	pub hinting_tables:				Option<HintingTables>,
+	pub hinting:					Option<HintingInstance>
 }
 
 impl FontFile {
-	pub fn new(file_path: &str) -> FontFile {
-		let file_buffer = file_bytes(file_path);
-		let table_directories = get_table_details(&file_buffer);
-		Self {
+	pub fn new(file_path: &str) -> Result<FontFile, FontError> {
+		Self::from_collection(file_path, 0)
+	}
+
+	pub fn from_collection(file_path: &str, face_index: usize) -> Result<FontFile, FontError> {
+		let file_buffer = Arc::new(decode_font_container(file_bytes(file_path)?)?);
+		Self::from_buffer(file_buffer, face_index)
+	}
+
+	// every face of a collection, all reading from the same buffer so tables they share are only loaded once
+	pub fn all_faces(file_path: &str) -> Result<Vec<FontFile>, FontError> {
+		let file_buffer = Arc::new(decode_font_container(file_bytes(file_path)?)?);
+		let face_count = get_face_offsets(&file_buffer)?.len();
+		(0..face_count).map(|face_index| Self::from_buffer(Arc::clone(&file_buffer), face_index)).collect()
+	}
+
+	// the entry point for untrusted bytes, the first face with everything the viewer and the render command read
+	// any input gives a font or an error, never a panic
+	pub fn parse(data: &[u8]) -> Result<FontFile, FontError> {
+		let mut font_file = Self::from_buffer(Arc::new(decode_font_container(data.to_vec())?), 0)?;
+		font_file.load_layout_tables()?;
+		font_file.get_os2_table()?;
+		font_file.get_name_table()?;
+		font_file.get_post_table()?;
+		Ok(font_file)
+	}
+
+	pub fn from_buffer(file_buffer: Arc<Vec<u8>>, face_index: usize) -> Result<FontFile, FontError> {
+		let face_offsets = get_face_offsets(&file_buffer)?;
+		let face_offset = *face_offsets.get(face_index).ok_or(FontError::FaceIndexOutOfRange { index: face_index, count: face_offsets.len() })?;
+		let table_directories = get_table_details(&file_buffer, face_offset)?;
+		Ok(Self {
 			file_buffer,
 			table_directories,
+			glyph_locations: vec![],
 			glyphs: vec![],
-			unicode_to_glyph_index_map: HashMap::new()
-		}
+			horizontal_header: HorizontalHeader::default(),
+			unicode_to_glyph_index_map: HashMap::new(),
+			variation_sequences: HashMap::new(),
+			normalized_coordinates: vec![],
+			name_table: NameTable::default(),
+			os2_table: None,
+			post_table: PostTable::default(),
+			kern_table: KernTable::default(),
+			gdef: Gdef::default(),
+			gpos: Gpos::default(),
+			gsub: Gsub::default(),
+			feature_settings: vec![],
+			fvar: None,
+			avar: None,
+			gvar: None,
+			hvar: None,
+			mvar: None,
+			hinting_tables: None,
+			hinting: None
+		})
+	}
+
+	// outlines, cmap and everything shaping reads, what drawing text needs
+	pub fn load_layout_tables(&mut self) -> Result<(), FontError> {
+		self.get_variation_tables()?;
+		self.get_glyphs()?;
+		self.get_unicode_to_glyph_index_map()?;
+		self.get_kern_table()?;
+		self.get_gdef_table()?;
+		self.get_gpos_table()?;
+		self.get_gsub_table()
 	}
-	pub fn get_table_directory(&self, tag: &str) -> &TableDirectory {
+
+	pub fn get_table_directory(&self, tag: &str) -> Result<&TableDirectory, FontError> {
 		self.table_directories.iter()
-			.find(|table| table.tag == tag).unwrap()
+			.find(|table| table.tag == tag)
+			.ok_or_else(|| FontError::MissingTable(tag.to_string()))
 	}
 
-	pub fn get_glyphs(&mut self) {
-		let num_glyphs_offset = self.get_table_directory("maxp").offset + 4;
-		let num_glyphs = get_u16(&self.file_buffer, num_glyphs_offset as usize);
+	pub fn table_reader(&self, tag: &str) -> Result<Reader<'_>, FontError> {
+		let table = self.get_table_directory(tag)?;
+		Reader::new(&self.file_buffer).sub_reader(table.offset as usize, table.length as usize)
+	}
 
-		let byte_entry_check_location = self.get_table_directory("head").offset + 50;
-		let entry_size = if get_i16(&self.file_buffer, byte_entry_check_location as usize) == 0 {2} else {4} as u32;
+	pub fn get_glyphs(&mut self) -> Result<(), FontError> {
+		let mut maxp = self.table_reader("maxp")?;
+		maxp.skip(4)?;
+		let num_glyphs = maxp.read_u16()?;
+		self.get_horizontal_header()?;
 
-		let location_table_location = self.get_table_directory("loca").offset;
-		let glyph_table_location = self.get_table_directory("glyf").offset;
+		// opentype fonts with postscript outlines carry a CFF or CFF2 table instead of glyf and loca
+		let cff_tag = ["CFF2", "CFF "].into_iter().find(|tag| self.get_table_directory(tag).is_ok());
+		if let (Err(_), Some(cff_tag)) = (self.get_table_directory("glyf"), cff_tag) {
+			self.glyphs = self.get_cff_glyphs(cff_tag, num_glyphs)?;
+			self.insert_inbetween_points();
+			return Ok(());
+		}
 
+		let mut head = self.table_reader("head")?;
+		head.seek(50)?;
+		let index_to_loc_format = head.read_i16()?;
+
+		// loca has numGlyphs + 1 entries, the last one marks the end of the final glyph
+		let mut loca = self.table_reader("loca")?;
 		let mut glyph_locations: Vec<u32> = vec![];
-		
-		let mut offset = location_table_location;
-		for i in 0..num_glyphs as u32{
-			let data_offset = if entry_size == 2 {get_u16(&self.file_buffer, offset as usize) as u32 * 2} else {get_u32(&self.file_buffer, offset as usize)};
-			glyph_locations.push(glyph_table_location + data_offset);
-			offset += entry_size;
+		for _ in 0..=num_glyphs as u32{
+			let location = if index_to_loc_format == 0 {loca.read_u16()? as u32 * 2} else {loca.read_u32()?};
+			glyph_locations.push(location);
 		}
+		self.glyph_locations = glyph_locations;
 
-		for i in 0..glyph_locations.len() {
-			let glyph = self.get_glyph_description(glyph_locations[i] as usize);
+		// glyphs are loaded again whenever the variation changes
+		self.glyphs.clear();
+		for i in 0..num_glyphs {
+			let glyph = self.get_glyph_description(i, &mut vec![])?;
 			self.glyphs.push(glyph);
 		}
 		self.insert_inbetween_points();
-		//self.insert_bezier_points();
+		Ok(())
+	}
+
+	fn get_cff_glyphs(&self, cff_tag: &str, num_glyphs: u16) -> Result<Vec<GlyphDescription>, FontError> {
+		// CFF2 outlines are evaluated at normalized_coordinates, empty means the default instance
+		let cff = CffFont::new(self.table_reader(cff_tag)?, &self.normalized_coordinates)?;
+		let mut glyphs: Vec<GlyphDescription> = vec![];
+		for i in 0..num_glyphs {
+			// a charstring that doesn't run draws nothing instead of taking the rest of the font with it
+			let mut glyph = cff.get_glyph_description(i).unwrap_or_default();
+			(glyph.advance_width, glyph.lsb) = self.get_horizontal_metrics(i)?;
+			glyphs.push(glyph);
+		}
+		Ok(glyphs)
+	}
+
+	fn get_horizontal_header(&mut self) -> Result<(), FontError> {
+		let mut hhea = self.table_reader("hhea")?;
+		hhea.skip(4)?; //skip version
+		self.horizontal_header = HorizontalHeader {
+			ascender: 				hhea.read_i16()?,
+			descender: 				hhea.read_i16()?,
+			line_gap: 				hhea.read_i16()?,
+			advance_width_max: 		hhea.read_u16()?,
+			number_of_h_metrics: 	{
+				hhea.seek(34)?;
+				hhea.read_u16()?
+			},
+		};
+		if self.horizontal_header.number_of_h_metrics == 0 {
+			return Err(FontError::Malformed("hhea has no horizontal metrics"));
+		}
+		Ok(())
+	}
+
+	fn get_horizontal_metrics(&self, glyph_index: u16) -> Result<(u16, i16), FontError> {
+		let number_of_h_metrics = self.horizontal_header.number_of_h_metrics as usize;
+		let mut hmtx = self.table_reader("hmtx")?;
+		let glyph_index = glyph_index as usize;
+
+		// glyphs past numberOfHMetrics only store an lsb and share the last advance width
+		let (advance_width, lsb) = if glyph_index < number_of_h_metrics {
+			hmtx.seek(glyph_index * 4)?;
+			(hmtx.read_u16()?, hmtx.read_i16()?)
+		} else {
+			hmtx.seek((number_of_h_metrics - 1) * 4)?;
+			let advance_width = hmtx.read_u16()?;
+			hmtx.seek(number_of_h_metrics * 4 + (glyph_index - number_of_h_metrics) * 2)?;
+			(advance_width, hmtx.read_i16()?)
+		};
+
+		// HVAR only moves the advance here, the lsb follows from the varied outline
+		match &self.hvar {
+			Some(hvar) if !self.normalized_coordinates.is_empty() => {
+				let delta = hvar.get_advance_delta(glyph_index as u16, &self.normalized_coordinates);
+				Ok((add_delta(advance_width, delta), lsb))
+			}
+			_ => Ok((advance_width, lsb)),
+		}
+	}
+
+	// gvar moves the points and, through the four phantom points after them, the advance when there's no HVAR
+	// returns whether anything moved so the caller knows to recompute the bounds
+	fn apply_glyph_variations(&self, glyph_index: u16, glyph: &mut GlyphDescription, points: &mut [(f32, f32)], end_points: &[u16]) -> Result<bool, FontError> {
+		let Some(gvar) = &self.gvar else {
+			return Ok(false);
+		};
+		let origin = glyph.xmin as f32 - glyph.lsb as f32;
+		let mut all_points = points.to_vec();
+		all_points.extend([(origin, 0.0), (origin + glyph.advance_width as f32, 0.0), (0.0, 0.0), (0.0, 0.0)]);
+		let Some(deltas) = gvar.get_deltas(glyph_index, &self.normalized_coordinates, &all_points, end_points)? else {
+			return Ok(false);
+		};
+		for (point, delta) in points.iter_mut().zip(&deltas) {
+			point.0 += delta.0;
+			point.1 += delta.1;
+		}
+		if self.hvar.is_none() {
+			let phantom = points.len();
+			glyph.advance_width = add_delta(glyph.advance_width, deltas[phantom + 1].0 - deltas[phantom].0);
+		}
+		Ok(true)
 	}
 
-	fn get_glyph_description(&self, mut offset:usize) -> GlyphDescription{
+	fn get_glyph_description(&self, glyph_index: u16, parents: &mut Vec<u16>) -> Result<GlyphDescription, FontError> {
+		let (advance_width, lsb) = self.get_horizontal_metrics(glyph_index)?;
+		let (start, end) = match self.glyph_locations.get(glyph_index as usize..glyph_index as usize + 2) {
+			Some(&[start, end]) if start <= end => (start as usize, end as usize),
+			_ => return Err(FontError::Malformed("glyph location out of order in loca")),
+		};
+		if start == end {
+			let mut glyph = GlyphDescription {
+				advance_width,
+				lsb,
+				..Default::default()
+			};
+			self.apply_glyph_variations(glyph_index, &mut glyph, &mut [], &[])?;
+			self.apply_hinting(&mut glyph);
+			return Ok(glyph);
+		}
+
+		// only the glyph's own bytes are visible, a bad loca entry can't read into its neighbours
+		let mut reader = self.table_reader("glyf")?.sub_reader(start, end - start)?;
 		let mut glyph = GlyphDescription {
-			number_of_contours: get_i16(&self.file_buffer, offset),
-			xmin: 				get_i16(&self.file_buffer, offset + 2),
-			ymin: 				get_i16(&self.file_buffer, offset + 4),
-			xmax: 				get_i16(&self.file_buffer, offset + 6),
-			ymax: 				get_i16(&self.file_buffer, offset + 8),
+			number_of_contours: reader.read_i16()?,
+			xmin: 				reader.read_i16()?,
+			ymin: 				reader.read_i16()?,
+			xmax: 				reader.read_i16()?,
+			ymax: 				reader.read_i16()?,
+			advance_width,
+			lsb,
 			..Default::default()
 		};
-		offset += 10;
-		if glyph.number_of_contours <= 0 {
-			return glyph;
+		if glyph.number_of_contours < 0 {
+			parents.push(glyph_index);
+			self.get_composite_glyph_description(glyph_index, &mut glyph, &mut reader, parents)?;
+			parents.pop();
+			return Ok(glyph);
 		}
-		for i in 0..glyph.number_of_contours as usize{
-			let point = get_u16(&self.file_buffer, offset);
+		if glyph.number_of_contours == 0 {
+			self.apply_hinting(&mut glyph);
+			return Ok(glyph);
+		}
+		for _ in 0..glyph.number_of_contours as usize{
+			let point = reader.read_u16()?;
+			if glyph.end_pts_of_contours.last().is_some_and(|&previous| point <= previous) {
+				return Err(FontError::Malformed("contour end points are not increasing"));
+			}
 			glyph.end_pts_of_contours.push(point);
-			offset += 2;
 		}
 
-		glyph.num_points = glyph.end_pts_of_contours[glyph.end_pts_of_contours.len() - 1] + 1;
-		glyph.instruction_length = get_u16(&self.file_buffer, offset);
-		offset += 2;
-		offset += glyph.instruction_length as usize; // TODO: read instructions
+		let last_point = glyph.end_pts_of_contours[glyph.end_pts_of_contours.len() - 1];
+		glyph.num_points = last_point.checked_add(1).ok_or(FontError::Malformed("too many points in glyph"))?;
+		glyph.instruction_length = reader.read_u16()?;
+		glyph.instructions = reader.read_bytes(glyph.instruction_length as usize)?.to_vec();
 
 		let points: Vec<Point> = vec![Point::default(); glyph.num_points as usize];
 		glyph.coordinates = points;
 
 		let mut flag_count = 0;
 		while flag_count < glyph.num_points {
-			let flag = self.file_buffer[offset];
+			// bit 7 is reserved in glyf and marks cubic control points here, so it's never kept
+			let flag = reader.read_u8()? & 0x3F;
 			glyph.coordinates[flag_count as usize].flags = flag;
-			offset += 1;
 			if bit_set(flag, 3){
-				let repeat = self.file_buffer[offset];
-				offset += 1;
-				for i in 0..=repeat as u16{
+				let repeat = reader.read_u8()?;
+				// a repeat count can claim more points than the glyph has, ignore the excess
+				let repeat = (repeat as u16).min(glyph.num_points - flag_count - 1);
+				for i in 0..=repeat {
 					glyph.coordinates[(flag_count + i) as usize].flags = flag;
 				}
-				flag_count += repeat as u16;
+				flag_count += repeat;
 			}
 			flag_count += 1;
 		}
@@ -153,170 +366,531 @@ impl FontFile {
 		let mut xcoordinates: Vec<i32> = vec![0; glyph.num_points as usize];
 		for i in 0..glyph.num_points as usize {
 			xcoordinates[i] = if i > 0 {xcoordinates[i - 1]} else {0};
-			
+
 			let flag = glyph.coordinates[i].flags;
 			if bit_set(flag, 1) {
-        		let dx = self.file_buffer[offset] as i32;
+        		let dx = reader.read_u8()? as i32;
         		xcoordinates[i] += if bit_set(flag, 4) { dx } else { -dx };
-				offset += 1;
 			}else if !bit_set(flag, 4) {
-				xcoordinates[i] += get_i16(&self.file_buffer, offset) as i32;
-				offset += 2;
+				xcoordinates[i] += reader.read_i16()? as i32;
 			}
 		}
 
 		let mut ycoordinates: Vec<i32> = vec![0; glyph.num_points as usize];
 		for i in 0..glyph.num_points as usize {
 			ycoordinates[i] = if i > 0 {ycoordinates[i - 1]} else {0};
-			
+
 			let flag = glyph.coordinates[i].flags;
 			if bit_set(flag, 2) {
-				let dy = self.file_buffer[offset] as i32;
+				let dy = reader.read_u8()? as i32;
 				ycoordinates[i] += if bit_set(flag, 5) { dy } else { -dy };
-				offset += 1;
 			}else if !bit_set(flag, 5) {
-				ycoordinates[i] += get_i16(&self.file_buffer, offset) as i32;
-				offset += 2;
+				ycoordinates[i] += reader.read_i16()? as i32;
+			}
+		}
+
+		let mut points: Vec<(f32, f32)> = xcoordinates.iter().zip(&ycoordinates).map(|(&x, &y)| (x as f32, y as f32)).collect();
+		let end_points = glyph.end_pts_of_contours.clone();
+		let varied = self.apply_glyph_variations(glyph_index, &mut glyph, &mut points, &end_points)?;
+		for (point, (x, y)) in glyph.coordinates.iter_mut().zip(points) {
+			point.x = x;
+			point.y = y;
+		}
+		if varied {
+			update_bounds(&mut glyph);
+		}
+		self.apply_hinting(&mut glyph);
+
+		Ok(glyph)
+	}
+
+	fn get_composite_glyph_description(&self, glyph_index: u16, glyph: &mut GlyphDescription, reader: &mut Reader, parents: &mut Vec<u16>) -> Result<(), FontError> {
+		const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+		const ARGS_ARE_XY_VALUES: u16 = 0x0002;
+		const ROUND_XY_TO_GRID: u16 = 0x0004;
+		const WE_HAVE_A_SCALE: u16 = 0x0008;
+		const MORE_COMPONENTS: u16 = 0x0020;
+		const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+		const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+		const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+		const USE_MY_METRICS: u16 = 0x0200;
+		const SCALED_COMPONENT_OFFSET: u16 = 0x0800;
+		const MAX_COMPONENT_DEPTH: usize = 32;
+
+		// gvar varies a composite through one point per component offset, so count them first
+		let mut component_deltas: Vec<(f32, f32)> = vec![];
+		let mut scan = *reader;
+		loop {
+			let flags = scan.read_u16()?;
+			scan.skip(if flags & ARG_1_AND_2_ARE_WORDS != 0 {6} else {4})?;
+			scan.skip(if flags & WE_HAVE_A_SCALE != 0 {2} else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {4} else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {8} else {0})?;
+			component_deltas.push((0.0, 0.0));
+			if flags & MORE_COMPONENTS == 0 {
+				break;
+			}
+		}
+		let varied = self.apply_glyph_variations(glyph_index, glyph, &mut component_deltas, &[])?;
+
+		for &component_delta in &component_deltas {
+			let flags = reader.read_u16()?;
+			let component_index = reader.read_u16()?;
+
+			let (argument1, argument2) = if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+				if flags & ARGS_ARE_XY_VALUES != 0 {
+					(reader.read_i16()? as i32, reader.read_i16()? as i32)
+				} else {
+					(reader.read_u16()? as i32, reader.read_u16()? as i32)
+				}
+			} else if flags & ARGS_ARE_XY_VALUES != 0 {
+				(reader.read_i8()? as i32, reader.read_i8()? as i32)
+			} else {
+				(reader.read_u8()? as i32, reader.read_u8()? as i32)
+			};
+
+			// [xscale, scale01, scale10, yscale]
+			let mut transform = [1.0, 0.0, 0.0, 1.0];
+			if flags & WE_HAVE_A_SCALE != 0 {
+				let scale = reader.read_f2dot14()?;
+				transform = [scale, 0.0, 0.0, scale];
+			} else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+				transform[0] = reader.read_f2dot14()?;
+				transform[3] = reader.read_f2dot14()?;
+			} else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+				for value in transform.iter_mut() {
+					*value = reader.read_f2dot14()?;
+				}
+			}
+
+			// a component that refers back to one of its parents would never terminate
+			let is_valid_component = (component_index as usize) + 1 < self.glyph_locations.len()
+				&& !parents.contains(&component_index)
+				&& parents.len() < MAX_COMPONENT_DEPTH;
+			if is_valid_component {
+				let mut component = self.get_glyph_description(component_index, parents)?;
+				for point in component.coordinates.iter_mut() {
+					let (x, y) = (point.x, point.y);
+					point.x = transform[0] * x + transform[2] * y;
+					point.y = transform[1] * x + transform[3] * y;
+				}
+
+				let (dx, dy) = if flags & ARGS_ARE_XY_VALUES != 0 {
+					let (dx, dy) = (argument1 as f32 + component_delta.0, argument2 as f32 + component_delta.1);
+					let (dx, dy) = if flags & SCALED_COMPONENT_OFFSET != 0 {
+						(transform[0] * dx + transform[2] * dy, transform[1] * dx + transform[3] * dy)
+					} else {
+						(dx, dy)
+					};
+					// hinted components keep their grid-fitted stems when the offset is a whole number of pixels
+					match &self.hinting {
+						Some(hinting) if flags & ROUND_XY_TO_GRID != 0 => (hinting.round_to_pixels(dx), hinting.round_to_pixels(dy)),
+						_ => (dx, dy),
+					}
+				} else {
+					// point matching: move the component so its point lines up with one already placed
+					match (glyph.coordinates.get(argument1 as usize), component.coordinates.get(argument2 as usize)) {
+						(Some(parent_point), Some(child_point)) => (parent_point.x - child_point.x, parent_point.y - child_point.y),
+						_ => (0.0, 0.0),
+					}
+				};
+
+				if glyph.coordinates.len() + component.coordinates.len() > u16::MAX as usize {
+					return Err(FontError::Malformed("too many points in composite glyph"));
+				}
+				let point_base = glyph.coordinates.len() as u16;
+				for point in component.coordinates.iter_mut() {
+					point.x += dx;
+					point.y += dy;
+				}
+				glyph.coordinates.extend(component.coordinates);
+				glyph.end_pts_of_contours.extend(component.end_pts_of_contours.iter().map(|end| point_base + end));
+
+				if flags & USE_MY_METRICS != 0 {
+					glyph.advance_width = component.advance_width;
+					glyph.lsb = component.lsb;
+				}
+			}
+
+			if flags & MORE_COMPONENTS == 0 {
+				if flags & WE_HAVE_INSTRUCTIONS != 0 {
+					glyph.instruction_length = reader.read_u16()?;
+					glyph.instructions = reader.read_bytes(glyph.instruction_length as usize)?.to_vec();
+				}
+				break;
 			}
 		}
+		glyph.number_of_contours = glyph.end_pts_of_contours.len() as i16;
+		glyph.num_points = glyph.coordinates.len() as u16;
+		if varied {
+			update_bounds(glyph);
+		}
+		// the components were hinted on their own, the composite's program then adjusts them together
+		self.apply_hinting(glyph);
+		Ok(())
+	}
 
-		for i in 0..glyph.num_points {
-			glyph.coordinates[i as usize].x = xcoordinates[i as usize] as f32;
-			glyph.coordinates[i as usize].y = ycoordinates[i as usize] as f32;
+	// grid-fits the outline together with its four phantom points, so the advance becomes whole pixels too
+	// a glyph program that fails leaves the outline as it was
+	fn apply_hinting(&self, glyph: &mut GlyphDescription) {
+		let Some(hinting) = &self.hinting else {
+			return;
+		};
+		let origin = glyph.xmin as f32 - glyph.lsb as f32;
+		let mut points: Vec<(f32, f32)> = glyph.coordinates.iter().map(|point| (point.x, point.y)).collect();
+		points.extend([
+			(origin, 0.0),
+			(origin + glyph.advance_width as f32, 0.0),
+			(0.0, self.horizontal_header.ascender as f32),
+			(0.0, self.horizontal_header.descender as f32),
+		]);
+		let mut on_curve: Vec<bool> = glyph.coordinates.iter().map(|point| bit_set(point.flags, 0)).chain([true; 4]).collect();
+		if hinting.hint_glyph(&mut points, &mut on_curve, &glyph.end_pts_of_contours, &glyph.instructions).is_err() {
+			return;
 		}
 
-		glyph
+		// the program may move the left phantom point, the outline follows it back to the origin
+		let phantom = glyph.coordinates.len();
+		let (left, right) = (points[phantom].0, points[phantom + 1].0);
+		for ((point, &(x, y)), &on_curve) in glyph.coordinates.iter_mut().zip(&points).zip(&on_curve) {
+			point.x = x - left + origin;
+			point.y = y;
+			point.flags = if on_curve {point.flags | 1} else {point.flags & !1};
+		}
+		glyph.advance_width = hinting.round_to_pixels(right - left).round().max(0.0) as u16;
+		update_bounds(glyph);
+		glyph.lsb = (glyph.xmin as f32 - origin).round() as i16;
 	}
 
-	pub fn get_unicode_to_glyph_index_map(&mut self) {
-		let mut offset = self.get_table_directory("cmap").offset as usize;
-		let cmap_offset = offset;
-		offset += 2;
-		let num_tables = get_u16(&self.file_buffer, offset);
-		offset += 2;
+	pub fn get_unicode_to_glyph_index_map(&mut self) -> Result<(), FontError> {
+		let mut cmap = self.table_reader("cmap")?;
+		cmap.skip(2)?; //skip version
+		let num_tables = cmap.read_u16()?;
 
 		struct CmapSubtable {
 			platform_id: u16,
 			platform_specific_id: u16,
-			cmap_offset: u32
+			cmap_offset: usize
 		}
 
 		let mut cmap_subtables: Vec<CmapSubtable> = vec![];
-		let mut windows_unicode_offset = -1;
-		let mut unicode_offset = -1;
-		for i in 0..num_tables as usize{
-			let platform_id = get_u16(&self.file_buffer, offset);
-			offset += 2;
-			let platform_specific_id = get_u16(&self.file_buffer, offset);
-			offset += 2;
-			let cmap_offset = get_u32(&self.file_buffer, offset);
-			offset += 4;
+		for _ in 0..num_tables as usize{
 			cmap_subtables.push(CmapSubtable{
-				platform_id,
-				platform_specific_id,
-				cmap_offset
+				platform_id: cmap.read_u16()?,
+				platform_specific_id: cmap.read_u16()?,
+				cmap_offset: cmap.read_offset32()?
 			});
 		}
-		cmap_subtables.sort_by(|a, b| a.platform_id.cmp(&b.platform_id));
-		cmap_subtables.sort_by(|a, b| a.platform_specific_id.cmp(&b.platform_specific_id));
 
-		for subtable in cmap_subtables {
-			if subtable.platform_id == 3 && subtable.platform_specific_id == 1 {
-				windows_unicode_offset = subtable.cmap_offset as i32;
-			}else if subtable.platform_id == 0 && subtable.platform_specific_id == 3 {
-				unicode_offset = subtable.cmap_offset as i32;
+		// full unicode subtables come first so code points outside the BMP stay reachable,
+		// legacy symbol and mac roman subtables are only used when nothing else is there
+		let preferred_encodings = [(3, 10), (0, 6), (0, 4), (3, 1), (0, 3), (0, 2), (0, 1), (0, 0), (3, 0), (1, 0)];
+		let subtable = preferred_encodings.iter()
+			.find_map(|&(platform_id, platform_specific_id)| cmap_subtables.iter()
+				.find(|subtable| subtable.platform_id == platform_id && subtable.platform_specific_id == platform_specific_id))
+			.or_else(|| cmap_subtables.iter().find(|subtable| !(subtable.platform_id == 0 && subtable.platform_specific_id == 5)))
+			.ok_or(FontError::NoSupportedCmap)?;
+
+		let mut variation_sequences = HashMap::new();
+		if let Some(subtable) = cmap_subtables.iter().find(|subtable| subtable.platform_id == 0 && subtable.platform_specific_id == 5) {
+			let mut reader = cmap.slice_from(subtable.cmap_offset)?;
+			if reader.read_u16()? == 14 {
+				variation_sequences = self.format_14_cmap(reader)?;
 			}
 		}
 
-		if windows_unicode_offset == -1 && unicode_offset == -1 {
-			panic!("No unicode cmap found");
-		}else if windows_unicode_offset != -1 {
-			offset = cmap_offset + windows_unicode_offset as usize;
-		}else{
-			offset = cmap_offset + unicode_offset as usize;
+		let glyph_index_map = self.get_cmap_subtable(cmap.at(subtable.cmap_offset)?)?;
+		self.variation_sequences = variation_sequences;
+		self.unicode_to_glyph_index_map = match (subtable.platform_id, subtable.platform_specific_id) {
+			(1, 0) => glyph_index_map.into_iter()
+				.filter(|&(char_code, _)| char_code < 256)
+				.map(|(char_code, glyph_index)| (mac_roman_to_char(char_code as u8) as u32, glyph_index))
+				.collect(),
+			(3, 0) => {
+				// symbol fonts park their glyphs at U+F020..U+F0FF, expose them at the plain ascii codes too
+				let mut glyph_index_map = glyph_index_map;
+				let aliases: Vec<(u32, u16)> = glyph_index_map.iter()
+					.filter(|&(&char_code, _)| (0xF020..=0xF0FF).contains(&char_code))
+					.map(|(&char_code, &glyph_index)| (char_code - 0xF000, glyph_index))
+					.collect();
+				for (char_code, glyph_index) in aliases {
+					glyph_index_map.entry(char_code).or_insert(glyph_index);
+				}
+				glyph_index_map
+			}
+			_ => glyph_index_map,
+		};
+
+		Ok(())
+	}
+
+	pub fn get_glyph_index(&self, character: char) -> u16 {
+		// unmapped characters fall back to .notdef
+		self.glyph_or_notdef(*self.unicode_to_glyph_index_map.get(&(character as u32)).unwrap_or(&0))
+	}
+
+	pub fn get_glyph_index_with_variation(&self, character: char, variation_selector: Option<char>) -> u16 {
+		let sequence = variation_selector.and_then(|selector| self.variation_sequences.get(&(character as u32, selector as u32)));
+		match sequence {
+			Some(Some(glyph_index)) => self.glyph_or_notdef(*glyph_index),
+			_ => self.get_glyph_index(character),
 		}
+	}
 
-		let format = get_u16(&self.file_buffer, offset);
-		offset += 2;
-		if format == 4 {
-			self.unicode_to_glyph_index_map = self.format_4_cmap(offset)
-		} else if format == 12 {
-			self.unicode_to_glyph_index_map = self.format_12_cmap()
-		} else {
-			panic!("Unsupported cmap format: {}", format);
-			
+	// nothing checks cmap or GSUB output against numGlyphs, ids past the loaded glyphs become .notdef
+	pub fn glyph_or_notdef(&self, glyph_index: u16) -> u16 {
+		if (glyph_index as usize) < self.glyphs.len() {glyph_index} else {0}
+	}
+
+	fn get_cmap_subtable(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		let format = reader.read_u16()?;
+		match format {
+			0 => self.format_0_cmap(reader),
+			2 => self.format_2_cmap(reader),
+			4 => self.format_4_cmap(reader),
+			6 => self.format_6_cmap(reader),
+			10 => self.format_10_cmap(reader),
+			12 => self.format_12_cmap(reader),
+			13 => self.format_13_cmap(reader),
+			_ => Err(FontError::UnsupportedFormat { table: "cmap", format: format as u32 }),
+		}
+	}
+
+	fn format_0_cmap(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		reader.skip(4)?; //skip length, language
+
+		let mut glyph_index_map: HashMap<u32, u16> = HashMap::new();
+		for char_code in 0..256 {
+			glyph_index_map.insert(char_code, reader.read_u8()? as u16);
 		}
+		Ok(glyph_index_map)
 	}
 
-	fn format_4_cmap(&self, mut offset: usize) -> HashMap<u16, u16> {
-		let length = get_u16(&self.file_buffer, offset);
-		
-		offset += 4; //skip language
-		
-		let seg_count = get_u16(&self.file_buffer, offset) / 2;
-		
-		offset += 8; //skip searchRange, entrySelector, rangeShift
+	fn format_2_cmap(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		reader.skip(4)?; //skip length, language
+
+		let mut sub_header_keys: Vec<u16> = vec![];
+		for _ in 0..256 {
+			sub_header_keys.push(reader.read_u16()?);
+		}
+		let sub_headers_location = reader.offset();
+
+		let mut glyph_index_map: HashMap<u32, u16> = HashMap::new();
+		for (high_byte, &sub_header_key) in sub_header_keys.iter().enumerate() {
+			// subheader 0 maps single byte codes, every other one the second byte of a two byte code
+			let mut sub_header = reader.at(sub_headers_location + sub_header_key as usize)?;
+			let first_code = sub_header.read_u16()?;
+			let entry_count = sub_header.read_u16()?;
+			let id_delta = sub_header.read_i16()?;
+			let glyph_index_array_location = sub_header.offset() + sub_header.read_u16()? as usize;
+
+			// second bytes only go up to 0xFF, larger counts would map the same codes again
+			let code_range = first_code as u32..(first_code as u32 + entry_count as u32).min(256);
+			let char_codes: Vec<(u32, u32)> = if sub_header_key == 0 {
+				if !code_range.contains(&(high_byte as u32)) {
+					continue;
+				}
+				vec![(high_byte as u32, high_byte as u32)]
+			} else {
+				code_range.map(|low_byte| (((high_byte as u32) << 8) | (low_byte & 0xFF), low_byte)).collect()
+			};
+
+			for (char_code, low_byte) in char_codes {
+				let glyph_index = reader.at(glyph_index_array_location + 2 * (low_byte - first_code as u32) as usize)?.read_u16()?;
+				let final_index = if glyph_index != 0 {
+					glyph_index.wrapping_add(id_delta as u16)
+				} else {
+					0
+				};
+				glyph_index_map.insert(char_code, final_index);
+			}
+		}
+		Ok(glyph_index_map)
+	}
+
+	fn format_4_cmap(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		reader.skip(4)?; //skip length, language
+
+		let seg_count = reader.read_u16()? / 2;
+
+		reader.skip(6)?; //skip searchRange, entrySelector, rangeShift
 
 		let mut end_code: Vec<u16> = vec![];
 		for _ in 0..seg_count {
-			end_code.push(get_u16(&self.file_buffer, offset));
-			offset += 2;
+			end_code.push(reader.read_u16()?);
 		}
 
-		offset += 2; //skip reservedPad
-		
+		reader.skip(2)?; //skip reservedPad
+
 		let mut start_code: Vec<u16> = vec![];
 		for _ in 0..seg_count {
-			start_code.push(get_u16(&self.file_buffer, offset));
-			offset += 2;
+			start_code.push(reader.read_u16()?);
 		}
-		
+
 		let mut id_delta: Vec<i16> = vec![];
 		for _ in 0..seg_count {
-			id_delta.push(get_i16(&self.file_buffer, offset));
-			offset += 2;
+			id_delta.push(reader.read_i16()?);
 		}
-		
-		let id_range_offset_pos = offset;
+
+		let id_range_offset_pos = reader.offset();
 		let mut id_range_offset: Vec<u16> = vec![];
 		for _ in 0..seg_count {
-			id_range_offset.push(get_u16(&self.file_buffer, offset));
-			offset += 2;
+			id_range_offset.push(reader.read_u16()?);
 		}
-		
-		let mut glyph_index_map: HashMap<u16, u16> = HashMap::new();
 
+		let mut glyph_index_map: HashMap<u32, u16> = HashMap::new();
+
+		// segments are sorted and don't overlap, one that reaches back into an earlier segment only maps
+		// the codes that are new, so a hostile table can't make the loops walk the same range over and over
+		let mut next_code: u32 = 0;
 		for i in 0..seg_count as usize{
 			let start = start_code[i];
 			let end = end_code[i];
 			let delta = id_delta[i];
 			let range_offset = id_range_offset[i];
-			
+			let first = (start as u32).max(next_code);
+			if first > end as u32 {
+				continue;
+			}
+			next_code = end as u32 + 1;
+			let first = first as u16;
+
 			if range_offset == 0 {
-				for j in start..=end{
-					let index = (j as i16 + delta) as u32 % 65536;
-					glyph_index_map.insert(j, index as u16);
+				for j in first..=end{
+					let index = j.wrapping_add(delta as u16);
+					glyph_index_map.insert(j as u32, index);
 				}
 			}else{
-	            for j in start..=end {
+	            for j in first..=end {
 	                let reader_location = id_range_offset_pos + (i * 2);
 	                let glyph_index_array_location = 2 * (j - start) as usize + (reader_location + range_offset as usize);
-				
-	                let glyph_index = get_u16(&self.file_buffer, glyph_index_array_location);
+
+	                let glyph_index = reader.at(glyph_index_array_location)?.read_u16()?;
 	                let final_index = if glyph_index != 0 {
-	                    (glyph_index as i16 + delta) as u32 % 65536
+	                    glyph_index.wrapping_add(delta as u16)
 	                } else {
 	                    0
 	                };
-	                glyph_index_map.insert(j, final_index as u16);
+	                glyph_index_map.insert(j as u32, final_index);
 	            }
 	        }
 	    }
-	    glyph_index_map
+	    Ok(glyph_index_map)
 	}
 
-	fn format_12_cmap(&self) -> HashMap<u16, u16> {
-		unimplemented!()
+	fn format_6_cmap(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		reader.skip(4)?; //skip length, language
+
+		let first_code = reader.read_u16()?;
+		let entry_count = reader.read_u16()?;
+
+		let mut glyph_index_map: HashMap<u32, u16> = HashMap::new();
+		for i in 0..entry_count as u32 {
+			glyph_index_map.insert(first_code as u32 + i, reader.read_u16()?);
+		}
+		Ok(glyph_index_map)
+	}
+
+	fn format_10_cmap(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		reader.skip(10)?; //skip reserved, length, language
+
+		let start_char_code = reader.read_u32()?;
+		let num_chars = reader.read_u32()?;
+
+		let mut glyph_index_map: HashMap<u32, u16> = HashMap::new();
+		for i in 0..num_chars {
+			glyph_index_map.insert(start_char_code.wrapping_add(i), reader.read_u16()?);
+		}
+		Ok(glyph_index_map)
+	}
+
+	fn format_12_cmap(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		reader.skip(10)?; //skip reserved, length, language
+
+		let num_groups = reader.read_u32()?;
+
+		let mut glyph_index_map: HashMap<u32, u16> = HashMap::new();
+		// groups are sorted and don't overlap, like format 4 only the part of a group past the previous one is mapped
+		let mut next_char_code: u32 = 0;
+		for _ in 0..num_groups {
+			let start_char_code = reader.read_u32()?;
+			let end_char_code = reader.read_u32()?.min(char::MAX as u32);
+			let start_glyph_id = reader.read_u32()?;
+			let first_char_code = start_char_code.max(next_char_code);
+			if first_char_code > end_char_code {
+				continue;
+			}
+			next_char_code = end_char_code + 1;
+
+			// each group maps a run of consecutive code points onto consecutive glyph ids
+			for char_code in first_char_code..=end_char_code {
+				// ids past 65535 can't name a glyph, truncating them would pick an unrelated one
+				let glyph_id = start_glyph_id.wrapping_add(char_code - start_char_code);
+				glyph_index_map.insert(char_code, u16::try_from(glyph_id).unwrap_or(0));
+			}
+		}
+		Ok(glyph_index_map)
+	}
+
+	fn format_13_cmap(&self, mut reader: Reader) -> Result<HashMap<u32, u16>, FontError> {
+		reader.skip(10)?; //skip reserved, length, language
+
+		let num_groups = reader.read_u32()?;
+
+		let mut glyph_index_map: HashMap<u32, u16> = HashMap::new();
+		// groups are sorted and don't overlap, like format 4 only the part of a group past the previous one is mapped
+		let mut next_char_code: u32 = 0;
+		for _ in 0..num_groups {
+			let start_char_code = reader.read_u32()?;
+			let end_char_code = reader.read_u32()?.min(char::MAX as u32);
+			let glyph_id = reader.read_u32()?;
+			let first_char_code = start_char_code.max(next_char_code);
+			if first_char_code > end_char_code {
+				continue;
+			}
+			next_char_code = end_char_code + 1;
+
+			// unlike format 12 the whole run shares a single glyph
+			let glyph_id = u16::try_from(glyph_id).unwrap_or(0);
+			for char_code in first_char_code..=end_char_code {
+				glyph_index_map.insert(char_code, glyph_id);
+			}
+		}
+		Ok(glyph_index_map)
+	}
+
+	fn format_14_cmap(&self, mut reader: Reader) -> Result<HashMap<(u32, u32), Option<u16>>, FontError> {
+		reader.skip(4)?; //skip length
+		let num_var_selector_records = reader.read_u32()?;
+
+		// None means the sequence uses the glyph the regular cmap already maps the base character to
+		let mut variation_sequences: HashMap<(u32, u32), Option<u16>> = HashMap::new();
+		for _ in 0..num_var_selector_records {
+			let var_selector = reader.read_u24()?;
+			let default_uvs_offset = reader.read_offset32()?;
+			let non_default_uvs_offset = reader.read_offset32()?;
+
+			if default_uvs_offset != 0 {
+				let mut ranges = reader.at(default_uvs_offset)?;
+				let num_unicode_value_ranges = ranges.read_u32()?;
+				for _ in 0..num_unicode_value_ranges {
+					let start_unicode_value = ranges.read_u24()?;
+					let additional_count = ranges.read_u8()? as u32;
+					for unicode_value in start_unicode_value..=start_unicode_value + additional_count {
+						variation_sequences.insert((unicode_value, var_selector), None);
+					}
+				}
+			}
+
+			if non_default_uvs_offset != 0 {
+				let mut mappings = reader.at(non_default_uvs_offset)?;
+				let num_uvs_mappings = mappings.read_u32()?;
+				for _ in 0..num_uvs_mappings {
+					let unicode_value = mappings.read_u24()?;
+					let glyph_id = mappings.read_u16()?;
+					variation_sequences.insert((unicode_value, var_selector), Some(glyph_id));
+				}
+			}
+		}
+		Ok(variation_sequences)
 	}
 
 	fn insert_inbetween_points(&mut self) {
@@ -330,7 +904,9 @@ impl FontFile {
 					let next_point = glyph.coordinates[if j == end {start} else {j + 1} as usize];
 					let current_point = glyph.coordinates[j as usize];
 					new_coordinates.push(current_point);
-					if bit_set(current_point.flags, 0) == bit_set(next_point.flags, 0) {
+					// the two control points of a cubic segment have no implied point between them
+					let is_cubic = current_point.flags & CUBIC_CONTROL_FLAG != 0 || next_point.flags & CUBIC_CONTROL_FLAG != 0;
+					if !is_cubic && bit_set(current_point.flags, 0) == bit_set(next_point.flags, 0) {
 						let new_point = Point {
 							x: (current_point.x + next_point.x) / 2.0,
 							y: (current_point.y + next_point.y) / 2.0,
@@ -347,38 +923,665 @@ impl FontFile {
 		}
 	}
 
-	fn insert_bezier_points(&mut self) {
-		for glyph in self.glyphs.iter_mut() {
-			let mut new_end_points: Vec<u16> = vec![];
-			let mut new_coordinates: Vec<Point> = vec![];
-			for i in 0..glyph.end_pts_of_contours.len() {
-				let start = if i == 0 {0} else {glyph.end_pts_of_contours[i - 1] + 1};
-				let end = glyph.end_pts_of_contours[i];
-				for j in start..=end {
-					let start_point = glyph.coordinates[j as usize];
-					if !bit_set(start_point.flags, 0) {
-						continue;
-					}
-					let middle_point = glyph.coordinates[if j == end {start} else {j + 1} as usize];
-					let end_point = glyph.coordinates[if j == end {start + 1} else if j == end - 1 {start} else {j + 2} as usize];
-					let new_points = calculate_beziers(start_point, middle_point, end_point, 25);
-					new_coordinates.extend(new_points);
-				}
-				new_end_points.push(new_coordinates.len() as u16 - 1);
+	// a font without name still renders, the names are just missing
+	pub fn get_name_table(&mut self) -> Result<(), FontError> {
+		self.name_table = self.get_optional_table("name", NameTable::parse)?.unwrap_or_default();
+		Ok(())
+	}
+
+	pub fn family_name(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::FAMILY)
+	}
+
+	pub fn subfamily_name(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::SUBFAMILY)
+	}
+
+	pub fn full_name(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::FULL_NAME)
+	}
+
+	pub fn postscript_name(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::POSTSCRIPT_NAME)
+	}
+
+	pub fn version(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::VERSION)
+	}
+
+	pub fn copyright(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::COPYRIGHT)
+	}
+
+	pub fn license(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::LICENSE)
+	}
+
+	pub fn license_url(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::LICENSE_URL)
+	}
+
+	// fonts with more than the four classic styles per family put the real family name in ID 16,
+	// older fonts only have ID 1
+	pub fn typographic_family_name(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::TYPOGRAPHIC_FAMILY).or_else(|| self.family_name())
+	}
+
+	pub fn typographic_subfamily_name(&self) -> Option<&str> {
+		self.name_table.get_name(name_table::TYPOGRAPHIC_SUBFAMILY).or_else(|| self.subfamily_name())
+	}
+
+	// apple fonts are allowed to leave OS/2 out, that isn't an error
+	pub fn get_os2_table(&mut self) -> Result<(), FontError> {
+		self.os2_table = match self.table_reader("OS/2") {
+			Ok(reader) => Some(Os2Table::parse(reader)?),
+			Err(FontError::MissingTable(_)) => None,
+			Err(error) => return Err(error),
+		};
+		Ok(())
+	}
+
+	pub fn weight_class(&self) -> u16 {
+		self.os2_table.as_ref().map_or(400, |os2| os2.weight_class)
+	}
+
+	pub fn width_class(&self) -> u16 {
+		self.os2_table.as_ref().map_or(5, |os2| os2.width_class)
+	}
+
+	// (ascender, descender, line gap), the typo metrics only when the font asks for them, hhea otherwise
+	// MVAR only names the typo metrics but the hhea ones are meant to vary the same way
+	pub fn line_metrics(&self) -> (i16, i16, i16) {
+		let (ascender, descender, line_gap) = match self.os2_table.as_ref().filter(|os2| os2.use_typo_metrics()).and_then(|os2| os2.typo_metrics) {
+			Some(typo) => (typo.ascender, typo.descender, typo.line_gap),
+			None => (self.horizontal_header.ascender, self.horizontal_header.descender, self.horizontal_header.line_gap),
+		};
+		(
+			ascender.saturating_add(self.metric_delta(*b"hasc")),
+			descender.saturating_add(self.metric_delta(*b"hdsc")),
+			line_gap.saturating_add(self.metric_delta(*b"hlgp")),
+		)
+	}
+
+	// without post there are no glyph names and the underline and italic metrics are zero
+	pub fn get_post_table(&mut self) -> Result<(), FontError> {
+		self.post_table = self.get_optional_table("post", PostTable::parse)?.unwrap_or_default();
+		Ok(())
+	}
+
+	pub fn glyph_name(&self, glyph_index: u16) -> Option<&str> {
+		self.post_table.glyph_name(glyph_index)
+	}
+
+	pub fn glyph_by_name(&self, name: &str) -> Option<u16> {
+		self.post_table.glyph_by_name(name)
+	}
+
+	// degrees counter-clockwise from vertical, negative for fonts leaning right
+	pub fn italic_angle(&self) -> f32 {
+		self.post_table.italic_angle
+	}
+
+	pub fn underline_position(&self) -> i16 {
+		self.post_table.underline_position.saturating_add(self.metric_delta(*b"undo"))
+	}
+
+	pub fn underline_thickness(&self) -> i16 {
+		self.post_table.underline_thickness.saturating_add(self.metric_delta(*b"unds"))
+	}
+
+	pub fn is_monospaced(&self) -> bool {
+		self.post_table.is_fixed_pitch
+	}
+
+	// most fonts only kern through GPOS now, so a missing kern table just means no pairs
+	pub fn get_kern_table(&mut self) -> Result<(), FontError> {
+		self.kern_table = match self.table_reader("kern") {
+			Ok(reader) => KernTable::parse(reader)?,
+			Err(FontError::MissingTable(_)) => KernTable::default(),
+			Err(error) => return Err(error),
+		};
+		Ok(())
+	}
+
+	pub fn get_kerning(&self, left: u16, right: u16) -> i16 {
+		self.kern_table.get_kerning(left, right)
+	}
+
+	pub fn get_gdef_table(&mut self) -> Result<(), FontError> {
+		self.gdef = match self.table_reader("GDEF") {
+			Ok(reader) => Gdef::parse(reader)?,
+			Err(FontError::MissingTable(_)) => Gdef::default(),
+			Err(error) => return Err(error),
+		};
+		Ok(())
+	}
+
+	pub fn get_gpos_table(&mut self) -> Result<(), FontError> {
+		self.gpos = match self.table_reader("GPOS") {
+			Ok(reader) => Gpos::parse_gpos(reader)?,
+			Err(FontError::MissingTable(_)) => Gpos::default(),
+			Err(error) => return Err(error),
+		};
+		Ok(())
+	}
+
+	pub fn get_gsub_table(&mut self) -> Result<(), FontError> {
+		self.gsub = match self.table_reader("GSUB") {
+			Ok(reader) => Gsub::parse_gsub(reader)?,
+			Err(FontError::MissingTable(_)) => Gsub::default(),
+			Err(error) => return Err(error),
+		};
+		Ok(())
+	}
+
+	// 0 turns a feature off, 1 on, stylistic alternates and character variants take higher values to pick among alternates
+	pub fn set_feature(&mut self, feature: Tag, value: u32) {
+		match self.feature_settings.iter_mut().find(|(tag, _)| *tag == feature) {
+			Some(setting) => setting.1 = value,
+			None => self.feature_settings.push((feature, value)),
+		}
+	}
+
+	pub fn get_feature_value(&self, feature: Tag) -> u32 {
+		self.feature_settings.iter()
+			.find(|(tag, _)| *tag == feature)
+			.map_or(DEFAULT_FEATURES.contains(&feature) as u32, |&(_, value)| value)
+	}
+
+	// the default features plus everything turned on with set_feature
+	pub fn get_feature_settings(&self) -> Vec<FeatureSetting> {
+		let mut settings: Vec<FeatureSetting> = DEFAULT_FEATURES.iter().map(|&tag| (tag, self.get_feature_value(tag))).collect();
+		settings.extend(self.feature_settings.iter().filter(|(tag, _)| !DEFAULT_FEATURES.contains(tag)));
+		settings
+	}
+
+	// static fonts have none of these, so each one is optional
+	pub fn get_variation_tables(&mut self) -> Result<(), FontError> {
+		self.fvar = self.get_optional_table("fvar", Fvar::parse)?;
+		self.avar = self.get_optional_table("avar", Avar::parse)?;
+		self.gvar = self.get_optional_table("gvar", Gvar::parse)?;
+		self.hvar = self.get_optional_table("HVAR", Hvar::parse)?;
+		self.mvar = self.get_optional_table("MVAR", Mvar::parse)?;
+		Ok(())
+	}
+
+	fn get_optional_table<T>(&self, tag: &str, parse: fn(Reader) -> Result<T, FontError>) -> Result<Option<T>, FontError> {
+		match self.table_reader(tag) {
+			Ok(reader) => Ok(Some(parse(reader)?)),
+			Err(FontError::MissingTable(_)) => Ok(None),
+			Err(error) => Err(error),
+		}
+	}
+
+	pub fn variation_axes(&self) -> &[VariationAxis] {
+		self.fvar.as_ref().map_or(&[], |fvar| &fvar.axes)
+	}
+
+	// user space values like ("wght", 650.0), axes that aren't listed go back to their default
+	// glyphs that are already loaded are rebuilt at the new coordinates
+	pub fn set_variation(&mut self, settings: &[(&str, f32)]) -> Result<(), FontError> {
+		if self.fvar.is_none() {
+			self.get_variation_tables()?;
+		}
+		let fvar = self.fvar.as_ref().ok_or_else(|| FontError::MissingTable("fvar".to_string()))?;
+		self.normalized_coordinates = fvar.axes.iter().enumerate().map(|(i, axis)| {
+			let value = settings.iter()
+				.find(|(tag, _)| tag.as_bytes() == axis.tag)
+				.map_or(axis.default_value, |&(_, value)| value);
+			let normalized = axis.normalize(value);
+			self.avar.as_ref().map_or(normalized, |avar| avar.map(i, normalized))
+		}).collect();
+		match self.hinting.as_ref().map(|hinting| hinting.ppem) {
+			// the font programs can read the coordinates, so they run again
+			Some(ppem) => self.set_hinting(Some(ppem))?,
+			None if !self.glyphs.is_empty() => self.get_glyphs()?,
+			None => {}
+		}
+		Ok(())
+	}
+
+	pub fn set_named_instance(&mut self, instance_index: usize) -> Result<(), FontError> {
+		if self.fvar.is_none() {
+			self.get_variation_tables()?;
+		}
+		let fvar = self.fvar.as_ref().ok_or_else(|| FontError::MissingTable("fvar".to_string()))?;
+		let instance = fvar.instances.get(instance_index).ok_or(FontError::Malformed("no such named instance"))?;
+		let tags: Vec<String> = fvar.axes.iter().map(|axis| String::from_utf8_lossy(&axis.tag).into_owned()).collect();
+		let settings: Vec<(&str, f32)> = tags.iter().map(|tag| tag.as_str()).zip(instance.coordinates.iter().copied()).collect();
+		self.set_variation(&settings)
+	}
+
+	// only truetype outlines are hinted, fonts without fpgm, prep or cvt still have glyph programs
+	pub fn get_hinting_tables(&mut self) -> Result<(), FontError> {
+		self.get_table_directory("glyf")?;
+		let mut head = self.table_reader("head")?;
+		head.seek(18)?;
+		self.hinting_tables = Some(HintingTables {
+			units_per_em: 			head.read_u16()?,
+			limits: 				MaxpLimits::parse(self.table_reader("maxp")?)?,
+			font_program: 			self.get_optional_table("fpgm", |fpgm| Ok(fpgm.data().to_vec()))?.unwrap_or_default(),
+			control_value_program: 	self.get_optional_table("prep", |prep| Ok(prep.data().to_vec()))?.unwrap_or_default(),
+			control_values: 		self.get_optional_table("cvt ", HintingTables::parse_control_values)?.unwrap_or_default(),
+		});
+		Ok(())
+	}
+
+	// grid-fits outlines at ppem pixels per em, None turns hinting off again
+	// glyphs that are already loaded are rebuilt at the new size
+	pub fn set_hinting(&mut self, ppem: Option<u16>) -> Result<(), FontError> {
+		self.hinting = None;
+		if let Some(ppem) = ppem {
+			if self.hinting_tables.is_none() {
+				self.get_hinting_tables()?;
 			}
-			glyph.coordinates = new_coordinates;
-			glyph.end_pts_of_contours = new_end_points;
-			glyph.num_points = glyph.coordinates.len() as u16;
+			let tables = self.hinting_tables.as_ref().ok_or_else(|| FontError::MissingTable("glyf".to_string()))?;
+			self.hinting = Some(HintingInstance::new(tables, ppem, &self.normalized_coordinates)?);
 		}
+		if !self.glyphs.is_empty() {
+			self.get_glyphs()?;
+		}
+		Ok(())
 	}
 
-	pub fn get_dimensions(&self) -> (i16, i16, i16, i16) {
-		let offset = self.get_table_directory("head").offset;
-		let xmin_offset = offset as usize + 36;
-		let ymin_offset = offset as usize + 38;
-		let xmax_offset = offset as usize + 40;
-		let ymax_offset = offset as usize + 42;
-		(get_i16(&self.file_buffer, xmax_offset), get_i16(&self.file_buffer, ymax_offset), get_i16(&self.file_buffer, xmin_offset), get_i16(&self.file_buffer, ymin_offset))
+	fn metric_delta(&self, tag: Tag) -> i16 {
+		match &self.mvar {
+			Some(mvar) if !self.normalized_coordinates.is_empty() => mvar.get_delta(tag, &self.normalized_coordinates).round() as i16,
+			_ => 0,
+		}
+	}
+
+	pub fn units_per_em(&self) -> Result<u16, FontError> {
+		self.table_reader("head")?.at(18)?.read_u16()
+	}
+
+	// the loaded outline on the cpu, no gl context needed
+	pub fn rasterize_glyph(&self, glyph_index: u16, ppem: f32, fill_rule: FillRule) -> Result<Bitmap, FontError> {
+		let glyph = self.glyphs.get(glyph_index as usize).ok_or(FontError::Malformed("glyph index out of range"))?;
+		Ok(rasterizer::rasterize_glyph(glyph, self.units_per_em()?, ppem, fill_rule))
+	}
+
+	pub fn get_dimensions(&self) -> Result<(i16, i16, i16, i16), FontError> {
+		let mut head = self.table_reader("head")?;
+		head.seek(36)?;
+		let xmin = head.read_i16()?;
+		let ymin = head.read_i16()?;
+		let xmax = head.read_i16()?;
+		let ymax = head.read_i16()?;
+		Ok((xmax, ymax, xmin, ymin))
 	}
 }
 
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sfnt_builder::*;
+
+	fn load(font: Vec<u8>) -> FontFile {
+		let mut font_file = FontFile::from_buffer(Arc::new(font), 0).unwrap();
+		font_file.get_glyphs().unwrap();
+		font_file
+	}
+
+	// straight from glyf, before insert_inbetween_points adds the implied on-curve points
+	fn decode(font_file: &FontFile, glyph_index: u16) -> Vec<(i32, i32, bool)> {
+		let glyph = font_file.get_glyph_description(glyph_index, &mut vec![]).unwrap();
+		glyph.coordinates.iter().map(|point| (point.x as i32, point.y as i32, bit_set(point.flags, 0))).collect()
+	}
+
+	fn empty_cmap() -> Vec<u8> {
+		cmap_format_4(&[])
+	}
+
+	fn random_contours(rng: &mut TestRng) -> Vec<Contour> {
+		(0..rng.range(1, 5)).map(|_| {
+			let (mut x, mut y) = (rng.range(-500, 500), rng.range(-500, 500));
+			(0..rng.range(1, 24)).map(|_| {
+				// zero, short and long deltas in both directions
+				let delta = |rng: &mut TestRng| match rng.range(0, 4) {
+					0 => 0,
+					1 => rng.range(-255, 256),
+					2 => rng.range(-2000, 2001),
+					_ => rng.range(-3, 4),
+				};
+				x = (x + delta(rng)).clamp(-8000, 8000);
+				y = (y + delta(rng)).clamp(-8000, 8000);
+				(x as i16, y as i16, rng.chance(60))
+			}).collect()
+		}).collect()
+	}
+
+	#[test]
+	fn repeated_flags_cover_following_points() {
+		// one on-curve flag with positive short x and y, repeated for the three points after it
+		let mut glyph = glyph_header(1, [10, 1, 100, 10]);
+		glyph.extend(3u16.to_be_bytes());
+		glyph.extend(0u16.to_be_bytes());
+		glyph.extend([0x01 | 0x02 | 0x04 | 0x10 | 0x20 | 0x08, 3]);
+		glyph.extend([10, 20, 30, 40]);
+		glyph.extend([1, 2, 3, 4]);
+		let font_file = load(minimal_font(&[glyph], false, empty_cmap()));
+		assert_eq!(decode(&font_file, 0), vec![(10, 1, true), (30, 3, true), (60, 6, true), (100, 10, true)]);
+	}
+
+	#[test]
+	fn reserved_flag_bits_are_dropped() {
+		// an off-curve point with bit 7 set is still quadratic, a cubic control only comes from cff
+		let mut glyph = glyph_header(1, [0, 0, 100, 100]);
+		glyph.extend(2u16.to_be_bytes());
+		glyph.extend(0u16.to_be_bytes());
+		glyph.extend([0x01 | 0x10 | 0x20, 0x80 | 0x40 | 0x02 | 0x04 | 0x10 | 0x20, 0x01 | 0x02 | 0x04 | 0x10]);
+		glyph.extend([50, 50]);
+		glyph.extend([100, 100]);
+		let font_file = load(minimal_font(&[glyph], false, empty_cmap()));
+		let glyph = font_file.get_glyph_description(0, &mut vec![]).unwrap();
+		assert!(glyph.coordinates.iter().all(|point| point.flags & CUBIC_CONTROL_FLAG == 0));
+		assert_eq!(decode(&font_file, 0), vec![(0, 0, true), (50, 100, false), (100, 0, true)]);
+	}
+
+	#[test]
+	fn repeat_count_past_the_last_point_is_ignored() {
+		let mut glyph = glyph_header(1, [0, 0, 2, 2]);
+		glyph.extend(1u16.to_be_bytes());
+		glyph.extend(0u16.to_be_bytes());
+		glyph.extend([0x01 | 0x02 | 0x04 | 0x10 | 0x20 | 0x08, 200]);
+		glyph.extend([1, 1]);
+		glyph.extend([1, 1]);
+		let font_file = load(minimal_font(&[glyph], false, empty_cmap()));
+		assert_eq!(decode(&font_file, 0), vec![(1, 1, true), (2, 2, true)]);
+	}
+
+	#[test]
+	fn short_vector_sign_bits() {
+		// x short with the sign bit clear is negative, set is positive, the same for y
+		let mut glyph = glyph_header(1, [-50, -50, 50, 50]);
+		glyph.extend(2u16.to_be_bytes());
+		glyph.extend(0u16.to_be_bytes());
+		glyph.extend([
+			0x01 | 0x02 | 0x04,
+			0x01 | 0x02 | 0x10 | 0x04,
+			0x02 | 0x04 | 0x20,
+		]);
+		glyph.extend([50, 100, 25]);
+		glyph.extend([40, 30, 70]);
+		let font_file = load(minimal_font(&[glyph], false, empty_cmap()));
+		assert_eq!(decode(&font_file, 0), vec![(-50, -40, true), (50, -70, true), (25, 0, false)]);
+	}
+
+	#[test]
+	fn same_as_previous_and_long_deltas() {
+		// without the short bit, the same bit repeats the previous coordinate and a clear one reads an i16
+		let mut glyph = glyph_header(1, [-1000, 0, 300, 1200]);
+		glyph.extend(2u16.to_be_bytes());
+		glyph.extend(0u16.to_be_bytes());
+		glyph.extend([0x01, 0x01 | 0x10, 0x20]);
+		glyph.extend(300i16.to_be_bytes());
+		glyph.extend((-1300i16).to_be_bytes());
+		glyph.extend(1200i16.to_be_bytes());
+		glyph.extend((-1200i16).to_be_bytes());
+		let font_file = load(minimal_font(&[glyph], false, empty_cmap()));
+		assert_eq!(decode(&font_file, 0), vec![(300, 1200, true), (300, 0, true), (-1000, 0, false)]);
+	}
+
+	#[test]
+	fn empty_glyphs() {
+		// glyph 0 has no bytes in glyf at all, glyph 1 is a header with zero contours
+		let glyphs = vec![vec![], glyph_header(0, [0, 0, 0, 0]), encode_simple_glyph(&[vec![(0, 0, true), (10, 0, true), (10, 10, true)]])];
+		let font_file = load(minimal_font(&glyphs, false, empty_cmap()));
+		for glyph_index in 0..2 {
+			let glyph = &font_file.glyphs[glyph_index];
+			assert!(glyph.coordinates.is_empty() && glyph.end_pts_of_contours.is_empty());
+			assert_eq!(glyph.advance_width, 500);
+		}
+		assert_eq!(decode(&font_file, 2).len(), 3);
+	}
+
+	#[test]
+	fn short_and_long_loca_agree() {
+		let mut rng = TestRng::new(7);
+		let glyphs: Vec<Vec<u8>> = (0..20).map(|i| if i % 5 == 0 {vec![]} else {encode_simple_glyph(&random_contours(&mut rng))}).collect();
+		let short = load(minimal_font(&glyphs, false, empty_cmap()));
+		let long = load(minimal_font(&glyphs, true, empty_cmap()));
+		assert_eq!(short.glyph_locations, long.glyph_locations);
+		for glyph_index in 0..glyphs.len() as u16 {
+			assert_eq!(decode(&short, glyph_index), decode(&long, glyph_index));
+		}
+	}
+
+	#[test]
+	fn cmap_format_4_id_range_offset() {
+		let segments = [
+			(0x20, 0x22, 5, None),
+			// the array values get the delta added, 0 stays .notdef
+			(0x41, 0x44, 100, Some(vec![1, 0, 3, 65535])),
+			(0x61, 0x62, 0, Some(vec![7, 8])),
+			// deltas wrap around modulo 65536
+			(0xF000, 0xF001, 0x1010, None),
+		];
+		let mut font_file = load(minimal_font(&[vec![]], false, cmap_format_4(&segments)));
+		font_file.get_unicode_to_glyph_index_map().unwrap();
+		let map = &font_file.unicode_to_glyph_index_map;
+		let expected = [
+			(0x20, 0x25), (0x21, 0x26), (0x22, 0x27),
+			(0x41, 101), (0x42, 0), (0x43, 103), (0x44, 99),
+			(0x61, 7), (0x62, 8),
+			(0xF000, 0x0010), (0xF001, 0x0011),
+		];
+		for (code_point, glyph_index) in expected {
+			assert_eq!(map.get(&code_point), Some(&glyph_index), "U+{:04X}", code_point);
+		}
+	}
+
+	#[test]
+	fn glyph_ids_past_the_font_map_to_notdef() {
+		// format 12 groups pointing inside the font, past numGlyphs and past 65535
+		let subtable = cmap_groups(12, &[(0x41, 0x42, 1), (0x43, 0x43, 7), (0x44, 0x44, 0x10001)]);
+		let mut font_file = load(minimal_font(&[vec![], vec![], vec![]], false, subtable));
+		font_file.get_unicode_to_glyph_index_map().unwrap();
+		assert_eq!(font_file.unicode_to_glyph_index_map.get(&0x44), Some(&0));
+		let glyph_indices: Vec<u16> = "ABCDE".chars().map(|character| font_file.get_glyph_index(character)).collect();
+		assert_eq!(glyph_indices, vec![1, 2, 0, 0, 0]);
+	}
+
+	fn cmap(font: Vec<u8>) -> FontFile {
+		let mut font_file = load(font);
+		font_file.get_unicode_to_glyph_index_map().unwrap();
+		font_file
+	}
+
+	#[test]
+	fn cmap_format_0_through_mac_roman() {
+		// a mac roman subtable is keyed by unicode once loaded, 0x80 is A with diaeresis
+		let mut glyph_ids = [0; 256];
+		glyph_ids[0x41] = 5;
+		glyph_ids[0x80] = 6;
+		let mac_roman = cmap_table(&[(1, 0, cmap_format_0(&glyph_ids))]);
+		let font_file = cmap(minimal_font_builder(&vec![vec![]; 8], false, empty_cmap()).table(b"cmap", mac_roman).build());
+		assert_eq!(font_file.get_glyph_index('A'), 5);
+		assert_eq!(font_file.get_glyph_index('\u{C4}'), 6);
+		assert_eq!(font_file.unicode_to_glyph_index_map.get(&0x80), None);
+		assert_eq!(font_file.unicode_to_glyph_index_map.len(), 256);
+	}
+
+	#[test]
+	fn cmap_format_2_sub_headers() {
+		let subtable = cmap_format_2((0x20, 0, vec![1, 2, 3]), &[
+			// array values get the delta added modulo 65536, 0 stays .notdef
+			(0x81, (0x40, 10, vec![5, 0, 65530])),
+			// second bytes stop at 0xFF whatever the entry count says
+			(0x82, (0xFE, 0, vec![1, 2, 3, 4])),
+		]);
+		let font_file = cmap(minimal_font(&[vec![]], false, subtable));
+		let expected: HashMap<u32, u16> = HashMap::from([
+			(0x20, 1), (0x21, 2), (0x22, 3),
+			(0x8140, 15), (0x8141, 0), (0x8142, 4),
+			(0x82FE, 1), (0x82FF, 2),
+		]);
+		// the high bytes of two byte codes aren't single byte codes themselves
+		assert_eq!(font_file.unicode_to_glyph_index_map, expected);
+	}
+
+	#[test]
+	fn cmap_formats_6_10_and_13() {
+		let trimmed = cmap(minimal_font(&[vec![]], false, cmap_format_6(0x30, &[4, 0, 5])));
+		assert_eq!(trimmed.unicode_to_glyph_index_map, HashMap::from([(0x30, 4), (0x31, 0), (0x32, 5)]));
+
+		let array = cmap(minimal_font(&vec![vec![]; 10], false, cmap_format_10(0x1F600, &[7, 8])));
+		assert_eq!(array.unicode_to_glyph_index_map, HashMap::from([(0x1F600, 7), (0x1F601, 8)]));
+		assert_eq!(array.get_glyph_index('\u{1F601}'), 8);
+
+		// every code in a group maps to the same glyph, overlapping groups only add codes past the earlier ones
+		let many_to_one = cmap(minimal_font(&[vec![]], false, cmap_groups(13, &[(0x41, 0x43, 9), (0x42, 0x44, 3), (0x10000, 0x10001, 2)])));
+		assert_eq!(many_to_one.unicode_to_glyph_index_map, HashMap::from([
+			(0x41, 9), (0x42, 9), (0x43, 9), (0x44, 3), (0x10000, 2), (0x10001, 2),
+		]));
+	}
+
+	#[test]
+	fn variation_sequences_default_and_non_default() {
+		// U+2229 and U+222A map to glyphs 3 and 4
+		let base = cmap_format_4(&[(0x2229, 0x222A, 3 - 0x2229, None)]);
+		let variations = cmap_format_14(&[
+			(0xFE00, vec![(0x2229, 1)], vec![]),
+			(0xE0100, vec![], vec![(0x2229, 7), (0x222A, 99)]),
+		]);
+		let with_variations = cmap_table(&[(3, 1, base), (0, 5, variations)]);
+		let font_file = cmap(minimal_font_builder(&vec![vec![]; 10], false, empty_cmap()).table(b"cmap", with_variations).build());
+		let glyph = |character: char, selector: Option<char>| font_file.get_glyph_index_with_variation(character, selector);
+		// default sequences keep the glyph the base character already has
+		assert_eq!(glyph('\u{2229}', Some('\u{FE00}')), 3);
+		assert_eq!(glyph('\u{222A}', Some('\u{FE00}')), 4);
+		// non-default ones name their own, past the end of the font it's .notdef rather than the base glyph
+		assert_eq!(glyph('\u{2229}', Some('\u{E0100}')), 7);
+		assert_eq!(glyph('\u{222A}', Some('\u{E0100}')), 0);
+		// sequences the font doesn't list fall back to the base character
+		assert_eq!(glyph('\u{2229}', Some('\u{FE01}')), 3);
+		assert_eq!(glyph('\u{2229}', None), 3);
+		assert_eq!(font_file.variation_sequences.len(), 4);
+	}
+
+	// the same three glyphs in both faces, only their cmaps differ, 'A' is glyph 1 in the first face and 2 in the second
+	fn two_faces() -> [SfntBuilder; 2] {
+		[1, 2].map(|glyph_index| minimal_font_builder(&vec![vec![]; 3], false, cmap_format_4(&[(0x41, 0x41, glyph_index - 0x41, None)])))
+	}
+
+	fn glyph_for_a(mut font_file: FontFile) -> u16 {
+		font_file.get_glyphs().unwrap();
+		font_file.get_unicode_to_glyph_index_map().unwrap();
+		font_file.get_glyph_index('A')
+	}
+
+	#[test]
+	fn collection_headers() {
+		for (major_version, header_len) in [(1, 20), (2, 32)] {
+			let buffer = Arc::new(collection(major_version, &two_faces()));
+			let face_offsets = get_face_offsets(&buffer).unwrap();
+			assert_eq!(face_offsets.len(), 2);
+			assert_eq!(face_offsets[0], header_len);
+			let faces = [0, 1].map(|face_index| FontFile::from_buffer(Arc::clone(&buffer), face_index).unwrap());
+			// tables both faces have in common point at the same bytes
+			let offset = |face: &FontFile, tag: &str| face.get_table_directory(tag).unwrap().offset;
+			assert_eq!(offset(&faces[0], "glyf"), offset(&faces[1], "glyf"));
+			assert_ne!(offset(&faces[0], "cmap"), offset(&faces[1], "cmap"));
+			assert_eq!(faces.map(glyph_for_a), [1, 2]);
+		}
+	}
+
+	#[test]
+	fn face_index_out_of_range() {
+		let buffer = Arc::new(collection(1, &two_faces()));
+		let error = FontFile::from_buffer(buffer, 2).err();
+		assert!(matches!(error, Some(FontError::FaceIndexOutOfRange { index: 2, count: 2 })));
+		// a plain font is a collection of one
+		let font = Arc::new(minimal_font(&[vec![]], false, empty_cmap()));
+		assert_eq!(get_face_offsets(&font).unwrap(), vec![0]);
+		assert!(matches!(FontFile::from_buffer(font, 1).err(), Some(FontError::FaceIndexOutOfRange { index: 1, count: 1 })));
+	}
+
+	#[test]
+	fn faces_of_a_collection_file() {
+		let path = std::env::temp_dir().join(format!("font_render_collection_{}.ttc", std::process::id()));
+		std::fs::write(&path, collection(2, &two_faces())).unwrap();
+		let file_path = path.to_str().unwrap();
+
+		let faces = FontFile::all_faces(file_path).unwrap();
+		assert_eq!(faces.len(), 2);
+		// every face reads from the one buffer
+		assert!(Arc::ptr_eq(&faces[0].file_buffer, &faces[1].file_buffer));
+		assert_eq!(glyph_for_a(FontFile::from_collection(file_path, 1).unwrap()), 2);
+		assert!(matches!(FontFile::from_collection(file_path, 5).err(), Some(FontError::FaceIndexOutOfRange { index: 5, count: 2 })));
+		std::fs::remove_file(&path).unwrap();
+	}
+
+	#[test]
+	fn parse_without_name_and_post() {
+		let font_file = FontFile::parse(&minimal_font(&[vec![]], false, empty_cmap())).unwrap();
+		assert_eq!(font_file.family_name(), None);
+		assert_eq!(font_file.glyph_name(0), None);
+		assert_eq!(font_file.underline_position(), 0);
+	}
+
+	#[test]
+	fn overlapping_cmap_segments_map_each_code_once() {
+		// thousands of segments over the whole range used to be walked one after another
+		let mut segments = vec![(0x20, 0x7E, 3, None)];
+		segments.extend((0..4000).map(|i| (0, 0xFFFE, i as i16, None)));
+		let mut font_file = load(minimal_font(&[vec![]], false, cmap_format_4(&segments)));
+		font_file.get_unicode_to_glyph_index_map().unwrap();
+		let map = &font_file.unicode_to_glyph_index_map;
+		// the first wide segment only adds the codes past the earlier one, the rest add nothing
+		assert_eq!(map.get(&0x41), Some(&0x44));
+		assert_eq!(map.get(&0x80), Some(&0x80));
+		assert_eq!(map.get(&0x10), None);
+		assert_eq!(map.len(), 0xFFFF - 0x20 + 1);
+	}
+
+	#[test]
+	fn random_contours_round_trip() {
+		let mut rng = TestRng::new(0x5EED);
+		for _ in 0..200 {
+			let contours = random_contours(&mut rng);
+			let font_file = load(minimal_font(&[encode_simple_glyph(&contours)], rng.chance(50), empty_cmap()));
+			let glyph = font_file.get_glyph_description(0, &mut vec![]).unwrap();
+			let expected: Vec<(i32, i32, bool)> = contours.iter().flatten().map(|&(x, y, on_curve)| (x as i32, y as i32, on_curve)).collect();
+			assert_eq!(decode(&font_file, 0), expected);
+
+			let mut end_point = 0;
+			let expected_end_points: Vec<u16> = contours.iter().map(|contour| {
+				end_point += contour.len() as u16;
+				end_point - 1
+			}).collect();
+			assert_eq!(glyph.end_pts_of_contours, expected_end_points);
+		}
+	}
+
+	#[test]
+	fn random_cmap_format_4_round_trip() {
+		let mut rng = TestRng::new(0xC0DE);
+		for _ in 0..50 {
+			let mut segments: Vec<(u16, u16, i16, Option<Vec<u16>>)> = vec![];
+			let mut expected: HashMap<u32, u16> = HashMap::new();
+			let mut code_point = rng.range(0, 64) as u16;
+			for _ in 0..rng.range(1, 12) {
+				let start = code_point;
+				let end = start + rng.range(0, 40) as u16;
+				let delta = rng.range(-32768, 32768) as i16;
+				let glyph_ids = rng.chance(50).then(|| (start..=end).map(|_| if rng.chance(10) {0} else {rng.range(1, 65536) as u16}).collect::<Vec<u16>>());
+				for (i, code) in (start..=end).enumerate() {
+					let glyph_index = match &glyph_ids {
+						Some(glyph_ids) if glyph_ids[i] == 0 => 0,
+						Some(glyph_ids) => glyph_ids[i].wrapping_add(delta as u16),
+						None => code.wrapping_add(delta as u16),
+					};
+					expected.insert(code as u32, glyph_index);
+				}
+				segments.push((start, end, delta, glyph_ids));
+				code_point = end + rng.range(1, 300) as u16;
+			}
+			let mut font_file = load(minimal_font(&[vec![]], false, cmap_format_4(&segments)));
+			font_file.get_unicode_to_glyph_index_map().unwrap();
+			expected.insert(0xFFFF, 0);
+			assert_eq!(font_file.unicode_to_glyph_index_map, expected);
+		}
+	}
+}
@@ -0,0 +1,658 @@
+use crate::font_error::FontError;
+use crate::layout::*;
+use crate::reader::Reader;
+use crate::shaping::Direction;
+
+pub const EXTENSION_POSITIONING: u16 = 9;
+const MAX_NESTING_LEVEL: usize = 8;
+
+// value format bits
+const X_PLACEMENT: u16 = 0x0001;
+const Y_PLACEMENT: u16 = 0x0002;
+const X_ADVANCE: u16 = 0x0004;
+const Y_ADVANCE: u16 = 0x0008;
+const X_PLACEMENT_DEVICE: u16 = 0x0010;
+const Y_PLACEMENT_DEVICE: u16 = 0x0020;
+const X_ADVANCE_DEVICE: u16 = 0x0040;
+const Y_ADVANCE_DEVICE: u16 = 0x0080;
+
+#[derive(Debug, Clone, Default)]
+pub struct ValueRecord {
+	pub x_placement: i16,
+	pub y_placement: i16,
+	pub x_advance: i16,
+	pub y_advance: i16,
+	pub x_placement_device: Option<Device>,
+	pub y_placement_device: Option<Device>,
+	pub x_advance_device: Option<Device>,
+	pub y_advance_device: Option<Device>,
+}
+
+impl ValueRecord {
+	// device offsets are from the start of the table holding the record
+	fn parse(parent: Reader, reader: &mut Reader, value_format: u16) -> Result<ValueRecord, FontError> {
+		let mut value = ValueRecord::default();
+		if value_format & X_PLACEMENT != 0 {
+			value.x_placement = reader.read_i16()?;
+		}
+		if value_format & Y_PLACEMENT != 0 {
+			value.y_placement = reader.read_i16()?;
+		}
+		if value_format & X_ADVANCE != 0 {
+			value.x_advance = reader.read_i16()?;
+		}
+		if value_format & Y_ADVANCE != 0 {
+			value.y_advance = reader.read_i16()?;
+		}
+		if value_format & X_PLACEMENT_DEVICE != 0 {
+			value.x_placement_device = Device::parse_optional(parent, reader.read_offset16()?)?;
+		}
+		if value_format & Y_PLACEMENT_DEVICE != 0 {
+			value.y_placement_device = Device::parse_optional(parent, reader.read_offset16()?)?;
+		}
+		if value_format & X_ADVANCE_DEVICE != 0 {
+			value.x_advance_device = Device::parse_optional(parent, reader.read_offset16()?)?;
+		}
+		if value_format & Y_ADVANCE_DEVICE != 0 {
+			value.y_advance_device = Device::parse_optional(parent, reader.read_offset16()?)?;
+		}
+		Ok(value)
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Anchor {
+	pub x: i16,
+	pub y: i16,
+	// a contour point on the hinted outline, unused without hinting
+	pub anchor_point: Option<u16>,
+	pub x_device: Option<Device>,
+	pub y_device: Option<Device>,
+}
+
+impl Anchor {
+	fn parse(anchor: Reader) -> Result<Anchor, FontError> {
+		let mut reader = anchor;
+		let format = reader.read_u16()?;
+		let mut result = Anchor {
+			x: reader.read_i16()?,
+			y: reader.read_i16()?,
+			anchor_point: None,
+			x_device: None,
+			y_device: None,
+		};
+		match format {
+			2 => result.anchor_point = Some(reader.read_u16()?),
+			3 => {
+				result.x_device = Device::parse_optional(anchor, reader.read_offset16()?)?;
+				result.y_device = Device::parse_optional(anchor, reader.read_offset16()?)?;
+			}
+			_ => (),
+		}
+		Ok(result)
+	}
+
+	fn parse_optional(parent: Reader, offset: usize) -> Result<Option<Anchor>, FontError> {
+		if offset == 0 {
+			return Ok(None);
+		}
+		Ok(Some(Anchor::parse(parent.slice_from(offset)?)?))
+	}
+}
+
+// a mark's class and its anchor
+pub type MarkRecord = (u16, Anchor);
+
+// one row per base glyph, one anchor per mark class
+pub type AnchorMatrix = Vec<Vec<Option<Anchor>>>;
+
+#[derive(Debug, Clone)]
+pub enum GposSubtable {
+	Single { coverage: Coverage, values: Vec<ValueRecord> },
+	Pair { coverage: Coverage, second_value_format: u16, pair_sets: Vec<Vec<(u16, ValueRecord, ValueRecord)>> },
+	PairClasses {
+		coverage: Coverage,
+		second_value_format: u16,
+		class_def1: ClassDef,
+		class_def2: ClassDef,
+		class2_count: u16,
+		records: Vec<(ValueRecord, ValueRecord)>,
+	},
+	Cursive { coverage: Coverage, entry_exits: Vec<(Option<Anchor>, Option<Anchor>)> },
+	MarkToBase { mark_coverage: Coverage, base_coverage: Coverage, marks: Vec<MarkRecord>, bases: AnchorMatrix },
+	MarkToLigature { mark_coverage: Coverage, ligature_coverage: Coverage, marks: Vec<MarkRecord>, ligatures: Vec<AnchorMatrix> },
+	MarkToMark { mark1_coverage: Coverage, mark2_coverage: Coverage, marks: Vec<MarkRecord>, mark2s: AnchorMatrix },
+	Context(SequenceContext),
+	ChainedContext(ChainedSequenceContext),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub enum Attachment {
+	Mark(usize),
+	Cursive(usize),
+}
+
+// in font units, offsets move the glyph without moving the pen
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GlyphPosition {
+	pub x_advance: i32,
+	pub y_advance: i32,
+	pub x_offset: i32,
+	pub y_offset: i32,
+	pub attachment: Option<Attachment>,
+}
+
+// the design space location, the pixel size hinting device tables apply at, and which way the pen moves
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PositioningOptions<'a> {
+	pub coordinates: &'a [f32],
+	pub ppem: Option<u16>,
+	pub direction: Direction,
+}
+
+pub type Gpos = LayoutTable<GposSubtable>;
+
+impl Gpos {
+	pub fn parse_gpos(gpos: Reader) -> Result<Gpos, FontError> {
+		LayoutTable::parse(gpos, EXTENSION_POSITIONING, parse_subtable)
+	}
+
+	// marks lose their advance once positioned, attached glyphs end up relative to what they're attached to
+	// glyphs are in logical order, right to left runs have their pen moving backwards through them
+	pub fn apply(&self, gdef: &Gdef, glyphs: &[u16], positions: &mut [GlyphPosition], lookup_indices: &[(u16, u32)], options: PositioningOptions) {
+		let positioner = Positioner { gpos: self, gdef, glyphs, options };
+		for &(lookup_index, _) in lookup_indices {
+			let mut index = 0;
+			while index < glyphs.len() {
+				index += positioner.apply_lookup(lookup_index, index, positions, 0).unwrap_or(1).max(1);
+			}
+		}
+		for (position, &glyph) in positions.iter_mut().zip(glyphs) {
+			if gdef.is_mark(glyph) {
+				position.x_advance = 0;
+				position.y_advance = 0;
+			}
+		}
+		resolve_attachments(positions, options.direction);
+	}
+}
+
+struct Positioner<'a> {
+	gpos: &'a Gpos,
+	gdef: &'a Gdef,
+	glyphs: &'a [u16],
+	options: PositioningOptions<'a>,
+}
+
+impl Positioner<'_> {
+	// how many glyphs the lookup consumed, None if it didn't apply
+	fn apply_lookup(&self, lookup_index: u16, index: usize, positions: &mut [GlyphPosition], depth: usize) -> Option<usize> {
+		let lookup = self.gpos.lookups.get(lookup_index as usize)?;
+		if depth > MAX_NESTING_LEVEL || self.gdef.should_skip(lookup.lookup_flag, lookup.mark_filtering_set, self.glyphs[index]) {
+			return None;
+		}
+		lookup.subtables.iter().find_map(|subtable| self.apply_subtable(lookup, subtable, index, positions, depth))
+	}
+
+	fn apply_subtable(&self, lookup: &Lookup<GposSubtable>, subtable: &GposSubtable, index: usize, positions: &mut [GlyphPosition], depth: usize) -> Option<usize> {
+		let glyph = self.glyphs[index];
+		let skip = |glyph: u16| self.gdef.should_skip(lookup.lookup_flag, lookup.mark_filtering_set, glyph);
+		match subtable {
+			GposSubtable::Single { coverage, values } => {
+				let coverage_index = coverage.get_index(glyph)? as usize;
+				let value = if values.len() == 1 {&values[0]} else {values.get(coverage_index)?};
+				self.apply_value(value, &mut positions[index]);
+				Some(1)
+			}
+			GposSubtable::Pair { coverage, second_value_format, pair_sets } => {
+				let pair_set = pair_sets.get(coverage.get_index(glyph)? as usize)?;
+				let second = self.next_glyph(index, &skip)?;
+				let (_, first_value, second_value) = pair_set.iter().find(|(second_glyph, _, _)| *second_glyph == self.glyphs[second])?;
+				self.apply_value(first_value, &mut positions[index]);
+				self.apply_value(second_value, &mut positions[second]);
+				Some(second - index + (*second_value_format != 0) as usize)
+			}
+			GposSubtable::PairClasses { coverage, second_value_format, class_def1, class_def2, class2_count, records } => {
+				coverage.get_index(glyph)?;
+				let second = self.next_glyph(index, &skip)?;
+				let class1 = class_def1.get_class(glyph) as usize;
+				let class2 = class_def2.get_class(self.glyphs[second]) as usize;
+				let (first_value, second_value) = records.get(class1 * *class2_count as usize + class2)?;
+				self.apply_value(first_value, &mut positions[index]);
+				self.apply_value(second_value, &mut positions[second]);
+				Some(second - index + (*second_value_format != 0) as usize)
+			}
+			// the glyph's entry joins the previous glyph's exit, the later glyph hangs off the earlier one
+			// in a right to left run the previous glyph is to the right, so the advances change sides
+			GposSubtable::Cursive { coverage, entry_exits } => {
+				let (entry, _) = entry_exits.get(coverage.get_index(glyph)? as usize)?;
+				let entry = entry.as_ref()?;
+				let previous = self.previous_glyph(index, &skip)?;
+				let (_, exit) = entry_exits.get(coverage.get_index(self.glyphs[previous])? as usize)?;
+				let exit = exit.as_ref()?;
+				let (entry_x, entry_y) = self.anchor_position(entry);
+				let (exit_x, exit_y) = self.anchor_position(exit);
+
+				if self.options.direction == Direction::RightToLeft {
+					let distance = exit_x + positions[previous].x_offset;
+					positions[previous].x_advance -= distance;
+					positions[previous].x_offset -= distance;
+					positions[index].x_advance = entry_x + positions[index].x_offset;
+				} else {
+					positions[previous].x_advance = exit_x + positions[previous].x_offset;
+					let distance = entry_x + positions[index].x_offset;
+					positions[index].x_advance -= distance;
+					positions[index].x_offset -= distance;
+				}
+				if lookup.lookup_flag & RIGHT_TO_LEFT != 0 {
+					positions[previous].y_offset = entry_y - exit_y;
+					positions[previous].attachment = Some(Attachment::Cursive(index));
+				} else {
+					positions[index].y_offset = exit_y - entry_y;
+					positions[index].attachment = Some(Attachment::Cursive(previous));
+				}
+				Some(1)
+			}
+			GposSubtable::MarkToBase { mark_coverage, base_coverage, marks, bases } => {
+				let mark = marks.get(mark_coverage.get_index(glyph)? as usize)?;
+				let base = self.previous_glyph(index, &|glyph| self.gdef.is_mark(glyph) || skip(glyph))?;
+				let base_anchors = bases.get(base_coverage.get_index(self.glyphs[base])? as usize)?;
+				self.attach_mark(mark, base_anchors, index, base, positions)
+			}
+			// without tracking ligature components the mark goes on the last one
+			GposSubtable::MarkToLigature { mark_coverage, ligature_coverage, marks, ligatures } => {
+				let mark = marks.get(mark_coverage.get_index(glyph)? as usize)?;
+				let ligature = self.previous_glyph(index, &|glyph| self.gdef.is_mark(glyph) || skip(glyph))?;
+				let components = ligatures.get(ligature_coverage.get_index(self.glyphs[ligature])? as usize)?;
+				self.attach_mark(mark, components.last()?, index, ligature, positions)
+			}
+			GposSubtable::MarkToMark { mark1_coverage, mark2_coverage, marks, mark2s } => {
+				let mark = marks.get(mark1_coverage.get_index(glyph)? as usize)?;
+				let mark2 = self.previous_glyph(index, &skip)?;
+				if !self.gdef.is_mark(self.glyphs[mark2]) {
+					return None;
+				}
+				let mark2_anchors = mark2s.get(mark2_coverage.get_index(self.glyphs[mark2])? as usize)?;
+				self.attach_mark(mark, mark2_anchors, index, mark2, positions)
+			}
+			GposSubtable::Context(context) => {
+				let (matched, lookups) = context.matches(self.glyphs, index, &skip)?;
+				self.apply_nested(&matched, lookups, positions, depth)
+			}
+			GposSubtable::ChainedContext(context) => {
+				let (matched, lookups) = context.matches(self.glyphs, index, &skip)?;
+				self.apply_nested(&matched, lookups, positions, depth)
+			}
+		}
+	}
+
+	fn apply_nested(&self, matched: &[usize], lookups: &[SequenceLookupRecord], positions: &mut [GlyphPosition], depth: usize) -> Option<usize> {
+		for record in lookups {
+			if let Some(&position) = matched.get(record.sequence_index as usize) {
+				self.apply_lookup(record.lookup_index, position, positions, depth + 1);
+			}
+		}
+		Some(matched.last()? - matched.first()? + 1)
+	}
+
+	fn attach_mark(&self, mark: &MarkRecord, anchors: &[Option<Anchor>], index: usize, target: usize, positions: &mut [GlyphPosition]) -> Option<usize> {
+		let (mark_class, mark_anchor) = mark;
+		let target_anchor = anchors.get(*mark_class as usize)?.as_ref()?;
+		let (mark_x, mark_y) = self.anchor_position(mark_anchor);
+		let (target_x, target_y) = self.anchor_position(target_anchor);
+		positions[index].x_offset = target_x - mark_x;
+		positions[index].y_offset = target_y - mark_y;
+		positions[index].attachment = Some(Attachment::Mark(target));
+		Some(1)
+	}
+
+	fn next_glyph(&self, index: usize, skip: &dyn Fn(u16) -> bool) -> Option<usize> {
+		match_forward(self.glyphs, index, 1, skip, |_, _| true)?.first().copied()
+	}
+
+	fn previous_glyph(&self, index: usize, skip: &dyn Fn(u16) -> bool) -> Option<usize> {
+		match_backward(self.glyphs, index, 1, skip, |_, _| true)?.first().copied()
+	}
+
+	fn device_delta(&self, device: &Option<Device>) -> i32 {
+		device.as_ref().map_or(0, |device| device.get_delta(self.options.ppem, self.options.coordinates, self.gdef.variation_store.as_ref()).round() as i32)
+	}
+
+	fn apply_value(&self, value: &ValueRecord, position: &mut GlyphPosition) {
+		position.x_offset += value.x_placement as i32 + self.device_delta(&value.x_placement_device);
+		position.y_offset += value.y_placement as i32 + self.device_delta(&value.y_placement_device);
+		position.x_advance += value.x_advance as i32 + self.device_delta(&value.x_advance_device);
+		position.y_advance += value.y_advance as i32 + self.device_delta(&value.y_advance_device);
+	}
+
+	fn anchor_position(&self, anchor: &Anchor) -> (i32, i32) {
+		(anchor.x as i32 + self.device_delta(&anchor.x_device), anchor.y as i32 + self.device_delta(&anchor.y_device))
+	}
+}
+
+// turns offsets relative to the attached-to glyph into offsets from the glyph's own pen position
+// a glyph's parent has to be resolved before it, chains are walked up to the first resolved glyph and then back down
+// instead of recursing, a right to left cursive chain can run the length of the run
+fn resolve_attachments(positions: &mut [GlyphPosition], direction: Direction) {
+	let mut resolved = vec![false; positions.len()];
+	let mut chain: Vec<usize> = vec![];
+	for start in 0..positions.len() {
+		let mut index = start;
+		while !resolved[index] {
+			resolved[index] = true;
+			chain.push(index);
+			match positions[index].attachment {
+				Some(Attachment::Cursive(parent) | Attachment::Mark(parent)) => index = parent,
+				None => break,
+			}
+		}
+		while let Some(index) = chain.pop() {
+			resolve_attachment(positions, index, direction);
+		}
+	}
+}
+
+fn resolve_attachment(positions: &mut [GlyphPosition], index: usize, direction: Direction) {
+	let Some(attachment) = positions[index].attachment else {
+		return;
+	};
+	match attachment {
+		Attachment::Cursive(parent) => {
+			positions[index].y_offset += positions[parent].y_offset;
+		}
+		Attachment::Mark(target) => {
+			positions[index].x_offset += positions[target].x_offset;
+			positions[index].y_offset += positions[target].y_offset;
+			// the advances between the two pen positions, which for right to left runs are those after the target up to the glyph
+			let advance = |range: &[GlyphPosition]| range.iter().map(|position| position.x_advance).sum::<i32>();
+			match (direction, target < index) {
+				(Direction::LeftToRight, true) => positions[index].x_offset -= advance(&positions[target..index]),
+				(Direction::LeftToRight, false) => positions[index].x_offset += advance(&positions[index..target]),
+				(Direction::RightToLeft, true) => positions[index].x_offset += advance(&positions[target + 1..=index]),
+				(Direction::RightToLeft, false) => positions[index].x_offset -= advance(&positions[index + 1..=target]),
+			}
+		}
+	}
+}
+
+fn parse_subtable(lookup_type: u16, subtable: Reader) -> Result<Option<GposSubtable>, FontError> {
+	let mut reader = subtable;
+	let format = reader.read_u16()?;
+	let parsed = match (lookup_type, format) {
+		(1, 1) | (1, 2) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let value_format = reader.read_u16()?;
+			let value_count = if format == 1 {1} else {reader.read_u16()?};
+			let mut values: Vec<ValueRecord> = vec![];
+			for _ in 0..value_count {
+				values.push(ValueRecord::parse(subtable, &mut reader, value_format)?);
+			}
+			GposSubtable::Single { coverage, values }
+		}
+		(2, 1) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let value_format1 = reader.read_u16()?;
+			let value_format2 = reader.read_u16()?;
+			let pair_set_count = reader.read_u16()?;
+			let mut pair_sets: Vec<Vec<(u16, ValueRecord, ValueRecord)>> = vec![];
+			for _ in 0..pair_set_count {
+				let pair_set = subtable.slice_from(reader.read_offset16()?)?;
+				let mut records = pair_set;
+				let pair_value_count = records.read_u16()?;
+				let mut pairs: Vec<(u16, ValueRecord, ValueRecord)> = vec![];
+				for _ in 0..pair_value_count {
+					let second_glyph = records.read_u16()?;
+					let first_value = ValueRecord::parse(pair_set, &mut records, value_format1)?;
+					let second_value = ValueRecord::parse(pair_set, &mut records, value_format2)?;
+					pairs.push((second_glyph, first_value, second_value));
+				}
+				pair_sets.push(pairs);
+			}
+			GposSubtable::Pair { coverage, second_value_format: value_format2, pair_sets }
+		}
+		(2, 2) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let value_format1 = reader.read_u16()?;
+			let value_format2 = reader.read_u16()?;
+			let class_def1 = ClassDef::parse_optional(subtable, reader.read_offset16()?)?;
+			let class_def2 = ClassDef::parse_optional(subtable, reader.read_offset16()?)?;
+			let class1_count = reader.read_u16()?;
+			let class2_count = reader.read_u16()?;
+			let mut records: Vec<(ValueRecord, ValueRecord)> = vec![];
+			for _ in 0..class1_count as usize * class2_count as usize {
+				let first_value = ValueRecord::parse(subtable, &mut reader, value_format1)?;
+				let second_value = ValueRecord::parse(subtable, &mut reader, value_format2)?;
+				records.push((first_value, second_value));
+			}
+			GposSubtable::PairClasses { coverage, second_value_format: value_format2, class_def1, class_def2, class2_count, records }
+		}
+		(3, 1) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let count = reader.read_u16()?;
+			let mut entry_exits: Vec<(Option<Anchor>, Option<Anchor>)> = vec![];
+			for _ in 0..count {
+				let entry = Anchor::parse_optional(subtable, reader.read_offset16()?)?;
+				let exit = Anchor::parse_optional(subtable, reader.read_offset16()?)?;
+				entry_exits.push((entry, exit));
+			}
+			GposSubtable::Cursive { coverage, entry_exits }
+		}
+		(4, 1) | (5, 1) | (6, 1) => {
+			let mark_coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let target_coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let mark_class_count = reader.read_u16()?;
+			let marks = parse_mark_array(subtable.slice_from(reader.read_offset16()?)?)?;
+			let targets = subtable.slice_from(reader.read_offset16()?)?;
+			match lookup_type {
+				4 => GposSubtable::MarkToBase { mark_coverage, base_coverage: target_coverage, marks, bases: parse_anchor_matrix(targets, mark_class_count)? },
+				5 => {
+					let mut offsets = targets;
+					let ligature_count = offsets.read_u16()?;
+					let mut ligatures: Vec<AnchorMatrix> = vec![];
+					for _ in 0..ligature_count {
+						ligatures.push(parse_anchor_matrix(targets.slice_from(offsets.read_offset16()?)?, mark_class_count)?);
+					}
+					GposSubtable::MarkToLigature { mark_coverage, ligature_coverage: target_coverage, marks, ligatures }
+				}
+				_ => GposSubtable::MarkToMark { mark1_coverage: mark_coverage, mark2_coverage: target_coverage, marks, mark2s: parse_anchor_matrix(targets, mark_class_count)? },
+			}
+		}
+		(7, _) => match SequenceContext::parse(subtable)? {
+			Some(context) => GposSubtable::Context(context),
+			None => return Ok(None),
+		},
+		(8, _) => match ChainedSequenceContext::parse(subtable)? {
+			Some(context) => GposSubtable::ChainedContext(context),
+			None => return Ok(None),
+		},
+		_ => return Ok(None),
+	};
+	Ok(Some(parsed))
+}
+
+fn parse_mark_array(mark_array: Reader) -> Result<Vec<MarkRecord>, FontError> {
+	let mut reader = mark_array;
+	let count = reader.read_u16()?;
+	let mut marks: Vec<MarkRecord> = vec![];
+	for _ in 0..count {
+		let mark_class = reader.read_u16()?;
+		marks.push((mark_class, Anchor::parse(mark_array.slice_from(reader.read_offset16()?)?)?));
+	}
+	Ok(marks)
+}
+
+// base arrays, mark2 arrays and ligature attach tables all share this layout
+fn parse_anchor_matrix(matrix: Reader, mark_class_count: u16) -> Result<AnchorMatrix, FontError> {
+	let mut reader = matrix;
+	let row_count = reader.read_u16()?;
+	let mut rows: AnchorMatrix = vec![];
+	for _ in 0..row_count {
+		let mut anchors: Vec<Option<Anchor>> = vec![];
+		for _ in 0..mark_class_count {
+			anchors.push(Anchor::parse_optional(matrix, reader.read_offset16()?)?);
+		}
+		rows.push(anchors);
+	}
+	Ok(rows)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sfnt_builder::*;
+
+	// glyphs 1 and 2 are bases, 3 and 4 marks
+	fn gdef() -> Gdef {
+		Gdef::parse(Reader::new(&gdef_table(&[(1, 2, BASE_GLYPH), (3, 4, MARK_GLYPH)]))).unwrap()
+	}
+
+	// every lookup in order over glyphs that start out 500 units wide
+	fn position(lookups: &[(u16, u16, Vec<Vec<u8>>)], glyphs: &[u16], options: PositioningOptions) -> Vec<GlyphPosition> {
+		let table = layout_table(&[(*b"test", (0..lookups.len() as u16).collect())], lookups);
+		let gpos = Gpos::parse_gpos(Reader::new(&table)).unwrap();
+		let mut positions = vec![GlyphPosition { x_advance: 500, ..Default::default() }; glyphs.len()];
+		let lookup_indices: Vec<(u16, u32)> = (0..lookups.len() as u16).map(|lookup_index| (lookup_index, 1)).collect();
+		gpos.apply(&gdef(), glyphs, &mut positions, &lookup_indices, options);
+		positions
+	}
+
+	// x advance, x offset, y offset
+	fn placements(positions: &[GlyphPosition]) -> Vec<(i32, i32, i32)> {
+		positions.iter().map(|position| (position.x_advance, position.x_offset, position.y_offset)).collect()
+	}
+
+	// one mark class, every mark anchored at its origin and every target at the same point
+	fn mark_attachment(marks: &[u16], targets: &[u16], target_anchor: (i16, i16)) -> Vec<u8> {
+		let mut mark_array = vec![Field::U16(marks.len() as u16)];
+		for _ in marks {
+			mark_array.extend([Field::U16(0), Field::Offset(anchor(0, 0))]);
+		}
+		let mut target_array = vec![Field::U16(targets.len() as u16)];
+		target_array.extend(targets.iter().map(|_| Field::Offset(anchor(target_anchor.0, target_anchor.1))));
+		offset_table(&[
+			Field::U16(1),
+			Field::Offset(coverage(marks)),
+			Field::Offset(coverage(targets)),
+			Field::U16(1),
+			Field::Offset(offset_table(&mark_array)),
+			Field::Offset(offset_table(&target_array)),
+		])
+	}
+
+	#[test]
+	fn single_adjustment_over_a_coverage_range() {
+		// format 2 coverage of glyphs 1 and 2, one value record each with x placement and x advance
+		let single = offset_table(&[
+			Field::U16(2),
+			Field::Offset(words(&[2, 1, 1, 2, 0])),
+			Field::U16(X_PLACEMENT | X_ADVANCE),
+			Field::U16(2),
+			Field::U16(10), Field::U16(-20i16 as u16),
+			Field::U16(30), Field::U16(40),
+		]);
+		let positions = position(&[(1, 0, vec![single])], &[2, 1, 3], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(540, 30, 0), (480, 10, 0), (0, 0, 0)]);
+	}
+
+	#[test]
+	fn device_adjusts_only_at_its_sizes() {
+		// 4 bit deltas 1, -2, 3 for sizes 10 to 12
+		let single = offset_table(&[
+			Field::U16(1),
+			Field::Offset(coverage(&[1])),
+			Field::U16(X_ADVANCE | X_ADVANCE_DEVICE),
+			Field::U16(0),
+			Field::Offset(words(&[10, 12, 2, 0x1E30])),
+		]);
+		let lookups = [(1, 0, vec![single])];
+		let advance = |ppem: Option<u16>| position(&lookups, &[1], PositioningOptions { ppem, ..Default::default() })[0].x_advance;
+		assert_eq!([None, Some(9), Some(10), Some(11), Some(12), Some(13)].map(advance), [500, 500, 501, 498, 503, 500]);
+	}
+
+	#[test]
+	fn pair_adjustment_consumes_the_second_glyph_only_with_a_value() {
+		let pair = |second_value_format: u16, pair_set: Vec<u8>| offset_table(&[
+			Field::U16(1),
+			Field::Offset(coverage(&[1])),
+			Field::U16(X_ADVANCE),
+			Field::U16(second_value_format),
+			Field::U16(1),
+			Field::Offset(pair_set),
+		]);
+		// without a second value the second glyph can start the next pair
+		let first_only = pair(0, words(&[1, 1, -50i16 as u16]));
+		let positions = position(&[(2, 0, vec![first_only.clone()])], &[1, 1, 1, 1], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(450, 0, 0), (450, 0, 0), (450, 0, 0), (500, 0, 0)]);
+		// marks the lookup ignores don't break up the pair
+		let positions = position(&[(2, IGNORE_MARKS, vec![first_only])], &[1, 3, 1], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(450, 0, 0), (0, 0, 0), (500, 0, 0)]);
+
+		let both = pair(Y_PLACEMENT, words(&[1, 1, -50i16 as u16, 10]));
+		let positions = position(&[(2, 0, vec![both])], &[1, 1, 1, 1], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(450, 0, 0), (500, 0, 10), (450, 0, 0), (500, 0, 10)]);
+	}
+
+	#[test]
+	fn pair_adjustment_by_class() {
+		// glyph 2 is class 1 on both sides, through a format 1 and a format 2 class definition
+		let pair = offset_table(&[
+			Field::U16(2),
+			Field::Offset(coverage(&[1, 2])),
+			Field::U16(X_ADVANCE),
+			Field::U16(0),
+			Field::Offset(words(&[1, 1, 2, 0, 1])),
+			Field::Offset(class_def(&[(2, 2, 1)])),
+			Field::U16(2),
+			Field::U16(2),
+			Field::U16(0), Field::U16(-10i16 as u16),
+			Field::U16(-20i16 as u16), Field::U16(-30i16 as u16),
+		]);
+		let positions = position(&[(2, 0, vec![pair])], &[1, 2, 2, 1, 1], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(490, 0, 0), (470, 0, 0), (480, 0, 0), (500, 0, 0), (500, 0, 0)]);
+	}
+
+	#[test]
+	fn cursive_exit_meets_the_next_entry() {
+		let cursive = offset_table(&[
+			Field::U16(1),
+			Field::Offset(coverage(&[1, 2])),
+			Field::U16(2),
+			Field::Null, Field::Offset(anchor(400, 50)),
+			Field::Offset(anchor(100, 0)), Field::Null,
+		]);
+		let positions = position(&[(3, 0, vec![cursive])], &[1, 2], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(400, 0, 0), (400, -100, 50)]);
+	}
+
+	#[test]
+	fn long_right_to_left_cursive_chain() {
+		// with the right to left flag each glyph hangs off the one after it, every link raises it by 10
+		let cursive = offset_table(&[
+			Field::U16(1),
+			Field::Offset(coverage(&[1])),
+			Field::U16(1),
+			Field::Offset(anchor(0, 10)), Field::Offset(anchor(0, 0)),
+		]);
+		let glyphs = vec![1; 100_000];
+		let positions = position(&[(3, RIGHT_TO_LEFT, vec![cursive])], &glyphs, PositioningOptions::default());
+		assert_eq!(positions[0].y_offset, 10 * 99_999);
+		assert_eq!(positions[50_000].y_offset, 10 * 49_999);
+		assert_eq!(positions[99_999].y_offset, 0);
+	}
+
+	#[test]
+	fn marks_attach_to_bases_and_to_each_other() {
+		let lookups = [
+			(4, 0, vec![mark_attachment(&[3], &[1], (250, 600))]),
+			(6, 0, vec![mark_attachment(&[4], &[3], (0, 100))]),
+		];
+		// glyph 4 stacks on glyph 3, which sits on the base, both pulled back over the base's advance
+		let positions = position(&lookups, &[1, 3, 4], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(500, 0, 0), (0, -250, 600), (0, -250, 700)]);
+		// the base lookup passes over other marks, mark to mark only takes the mark right before it
+		let positions = position(&lookups, &[1, 4, 3], PositioningOptions::default());
+		assert_eq!(placements(&positions), vec![(500, 0, 0), (0, 0, 0), (0, -250, 600)]);
+		let positions = position(&lookups, &[1, 3, 4], PositioningOptions { direction: Direction::RightToLeft, ..Default::default() });
+		assert_eq!(placements(&positions), vec![(500, 0, 0), (0, 250, 600), (0, 250, 700)]);
+	}
+}
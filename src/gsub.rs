@@ -0,0 +1,310 @@
+use crate::font_error::FontError;
+use crate::layout::*;
+use crate::reader::Reader;
+
+pub const EXTENSION_SUBSTITUTION: u16 = 7;
+const REVERSE_CHAINING_SINGLE: u16 = 8;
+const MAX_NESTING_LEVEL: usize = 8;
+// multiple substitutions stop once the text grows past this many glyphs per character, or MIN_MAX_LENGTH for short text
+// the same limits harfbuzz sets, chained expansions would otherwise run out of memory
+const MAX_LENGTH_FACTOR: usize = 64;
+const MIN_MAX_LENGTH: usize = 16384;
+
+#[derive(Debug, Clone)]
+pub enum GsubSubtable {
+	SingleDelta { coverage: Coverage, delta: i16 },
+	Single { coverage: Coverage, substitutes: Vec<u16> },
+	Multiple { coverage: Coverage, sequences: Vec<Vec<u16>> },
+	Alternate { coverage: Coverage, alternate_sets: Vec<Vec<u16>> },
+	// the ligature glyph and the components after the first one
+	Ligature { coverage: Coverage, ligature_sets: Vec<Vec<(u16, Vec<u16>)>> },
+	Context(SequenceContext),
+	ChainedContext(ChainedSequenceContext),
+	ReverseChained { coverage: Coverage, backtrack: Vec<Coverage>, lookahead: Vec<Coverage>, substitutes: Vec<u16> },
+}
+
+// glyphs and the cluster of the character each one came from, ligatures keep the first
+#[derive(Debug, Clone, Default)]
+pub struct GlyphBuffer {
+	pub glyphs: Vec<u16>,
+	pub clusters: Vec<usize>,
+}
+
+impl GlyphBuffer {
+	pub fn len(&self) -> usize {
+		self.glyphs.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.glyphs.is_empty()
+	}
+
+	fn replace(&mut self, index: usize, replacement: &[u16]) {
+		if let [glyph] = replacement {
+			self.glyphs[index] = *glyph;
+			return;
+		}
+		let cluster = self.clusters[index];
+		self.glyphs.splice(index..=index, replacement.iter().copied());
+		self.clusters.splice(index..=index, replacement.iter().map(|_| cluster));
+	}
+
+	// everything from the first component to the last ends up in one cluster
+	fn ligate(&mut self, components: &[usize], ligature: u16) {
+		let (&first, &last) = (components.first().unwrap(), components.last().unwrap());
+		let cluster = self.clusters[first..=last].iter().copied().min().unwrap();
+		self.clusters[first..=last].fill(cluster);
+		self.glyphs[first] = ligature;
+		for &component in components[1..].iter().rev() {
+			self.glyphs.remove(component);
+			self.clusters.remove(component);
+		}
+	}
+}
+
+pub type Gsub = LayoutTable<GsubSubtable>;
+
+impl Gsub {
+	pub fn parse_gsub(gsub: Reader) -> Result<Gsub, FontError> {
+		LayoutTable::parse(gsub, EXTENSION_SUBSTITUTION, parse_subtable)
+	}
+
+	// lookups come with the value of the feature that turned them on, alternate substitutions use it as a 1-based index
+	pub fn apply(&self, gdef: &Gdef, buffer: &mut GlyphBuffer, lookup_indices: &[(u16, u32)]) {
+		let max_length = buffer.len().saturating_mul(MAX_LENGTH_FACTOR).max(MIN_MAX_LENGTH);
+		let substitutor = Substitutor { gsub: self, gdef, max_length };
+		for &(lookup_index, value) in lookup_indices {
+			let Some(lookup) = self.lookups.get(lookup_index as usize) else {
+				continue;
+			};
+			// reverse chaining substitutions run from the end of the text backwards and never change its length
+			if lookup.lookup_type == REVERSE_CHAINING_SINGLE {
+				for index in (0..buffer.len()).rev() {
+					substitutor.apply_lookup(lookup_index, value, index, buffer, 0);
+				}
+				continue;
+			}
+			// an empty multiple substitution deletes the glyph and moves 0, the next glyph is then at index
+			let mut index = 0;
+			while index < buffer.len() {
+				index += substitutor.apply_lookup(lookup_index, value, index, buffer, 0).unwrap_or(1);
+			}
+		}
+	}
+}
+
+struct Substitutor<'a> {
+	gsub: &'a Gsub,
+	gdef: &'a Gdef,
+	max_length: usize,
+}
+
+impl Substitutor<'_> {
+	// how many glyphs to move past, None if the lookup didn't apply
+	fn apply_lookup(&self, lookup_index: u16, value: u32, index: usize, buffer: &mut GlyphBuffer, depth: usize) -> Option<usize> {
+		let lookup = self.gsub.lookups.get(lookup_index as usize)?;
+		let &glyph = buffer.glyphs.get(index)?;
+		if depth > MAX_NESTING_LEVEL || self.gdef.should_skip(lookup.lookup_flag, lookup.mark_filtering_set, glyph) {
+			return None;
+		}
+		lookup.subtables.iter().find_map(|subtable| self.apply_subtable(lookup, subtable, value, index, buffer, depth))
+	}
+
+	fn apply_subtable(&self, lookup: &Lookup<GsubSubtable>, subtable: &GsubSubtable, value: u32, index: usize, buffer: &mut GlyphBuffer, depth: usize) -> Option<usize> {
+		let glyph = buffer.glyphs[index];
+		let skip = |glyph: u16| self.gdef.should_skip(lookup.lookup_flag, lookup.mark_filtering_set, glyph);
+		match subtable {
+			GsubSubtable::SingleDelta { coverage, delta } => {
+				coverage.get_index(glyph)?;
+				buffer.glyphs[index] = glyph.wrapping_add_signed(*delta);
+				Some(1)
+			}
+			GsubSubtable::Single { coverage, substitutes } => {
+				buffer.glyphs[index] = *substitutes.get(coverage.get_index(glyph)? as usize)?;
+				Some(1)
+			}
+			GsubSubtable::Multiple { coverage, sequences } => {
+				let sequence = sequences.get(coverage.get_index(glyph)? as usize)?;
+				if buffer.len() - 1 + sequence.len() > self.max_length {
+					return None;
+				}
+				buffer.replace(index, sequence);
+				Some(sequence.len())
+			}
+			GsubSubtable::Alternate { coverage, alternate_sets } => {
+				let alternates = alternate_sets.get(coverage.get_index(glyph)? as usize)?;
+				buffer.glyphs[index] = *alternates.get(value.checked_sub(1)? as usize)?;
+				Some(1)
+			}
+			// the first ligature in the set that matches wins, fonts order them longest first
+			GsubSubtable::Ligature { coverage, ligature_sets } => {
+				let ligatures = ligature_sets.get(coverage.get_index(glyph)? as usize)?;
+				ligatures.iter().find_map(|(ligature, components)| {
+					let mut positions = vec![index];
+					positions.extend(match_forward(&buffer.glyphs, index, components.len(), &skip, |i, glyph| glyph == components[i])?);
+					buffer.ligate(&positions, *ligature);
+					Some(1)
+				})
+			}
+			GsubSubtable::Context(context) => {
+				let (matched, lookups) = context.matches(&buffer.glyphs, index, &skip)?;
+				Some(self.apply_nested(matched, lookups, value, buffer, depth))
+			}
+			GsubSubtable::ChainedContext(context) => {
+				let (matched, lookups) = context.matches(&buffer.glyphs, index, &skip)?;
+				Some(self.apply_nested(matched, lookups, value, buffer, depth))
+			}
+			GsubSubtable::ReverseChained { coverage, backtrack, lookahead, substitutes } => {
+				let substitute = *substitutes.get(coverage.get_index(glyph)? as usize)?;
+				match_backward(&buffer.glyphs, index, backtrack.len(), &skip, |i, glyph| backtrack[i].contains(glyph))?;
+				match_forward(&buffer.glyphs, index, lookahead.len(), &skip, |i, glyph| lookahead[i].contains(glyph))?;
+				buffer.glyphs[index] = substitute;
+				Some(1)
+			}
+		}
+	}
+
+	// nested lookups can grow or shrink the buffer, matched positions after the change move with it
+	fn apply_nested(&self, mut matched: Vec<usize>, lookups: &[SequenceLookupRecord], value: u32, buffer: &mut GlyphBuffer, depth: usize) -> usize {
+		let start = matched[0];
+		let mut end = *matched.last().unwrap() + 1;
+		for record in lookups {
+			let Some(&position) = matched.get(record.sequence_index as usize) else {
+				continue;
+			};
+			let length = buffer.len();
+			if self.apply_lookup(record.lookup_index, value, position, buffer, depth + 1).is_none() {
+				continue;
+			}
+			let change = buffer.len() as isize - length as isize;
+			if change == 0 {
+				continue;
+			}
+			end = end.saturating_add_signed(change).max(position + 1);
+			for later in matched.iter_mut().filter(|later| **later > position) {
+				*later = later.saturating_add_signed(change).max(position + 1);
+			}
+		}
+		end.saturating_sub(start).max(1)
+	}
+}
+
+fn parse_subtable(lookup_type: u16, subtable: Reader) -> Result<Option<GsubSubtable>, FontError> {
+	let mut reader = subtable;
+	let format = reader.read_u16()?;
+	let parsed = match (lookup_type, format) {
+		(1, 1) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			GsubSubtable::SingleDelta { coverage, delta: reader.read_i16()? }
+		}
+		(1, 2) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let count = reader.read_u16()?;
+			GsubSubtable::Single { coverage, substitutes: read_u16_array(&mut reader, count)? }
+		}
+		// multiple and alternate subtables are both a coverage and a list of glyph arrays
+		(2, 1) | (3, 1) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let count = reader.read_u16()?;
+			let mut sequences: Vec<Vec<u16>> = vec![];
+			for _ in 0..count {
+				let mut sequence = subtable.slice_from(reader.read_offset16()?)?;
+				let glyph_count = sequence.read_u16()?;
+				sequences.push(read_u16_array(&mut sequence, glyph_count)?);
+			}
+			if lookup_type == 2 {
+				GsubSubtable::Multiple { coverage, sequences }
+			} else {
+				GsubSubtable::Alternate { coverage, alternate_sets: sequences }
+			}
+		}
+		(4, 1) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let ligature_set_count = reader.read_u16()?;
+			let mut ligature_sets: Vec<Vec<(u16, Vec<u16>)>> = vec![];
+			for _ in 0..ligature_set_count {
+				let ligature_set = subtable.slice_from(reader.read_offset16()?)?;
+				let mut offsets = ligature_set;
+				let ligature_count = offsets.read_u16()?;
+				let mut ligatures: Vec<(u16, Vec<u16>)> = vec![];
+				for _ in 0..ligature_count {
+					let mut ligature = ligature_set.slice_from(offsets.read_offset16()?)?;
+					let ligature_glyph = ligature.read_u16()?;
+					let component_count = ligature.read_u16()?;
+					ligatures.push((ligature_glyph, read_u16_array(&mut ligature, component_count.saturating_sub(1))?));
+				}
+				ligature_sets.push(ligatures);
+			}
+			GsubSubtable::Ligature { coverage, ligature_sets }
+		}
+		(5, _) => match SequenceContext::parse(subtable)? {
+			Some(context) => GsubSubtable::Context(context),
+			None => return Ok(None),
+		},
+		(6, _) => match ChainedSequenceContext::parse(subtable)? {
+			Some(context) => GsubSubtable::ChainedContext(context),
+			None => return Ok(None),
+		},
+		(8, 1) => {
+			let coverage = Coverage::parse(subtable.slice_from(reader.read_offset16()?)?)?;
+			let backtrack_count = reader.read_u16()?;
+			let backtrack = read_coverages(subtable, &mut reader, backtrack_count)?;
+			let lookahead_count = reader.read_u16()?;
+			let lookahead = read_coverages(subtable, &mut reader, lookahead_count)?;
+			let glyph_count = reader.read_u16()?;
+			GsubSubtable::ReverseChained { coverage, backtrack, lookahead, substitutes: read_u16_array(&mut reader, glyph_count)? }
+		}
+		_ => return Ok(None),
+	};
+	Ok(Some(parsed))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn multiple_substitution(coverage: Vec<u16>, sequences: Vec<Vec<u16>>) -> Gsub {
+		Gsub {
+			lookups: vec![Lookup {
+				lookup_type: 2,
+				lookup_flag: 0,
+				mark_filtering_set: None,
+				subtables: vec![GsubSubtable::Multiple { coverage: Coverage::Glyphs(coverage), sequences }],
+			}],
+			..Default::default()
+		}
+	}
+
+	fn apply(gsub: &Gsub, glyphs: Vec<u16>) -> GlyphBuffer {
+		let mut buffer = GlyphBuffer { clusters: (0..glyphs.len()).collect(), glyphs };
+		gsub.apply(&Gdef::default(), &mut buffer, &[(0, 1)]);
+		buffer
+	}
+
+	#[test]
+	fn empty_sequence_deletes_every_covered_glyph() {
+		let gsub = multiple_substitution(vec![5], vec![vec![]]);
+		let buffer = apply(&gsub, vec![5, 5, 6, 5]);
+		assert_eq!(buffer.glyphs, vec![6]);
+		assert_eq!(buffer.clusters, vec![2]);
+	}
+
+	#[test]
+	fn expansion_stops_at_the_maximum_length() {
+		// five lookups each turning glyph 5 into a hundred of itself
+		let mut gsub = multiple_substitution(vec![5], vec![vec![5; 100]]);
+		gsub.lookups = vec![gsub.lookups[0].clone(); 5];
+		let mut buffer = GlyphBuffer { glyphs: vec![5; 5], clusters: (0..5).collect() };
+		gsub.apply(&Gdef::default(), &mut buffer, &(0..5).map(|lookup_index| (lookup_index, 1)).collect::<Vec<_>>());
+		assert!(buffer.len() <= MIN_MAX_LENGTH && buffer.len() > MIN_MAX_LENGTH - 100, "{}", buffer.len());
+		assert_eq!(buffer.clusters.len(), buffer.len());
+	}
+
+	#[test]
+	fn sequence_expands_in_one_cluster() {
+		let gsub = multiple_substitution(vec![5], vec![vec![7, 8]]);
+		let buffer = apply(&gsub, vec![4, 5, 5]);
+		assert_eq!(buffer.glyphs, vec![4, 7, 8, 7, 8]);
+		assert_eq!(buffer.clusters, vec![0, 1, 1, 2, 2]);
+	}
+}
@@ -0,0 +1,1474 @@
+use std::collections::HashMap;
+
+use crate::font_error::FontError;
+use crate::reader::Reader;
+
+// coordinates are 26.6 fixed point pixels while a program runs, vectors are unit length floats
+const ONE_PIXEL: i32 = 64;
+const MAX_CALL_DEPTH: usize = 64;
+// a runaway program is stopped instead of hanging the glyph load
+const MAX_INSTRUCTIONS: usize = 1_000_000;
+
+const FONT_PROGRAM: usize = 0;
+const CONTROL_VALUE_PROGRAM: usize = 1;
+const GLYPH_PROGRAM: usize = 2;
+
+const TWILIGHT_ZONE: usize = 0;
+const GLYPH_ZONE: usize = 1;
+
+const TOUCHED_X: u8 = 0x01;
+const TOUCHED_Y: u8 = 0x02;
+
+// the hinting limits from a version 1.0 maxp, CFF fonts have the short version without them
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxpLimits {
+	pub max_zones: u16,
+	pub max_twilight_points: u16,
+	pub max_storage: u16,
+	pub max_function_defs: u16,
+	pub max_instruction_defs: u16,
+	pub max_stack_elements: u16,
+	pub max_size_of_instructions: u16,
+}
+
+impl MaxpLimits {
+	pub fn parse(maxp: Reader) -> Result<MaxpLimits, FontError> {
+		let mut reader = maxp;
+		let version = reader.read_u32()?;
+		if version < 0x00010000 {
+			return Ok(MaxpLimits::default());
+		}
+		reader.skip(10)?; //skip numGlyphs, maxPoints, maxContours, maxCompositePoints, maxCompositeContours
+		Ok(MaxpLimits {
+			max_zones: 					reader.read_u16()?,
+			max_twilight_points: 		reader.read_u16()?,
+			max_storage: 				reader.read_u16()?,
+			max_function_defs: 			reader.read_u16()?,
+			max_instruction_defs: 		reader.read_u16()?,
+			max_stack_elements: 		reader.read_u16()?,
+			max_size_of_instructions: 	reader.read_u16()?,
+		})
+	}
+}
+
+// fpgm, prep and cvt as stored in the font, shared by every size
+#[derive(Debug, Clone, Default)]
+pub struct HintingTables {
+	pub units_per_em: u16,
+	pub limits: MaxpLimits,
+	pub font_program: Vec<u8>,
+	pub control_value_program: Vec<u8>,
+	pub control_values: Vec<i16>,
+}
+
+impl HintingTables {
+	pub fn parse_control_values(cvt: Reader) -> Result<Vec<i16>, FontError> {
+		let mut reader = cvt;
+		let mut control_values: Vec<i16> = vec![];
+		while reader.remaining() >= 2 {
+			control_values.push(reader.read_i16()?);
+		}
+		Ok(control_values)
+	}
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum RoundState {
+	ToGrid,
+	ToHalfGrid,
+	ToDoubleGrid,
+	DownToGrid,
+	UpToGrid,
+	Off,
+	// SROUND and S45ROUND, in 26.6
+	Super { period: i32, phase: i32, threshold: i32 },
+	Super45 { period: i32, phase: i32, threshold: i32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+struct GraphicsState {
+	projection_vector: (f32, f32),
+	freedom_vector: (f32, f32),
+	dual_vector: (f32, f32),
+	reference_points: [usize; 3],
+	zone_pointers: [usize; 3],
+	loop_count: i32,
+	round_state: RoundState,
+	minimum_distance: i32,
+	control_value_cutin: i32,
+	single_width_cutin: i32,
+	single_width_value: i32,
+	delta_base: i32,
+	delta_shift: i32,
+	auto_flip: bool,
+	instruct_control: i32,
+}
+
+impl Default for GraphicsState {
+	fn default() -> Self {
+		Self {
+			projection_vector: (1.0, 0.0),
+			freedom_vector: (1.0, 0.0),
+			dual_vector: (1.0, 0.0),
+			reference_points: [0; 3],
+			zone_pointers: [GLYPH_ZONE; 3],
+			loop_count: 1,
+			round_state: RoundState::ToGrid,
+			minimum_distance: ONE_PIXEL,
+			control_value_cutin: 68,
+			single_width_cutin: 0,
+			single_width_value: 0,
+			delta_base: 9,
+			delta_shift: 3,
+			auto_flip: true,
+			instruct_control: 0,
+		}
+	}
+}
+
+impl GraphicsState {
+	// prep can change the defaults for every glyph, but vectors, zones and reference points start over
+	fn reset_for_glyph(&mut self) {
+		let defaults = GraphicsState::default();
+		self.projection_vector = defaults.projection_vector;
+		self.freedom_vector = defaults.freedom_vector;
+		self.dual_vector = defaults.dual_vector;
+		self.reference_points = defaults.reference_points;
+		self.zone_pointers = defaults.zone_pointers;
+		self.loop_count = defaults.loop_count;
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Definition {
+	program: usize,
+	start: usize,
+	end: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Zone {
+	current: Vec<(i32, i32)>,
+	original: Vec<(i32, i32)>,
+	touched: Vec<u8>,
+	on_curve: Vec<bool>,
+	end_points: Vec<usize>,
+}
+
+impl Zone {
+	fn with_points(count: usize) -> Self {
+		Self {
+			current: vec![(0, 0); count],
+			original: vec![(0, 0); count],
+			touched: vec![0; count],
+			on_curve: vec![false; count],
+			end_points: vec![],
+		}
+	}
+
+	fn check(&self, point: usize) -> Result<(), FontError> {
+		if point < self.current.len() {Ok(())} else {Err(FontError::Malformed("hinting refers to a missing point"))}
+	}
+}
+
+// the state left behind by fpgm and prep at one size, every glyph starts from a copy of it
+#[derive(Debug, Clone)]
+pub struct HintingInstance {
+	pub ppem: u16,
+	// 26.6 pixels per font unit
+	pub scale: f32,
+	programs: [Vec<u8>; 2],
+	functions: HashMap<i32, Definition>,
+	instruction_defs: HashMap<u8, Definition>,
+	control_values: Vec<i32>,
+	storage: Vec<i32>,
+	graphics_state: GraphicsState,
+	twilight_points: usize,
+	stack_limit: usize,
+	coordinates: Vec<f32>,
+}
+
+impl HintingInstance {
+	// runs fpgm once and prep at this size
+	pub fn new(tables: &HintingTables, ppem: u16, coordinates: &[f32]) -> Result<HintingInstance, FontError> {
+		if tables.units_per_em == 0 {
+			return Err(FontError::Malformed("units per em is zero"));
+		}
+		let scale = ppem as f32 * ONE_PIXEL as f32 / tables.units_per_em as f32;
+		let mut instance = HintingInstance {
+			ppem,
+			scale,
+			programs: [tables.font_program.clone(), tables.control_value_program.clone()],
+			functions: HashMap::new(),
+			instruction_defs: HashMap::new(),
+			control_values: tables.control_values.iter().map(|&value| (value as f32 * scale).round() as i32).collect(),
+			storage: vec![0; tables.limits.max_storage as usize],
+			graphics_state: GraphicsState::default(),
+			twilight_points: tables.limits.max_twilight_points as usize,
+			// fonts often understate their stack use, so leave some room
+			stack_limit: tables.limits.max_stack_elements as usize + 32,
+			coordinates: coordinates.to_vec(),
+		};
+		for program in [FONT_PROGRAM, CONTROL_VALUE_PROGRAM] {
+			let (functions, instruction_defs, control_values, storage, state) = {
+				let mut executor = Executor::new(&instance, &[], Zone::default());
+				executor.run(program)?;
+				(executor.functions, executor.instruction_defs, executor.control_values, executor.storage, executor.state)
+			};
+			instance.functions = functions;
+			instance.instruction_defs = instruction_defs;
+			instance.control_values = control_values;
+			instance.storage = storage;
+			// bit 2 of INSTCTRL keeps prep from changing the defaults
+			if program == CONTROL_VALUE_PROGRAM && state.instruct_control & 0x2 == 0 {
+				instance.graphics_state = state;
+			}
+			instance.graphics_state.instruct_control = state.instruct_control;
+		}
+		Ok(instance)
+	}
+
+	// points are in font units with the four phantom points at the end, on return they're grid-fitted
+	pub fn hint_glyph(&self, points: &mut [(f32, f32)], on_curve: &mut [bool], end_points: &[u16], instructions: &[u8]) -> Result<(), FontError> {
+		// bit 1 of INSTCTRL turns glyph programs off
+		if self.graphics_state.instruct_control & 0x1 != 0 {
+			return Ok(());
+		}
+		let mut zone = Zone::with_points(points.len());
+		for (i, &(x, y)) in points.iter().enumerate() {
+			zone.original[i] = ((x * self.scale).round() as i32, (y * self.scale).round() as i32);
+		}
+		zone.on_curve.copy_from_slice(on_curve);
+		zone.end_points = end_points.iter().map(|&end| end as usize).filter(|&end| end + 4 < points.len()).collect();
+		// the phantom points start on whole pixels so the advance stays one
+		let phantom = points.len().saturating_sub(4);
+		for point in &mut zone.original[phantom..] {
+			*point = (round_to_grid(point.0), round_to_grid(point.1));
+		}
+		zone.current = zone.original.clone();
+
+		let mut executor = Executor::new(self, instructions, zone);
+		executor.state.reset_for_glyph();
+		executor.run(GLYPH_PROGRAM)?;
+
+		let zone = &executor.zones[GLYPH_ZONE];
+		for (i, point) in points.iter_mut().enumerate() {
+			*point = (zone.current[i].0 as f32 / self.scale, zone.current[i].1 as f32 / self.scale);
+		}
+		on_curve.copy_from_slice(&zone.on_curve);
+		Ok(())
+	}
+
+	pub fn round_to_pixels(&self, distance: f32) -> f32 {
+		round_to_grid((distance * self.scale).round() as i32) as f32 / self.scale
+	}
+}
+
+fn round_to_grid(distance: i32) -> i32 {
+	if distance >= 0 {
+		distance.wrapping_add(32) & !63
+	} else {
+		(32i32.wrapping_sub(distance) & !63).wrapping_neg()
+	}
+}
+
+struct Executor<'a> {
+	programs: [&'a [u8]; 3],
+	functions: HashMap<i32, Definition>,
+	instruction_defs: HashMap<u8, Definition>,
+	control_values: Vec<i32>,
+	storage: Vec<i32>,
+	state: GraphicsState,
+	zones: [Zone; 2],
+	stack: Vec<i32>,
+	stack_limit: usize,
+	ppem: u16,
+	scale: f32,
+	coordinates: &'a [f32],
+	instruction_count: usize,
+	is_glyph_program: bool,
+}
+
+impl<'a> Executor<'a> {
+	fn new(instance: &'a HintingInstance, glyph_program: &'a [u8], glyph_zone: Zone) -> Self {
+		Self {
+			programs: [&instance.programs[FONT_PROGRAM], &instance.programs[CONTROL_VALUE_PROGRAM], glyph_program],
+			functions: instance.functions.clone(),
+			instruction_defs: instance.instruction_defs.clone(),
+			control_values: instance.control_values.clone(),
+			storage: instance.storage.clone(),
+			state: instance.graphics_state,
+			zones: [Zone::with_points(instance.twilight_points), glyph_zone],
+			stack: vec![],
+			stack_limit: instance.stack_limit,
+			ppem: instance.ppem,
+			scale: instance.scale,
+			coordinates: &instance.coordinates,
+			instruction_count: 0,
+			is_glyph_program: false,
+		}
+	}
+
+	fn run(&mut self, program: usize) -> Result<(), FontError> {
+		self.is_glyph_program = program == GLYPH_PROGRAM;
+		let end = self.programs[program].len();
+		self.execute(program, 0, end, 0)
+	}
+
+	fn push(&mut self, value: i32) -> Result<(), FontError> {
+		if self.stack.len() >= self.stack_limit {
+			return Err(FontError::Malformed("hinting stack overflow"));
+		}
+		self.stack.push(value);
+		Ok(())
+	}
+
+	fn pop(&mut self) -> Result<i32, FontError> {
+		self.stack.pop().ok_or(FontError::Malformed("hinting stack underflow"))
+	}
+
+	fn pop_index(&mut self) -> Result<usize, FontError> {
+		let value = self.pop()?;
+		usize::try_from(value).map_err(|_| FontError::Malformed("negative index in hinting program"))
+	}
+
+	fn zone(&self, pointer: usize) -> &Zone {
+		&self.zones[self.state.zone_pointers[pointer]]
+	}
+
+	fn zone_mut(&mut self, pointer: usize) -> &mut Zone {
+		&mut self.zones[self.state.zone_pointers[pointer]]
+	}
+
+	fn point(&self, pointer: usize, point: usize) -> Result<(i32, i32), FontError> {
+		let zone = self.zone(pointer);
+		zone.check(point)?;
+		Ok(zone.current[point])
+	}
+
+	fn original_point(&self, pointer: usize, point: usize) -> Result<(i32, i32), FontError> {
+		let zone = self.zone(pointer);
+		zone.check(point)?;
+		Ok(zone.original[point])
+	}
+
+	fn project(&self, vector: (i32, i32)) -> i32 {
+		let (x, y) = self.state.projection_vector;
+		(vector.0 as f32 * x + vector.1 as f32 * y).round() as i32
+	}
+
+	fn dual_project(&self, vector: (i32, i32)) -> i32 {
+		let (x, y) = self.state.dual_vector;
+		(vector.0 as f32 * x + vector.1 as f32 * y).round() as i32
+	}
+
+	// moves along the freedom vector until the projection has changed by distance
+	fn move_point(&mut self, pointer: usize, point: usize, distance: i32, touch: bool) -> Result<(), FontError> {
+		let (fx, fy) = self.state.freedom_vector;
+		let (px, py) = self.state.projection_vector;
+		let mut dot = fx * px + fy * py;
+		if dot.abs() < 1.0 / 16384.0 {
+			dot = 1.0;
+		}
+		let zone = self.zone_mut(pointer);
+		zone.check(point)?;
+		if fx != 0.0 {
+			zone.current[point].0 = zone.current[point].0.wrapping_add((distance as f32 * fx / dot).round() as i32);
+			if touch {
+				zone.touched[point] |= TOUCHED_X;
+			}
+		}
+		if fy != 0.0 {
+			zone.current[point].1 = zone.current[point].1.wrapping_add((distance as f32 * fy / dot).round() as i32);
+			if touch {
+				zone.touched[point] |= TOUCHED_Y;
+			}
+		}
+		Ok(())
+	}
+
+	fn round(&self, distance: i32) -> i32 {
+		let sign_preserving = |rounded: i32| if distance >= 0 {rounded.max(0)} else {rounded.min(0)};
+		match self.state.round_state {
+			RoundState::Off => distance,
+			RoundState::ToGrid => round_to_grid(distance),
+			RoundState::ToHalfGrid => if distance >= 0 {
+				(distance & !63).wrapping_add(32)
+			} else {
+				(distance.wrapping_neg() & !63).wrapping_add(32).wrapping_neg()
+			},
+			RoundState::ToDoubleGrid => if distance >= 0 {
+				sign_preserving(distance.wrapping_add(16) & !31)
+			} else {
+				sign_preserving((16i32.wrapping_sub(distance) & !31).wrapping_neg())
+			},
+			RoundState::DownToGrid => if distance >= 0 {distance & !63} else {(distance.wrapping_neg() & !63).wrapping_neg()},
+			RoundState::UpToGrid => if distance >= 0 {
+				sign_preserving(distance.wrapping_add(63) & !63)
+			} else {
+				sign_preserving((63i32.wrapping_sub(distance) & !63).wrapping_neg())
+			},
+			RoundState::Super { period, phase, threshold } => if distance >= 0 {
+				let rounded = (distance.wrapping_sub(phase).wrapping_add(threshold) & -period).wrapping_add(phase);
+				if rounded < 0 {phase} else {rounded}
+			} else {
+				let rounded = (phase.wrapping_sub(distance).wrapping_add(threshold) & -period).wrapping_neg().wrapping_sub(phase);
+				if rounded > 0 {-phase} else {rounded}
+			},
+			RoundState::Super45 { period, phase, threshold } => if distance >= 0 {
+				let rounded = (distance.wrapping_sub(phase).wrapping_add(threshold) / period).wrapping_mul(period).wrapping_add(phase);
+				if rounded < 0 {phase} else {rounded}
+			} else {
+				let rounded = (phase.wrapping_sub(distance).wrapping_add(threshold) / period).wrapping_mul(period).wrapping_neg().wrapping_sub(phase);
+				if rounded > 0 {-phase} else {rounded}
+			},
+		}
+	}
+
+	// SROUND packs period, phase and threshold into one byte
+	fn super_round(&mut self, selector: i32, grid_period: f32) {
+		let period = match (selector >> 6) & 3 {
+			0 => grid_period / 2.0,
+			2 => grid_period * 2.0,
+			_ => grid_period,
+		};
+		let phase = match (selector >> 4) & 3 {
+			0 => 0.0,
+			1 => period / 4.0,
+			2 => period / 2.0,
+			_ => period * 3.0 / 4.0,
+		};
+		let threshold = match selector & 0x0F {
+			0 => period - 1.0,
+			n => (n - 4) as f32 * period / 8.0,
+		};
+		let (period, phase, threshold) = ((period as i32).max(1), phase as i32, threshold as i32);
+		self.state.round_state = if grid_period == ONE_PIXEL as f32 {
+			RoundState::Super { period, phase, threshold }
+		} else {
+			RoundState::Super45 { period, phase, threshold }
+		};
+	}
+
+	fn control_value(&self, index: usize) -> Result<i32, FontError> {
+		self.control_values.get(index).copied().ok_or(FontError::Malformed("hinting refers to a missing control value"))
+	}
+
+	fn set_control_value(&mut self, index: usize, value: i32) -> Result<(), FontError> {
+		let slot = self.control_values.get_mut(index).ok_or(FontError::Malformed("hinting refers to a missing control value"))?;
+		*slot = value;
+		Ok(())
+	}
+
+	fn set_vector_from_stack(&mut self) -> Result<(f32, f32), FontError> {
+		let y = self.pop()? as i16 as f32;
+		let x = self.pop()? as i16 as f32;
+		Ok(normalize(x, y))
+	}
+
+	// the line from p2 to p1, rotated a quarter turn counter-clockwise for the perpendicular forms
+	fn vector_from_line(&mut self, perpendicular: bool, use_original: bool) -> Result<(f32, f32), FontError> {
+		let p1 = self.pop_index()?;
+		let p2 = self.pop_index()?;
+		let (a, b) = if use_original {(self.original_point(2, p1)?, self.original_point(1, p2)?)} else {(self.point(2, p1)?, self.point(1, p2)?)};
+		let (mut dx, mut dy) = ((b.0 as f32) - (a.0 as f32), (b.1 as f32) - (a.1 as f32));
+		if dx == 0.0 && dy == 0.0 {
+			return Ok((1.0, 0.0));
+		}
+		if perpendicular {
+			(dx, dy) = (-dy, dx);
+		}
+		Ok(normalize(dx, dy))
+	}
+
+	// how far a reference point has moved, for the shift instructions
+	fn reference_displacement(&self, use_rp1: bool) -> Result<(usize, usize, i32, i32), FontError> {
+		let (pointer, reference) = if use_rp1 {(0, self.state.reference_points[1])} else {(1, self.state.reference_points[2])};
+		let current = self.point(pointer, reference)?;
+		let original = self.original_point(pointer, reference)?;
+		let distance = self.project((current.0.wrapping_sub(original.0), current.1.wrapping_sub(original.1)));
+		let (fx, fy) = self.state.freedom_vector;
+		let (px, py) = self.state.projection_vector;
+		let mut dot = fx * px + fy * py;
+		if dot.abs() < 1.0 / 16384.0 {
+			dot = 1.0;
+		}
+		let dx = (distance as f32 * fx / dot).round() as i32;
+		let dy = (distance as f32 * fy / dot).round() as i32;
+		Ok((self.state.zone_pointers[pointer], reference, dx, dy))
+	}
+
+	fn shift_point(&mut self, zone: usize, point: usize, dx: i32, dy: i32, touch: bool) -> Result<(), FontError> {
+		let (fx, fy) = self.state.freedom_vector;
+		let zone = &mut self.zones[zone];
+		zone.check(point)?;
+		if fx != 0.0 {
+			zone.current[point].0 = zone.current[point].0.wrapping_add(dx);
+			if touch {
+				zone.touched[point] |= TOUCHED_X;
+			}
+		}
+		if fy != 0.0 {
+			zone.current[point].1 = zone.current[point].1.wrapping_add(dy);
+			if touch {
+				zone.touched[point] |= TOUCHED_Y;
+			}
+		}
+		Ok(())
+	}
+
+	fn count_instruction(&mut self) -> Result<(), FontError> {
+		self.instruction_count += 1;
+		if self.instruction_count > MAX_INSTRUCTIONS {
+			return Err(FontError::Malformed("hinting program runs too long"));
+		}
+		Ok(())
+	}
+
+	fn execute(&mut self, program: usize, start: usize, end: usize, depth: usize) -> Result<(), FontError> {
+		if depth > MAX_CALL_DEPTH {
+			return Err(FontError::Malformed("hinting calls nest too deeply"));
+		}
+		// the call itself counts too, a LOOPCALL of an empty function would otherwise loop for free
+		self.count_instruction()?;
+		let code = self.programs[program];
+		let mut ip = start;
+		while ip < end {
+			self.count_instruction()?;
+			let opcode = code[ip];
+			let mut next = ip + instruction_length(code, ip)?;
+			match opcode {
+				// SVTCA, SPVTCA, SFVTCA
+				0x00..=0x05 => {
+					let axis = if opcode & 1 != 0 {(1.0, 0.0)} else {(0.0, 1.0)};
+					if opcode <= 0x03 {
+						self.state.projection_vector = axis;
+						self.state.dual_vector = axis;
+					}
+					if opcode <= 0x01 || opcode >= 0x04 {
+						self.state.freedom_vector = axis;
+					}
+				}
+				// SPVTL, SFVTL
+				0x06..=0x09 => {
+					let vector = self.vector_from_line(opcode & 1 != 0, false)?;
+					if opcode <= 0x07 {
+						self.state.projection_vector = vector;
+						self.state.dual_vector = vector;
+					} else {
+						self.state.freedom_vector = vector;
+					}
+				}
+				// SPVFS
+				0x0A => {
+					let vector = self.set_vector_from_stack()?;
+					self.state.projection_vector = vector;
+					self.state.dual_vector = vector;
+				}
+				// SFVFS
+				0x0B => self.state.freedom_vector = self.set_vector_from_stack()?,
+				// GPV, GFV
+				0x0C | 0x0D => {
+					let (x, y) = if opcode == 0x0C {self.state.projection_vector} else {self.state.freedom_vector};
+					self.push((x * 16384.0).round() as i32)?;
+					self.push((y * 16384.0).round() as i32)?;
+				}
+				// SFVTPV
+				0x0E => self.state.freedom_vector = self.state.projection_vector,
+				// ISECT
+				0x0F => {
+					let b1 = self.pop_index()?;
+					let b0 = self.pop_index()?;
+					let a1 = self.pop_index()?;
+					let a0 = self.pop_index()?;
+					let point = self.pop_index()?;
+					let (a0, a1) = (self.point(1, a0)?, self.point(1, a1)?);
+					let (b0, b1) = (self.point(0, b0)?, self.point(0, b1)?);
+					// in f64, points can sit anywhere in i32 and their differences don't fit it
+					let (dax, day) = (a1.0 as f64 - a0.0 as f64, a1.1 as f64 - a0.1 as f64);
+					let (dbx, dby) = (b1.0 as f64 - b0.0 as f64, b1.1 as f64 - b0.1 as f64);
+					let denominator = dax * dby - day * dbx;
+					let intersection = if denominator.abs() < 1e-9 {
+						// parallel lines meet halfway between their middles
+						(((a0.0 as f64 + a1.0 as f64 + b0.0 as f64 + b1.0 as f64) / 4.0) as i32, ((a0.1 as f64 + a1.1 as f64 + b0.1 as f64 + b1.1 as f64) / 4.0) as i32)
+					} else {
+						let t = ((b0.0 as f64 - a0.0 as f64) * dby - (b0.1 as f64 - a0.1 as f64) * dbx) / denominator;
+						((a0.0 as f64 + t * dax).round() as i32, (a0.1 as f64 + t * day).round() as i32)
+					};
+					let zone = self.zone_mut(2);
+					zone.check(point)?;
+					zone.current[point] = intersection;
+					zone.touched[point] |= TOUCHED_X | TOUCHED_Y;
+				}
+				// SRP0, SRP1, SRP2
+				0x10..=0x12 => self.state.reference_points[(opcode - 0x10) as usize] = self.pop_index()?,
+				// SZP0, SZP1, SZP2, SZPS
+				0x13..=0x16 => {
+					let zone = match self.pop()? {
+						0 => TWILIGHT_ZONE,
+						1 => GLYPH_ZONE,
+						_ => return Err(FontError::Malformed("hinting refers to a missing zone")),
+					};
+					if opcode == 0x16 {
+						self.state.zone_pointers = [zone; 3];
+					} else {
+						self.state.zone_pointers[(opcode - 0x13) as usize] = zone;
+					}
+				}
+				// SLOOP
+				0x17 => self.state.loop_count = self.pop()?.max(0),
+				0x18 => self.state.round_state = RoundState::ToGrid,
+				0x19 => self.state.round_state = RoundState::ToHalfGrid,
+				// SMD
+				0x1A => self.state.minimum_distance = self.pop()?,
+				// ELSE, only reached after the IF branch ran
+				0x1B => next = skip_to_end_of_if(code, next, end, false)?,
+				// JMPR
+				0x1C => next = jump(ip, self.pop()?, end)?,
+				// SCVTCI, SSWCI
+				0x1D => self.state.control_value_cutin = self.pop()?,
+				0x1E => self.state.single_width_cutin = self.pop()?,
+				// SSW, given in font units
+				0x1F => self.state.single_width_value = (self.pop()? as f32 * self.scale).round() as i32,
+				// DUP
+				0x20 => {
+					let value = self.pop()?;
+					self.push(value)?;
+					self.push(value)?;
+				}
+				// POP
+				0x21 => {
+					self.pop()?;
+				}
+				// CLEAR
+				0x22 => self.stack.clear(),
+				// SWAP
+				0x23 => {
+					let a = self.pop()?;
+					let b = self.pop()?;
+					self.push(a)?;
+					self.push(b)?;
+				}
+				// DEPTH
+				0x24 => self.push(self.stack.len() as i32)?,
+				// CINDEX, MINDEX
+				0x25 | 0x26 => {
+					let index = self.pop_index()?;
+					if index == 0 || index > self.stack.len() {
+						return Err(FontError::Malformed("hinting stack index out of range"));
+					}
+					let position = self.stack.len() - index;
+					let value = if opcode == 0x25 {self.stack[position]} else {self.stack.remove(position)};
+					self.push(value)?;
+				}
+				// ALIGNPTS
+				0x27 => {
+					let p2 = self.pop_index()?;
+					let p1 = self.pop_index()?;
+					let (a, b) = (self.point(1, p1)?, self.point(0, p2)?);
+					let distance = self.project((b.0.wrapping_sub(a.0), b.1.wrapping_sub(a.1))) / 2;
+					self.move_point(1, p1, distance, true)?;
+					self.move_point(0, p2, -distance, true)?;
+				}
+				// UTP
+				0x29 => {
+					let point = self.pop_index()?;
+					let (fx, fy) = self.state.freedom_vector;
+					let zone = self.zone_mut(0);
+					zone.check(point)?;
+					if fx != 0.0 {
+						zone.touched[point] &= !TOUCHED_X;
+					}
+					if fy != 0.0 {
+						zone.touched[point] &= !TOUCHED_Y;
+					}
+				}
+				// LOOPCALL, CALL
+				0x2A | 0x2B => {
+					let function = self.pop()?;
+					let count = if opcode == 0x2A {self.pop()?} else {1};
+					let definition = *self.functions.get(&function).ok_or(FontError::Malformed("hinting calls a missing function"))?;
+					for _ in 0..count.max(0) {
+						self.execute(definition.program, definition.start, definition.end, depth + 1)?;
+					}
+				}
+				// FDEF
+				0x2C => {
+					let function = self.pop()?;
+					let function_end = skip_to_endf(code, next, end)?;
+					self.functions.insert(function, Definition { program, start: next, end: function_end });
+					next = function_end + 1;
+				}
+				// ENDF
+				0x2D => {
+					if depth == 0 {
+						return Err(FontError::Malformed("ENDF outside of a function"));
+					}
+					return Ok(());
+				}
+				// MDAP
+				0x2E | 0x2F => {
+					let point = self.pop_index()?;
+					let distance = if opcode & 1 != 0 {
+						let position = self.project(self.point(0, point)?);
+						self.round(position).wrapping_sub(position)
+					} else {
+						0
+					};
+					self.move_point(0, point, distance, true)?;
+					self.state.reference_points[0] = point;
+					self.state.reference_points[1] = point;
+				}
+				// IUP
+				0x30 | 0x31 => self.interpolate_untouched(opcode & 1 != 0),
+				// SHP
+				0x32 | 0x33 => {
+					let (reference_zone, reference, dx, dy) = self.reference_displacement(opcode & 1 != 0)?;
+					let zone = self.state.zone_pointers[2];
+					for _ in 0..self.take_loop() {
+						let point = self.pop_index()?;
+						if zone == reference_zone && point == reference {
+							continue;
+						}
+						self.shift_point(zone, point, dx, dy, true)?;
+					}
+				}
+				// SHC
+				0x34 | 0x35 => {
+					let (reference_zone, reference, dx, dy) = self.reference_displacement(opcode & 1 != 0)?;
+					let contour = self.pop_index()?;
+					let zone = self.state.zone_pointers[2];
+					let end_points = &self.zones[zone].end_points;
+					let last = *end_points.get(contour).ok_or(FontError::Malformed("hinting refers to a missing contour"))?;
+					let first = if contour == 0 {0} else {end_points[contour - 1] + 1};
+					for point in first..=last {
+						if zone != reference_zone || point != reference {
+							self.shift_point(zone, point, dx, dy, true)?;
+						}
+					}
+				}
+				// SHZ, the phantom points stay where they are
+				0x36 | 0x37 => {
+					let (reference_zone, reference, dx, dy) = self.reference_displacement(opcode & 1 != 0)?;
+					let zone = match self.pop()? {
+						0 => TWILIGHT_ZONE,
+						1 => GLYPH_ZONE,
+						_ => return Err(FontError::Malformed("hinting refers to a missing zone")),
+					};
+					let count = self.zones[zone].current.len();
+					let count = if zone == GLYPH_ZONE {count.saturating_sub(4)} else {count};
+					for point in 0..count {
+						if zone != reference_zone || point != reference {
+							self.shift_point(zone, point, dx, dy, false)?;
+						}
+					}
+				}
+				// SHPIX
+				0x38 => {
+					let distance = self.pop()?;
+					let (fx, fy) = self.state.freedom_vector;
+					let (dx, dy) = ((distance as f32 * fx).round() as i32, (distance as f32 * fy).round() as i32);
+					let zone = self.state.zone_pointers[2];
+					for _ in 0..self.take_loop() {
+						let point = self.pop_index()?;
+						self.shift_point(zone, point, dx, dy, true)?;
+					}
+				}
+				// IP
+				0x39 => self.interpolate_points()?,
+				// MSIRP
+				0x3A | 0x3B => {
+					let distance = self.pop()?;
+					let point = self.pop_index()?;
+					let rp0 = self.state.reference_points[0];
+					if self.state.zone_pointers[1] == TWILIGHT_ZONE {
+						let origin = self.original_point(0, rp0)?;
+						let zone = self.zone_mut(1);
+						zone.check(point)?;
+						zone.original[point] = origin;
+						zone.current[point] = origin;
+					}
+					let (a, b) = (self.point(1, point)?, self.point(0, rp0)?);
+					let current = self.project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)));
+					self.move_point(1, point, distance.wrapping_sub(current), true)?;
+					self.state.reference_points[1] = rp0;
+					self.state.reference_points[2] = point;
+					if opcode & 1 != 0 {
+						self.state.reference_points[0] = point;
+					}
+				}
+				// ALIGNRP
+				0x3C => {
+					let rp0 = self.state.reference_points[0];
+					for _ in 0..self.take_loop() {
+						let point = self.pop_index()?;
+						let (a, b) = (self.point(1, point)?, self.point(0, rp0)?);
+						let distance = self.project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)));
+						self.move_point(1, point, distance.wrapping_neg(), true)?;
+					}
+				}
+				0x3D => self.state.round_state = RoundState::ToDoubleGrid,
+				// MIAP
+				0x3E | 0x3F => {
+					let index = self.pop_index()?;
+					let point = self.pop_index()?;
+					let mut distance = self.control_value(index)?;
+					// twilight points are placed along the freedom vector first
+					if self.state.zone_pointers[0] == TWILIGHT_ZONE {
+						let (fx, fy) = self.state.freedom_vector;
+						let position = ((distance as f32 * fx).round() as i32, (distance as f32 * fy).round() as i32);
+						let zone = self.zone_mut(0);
+						zone.check(point)?;
+						zone.original[point] = position;
+						zone.current[point] = position;
+					}
+					let current = self.project(self.point(0, point)?);
+					if opcode & 1 != 0 {
+						if distance.wrapping_sub(current).abs() > self.state.control_value_cutin {
+							distance = current;
+						}
+						distance = self.round(distance);
+					}
+					self.move_point(0, point, distance.wrapping_sub(current), true)?;
+					self.state.reference_points[0] = point;
+					self.state.reference_points[1] = point;
+				}
+				// NPUSHB, NPUSHW, PUSHB, PUSHW
+				0x40 | 0x41 | 0xB0..=0xBF => {
+					let (count, words, data_start) = match opcode {
+						0x40 => (code[ip + 1] as usize, false, ip + 2),
+						0x41 => (code[ip + 1] as usize, true, ip + 2),
+						_ => ((opcode & 0x07) as usize + 1, opcode >= 0xB8, ip + 1),
+					};
+					for i in 0..count {
+						let value = if words {
+							i16::from_be_bytes([code[data_start + i * 2], code[data_start + i * 2 + 1]]) as i32
+						} else {
+							code[data_start + i] as i32
+						};
+						self.push(value)?;
+					}
+				}
+				// WS, RS
+				0x42 => {
+					let value = self.pop()?;
+					let index = self.pop_index()?;
+					*self.storage.get_mut(index).ok_or(FontError::Malformed("hinting refers to missing storage"))? = value;
+				}
+				0x43 => {
+					let index = self.pop_index()?;
+					let value = *self.storage.get(index).ok_or(FontError::Malformed("hinting refers to missing storage"))?;
+					self.push(value)?;
+				}
+				// WCVTP, WCVTF
+				0x44 | 0x70 => {
+					let value = self.pop()?;
+					let index = self.pop_index()?;
+					let value = if opcode == 0x70 {(value as f32 * self.scale).round() as i32} else {value};
+					self.set_control_value(index, value)?;
+				}
+				// RCVT
+				0x45 => {
+					let index = self.pop_index()?;
+					let value = self.control_value(index)?;
+					self.push(value)?;
+				}
+				// GC
+				0x46 | 0x47 => {
+					let point = self.pop_index()?;
+					let value = if opcode & 1 != 0 {self.dual_project(self.original_point(2, point)?)} else {self.project(self.point(2, point)?)};
+					self.push(value)?;
+				}
+				// SCFS
+				0x48 => {
+					let value = self.pop()?;
+					let point = self.pop_index()?;
+					let current = self.project(self.point(2, point)?);
+					self.move_point(2, point, value.wrapping_sub(current), true)?;
+					if self.state.zone_pointers[2] == TWILIGHT_ZONE {
+						let zone = self.zone_mut(2);
+						zone.original[point] = zone.current[point];
+					}
+				}
+				// MD, 0x49 measures the grid-fitted outline and 0x4A the original one
+				0x49 | 0x4A => {
+					let p2 = self.pop_index()?;
+					let p1 = self.pop_index()?;
+					let distance = if opcode == 0x49 {
+						let (a, b) = (self.point(0, p1)?, self.point(1, p2)?);
+						self.project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)))
+					} else {
+						let (a, b) = (self.original_point(0, p1)?, self.original_point(1, p2)?);
+						self.dual_project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)))
+					};
+					self.push(distance)?;
+				}
+				// MPPEM, MPS
+				0x4B | 0x4C => self.push(self.ppem as i32)?,
+				0x4D => self.state.auto_flip = true,
+				0x4E => self.state.auto_flip = false,
+				// DEBUG
+				0x4F => {
+					self.pop()?;
+				}
+				// LT, LTEQ, GT, GTEQ, EQ, NEQ
+				0x50..=0x55 => {
+					let b = self.pop()?;
+					let a = self.pop()?;
+					let result = match opcode {
+						0x50 => a < b,
+						0x51 => a <= b,
+						0x52 => a > b,
+						0x53 => a >= b,
+						0x54 => a == b,
+						_ => a != b,
+					};
+					self.push(result as i32)?;
+				}
+				// ODD, EVEN, after rounding
+				0x56 | 0x57 => {
+					let value = self.pop()?;
+					let is_odd = self.round(value) & 127 == 64;
+					self.push((is_odd == (opcode == 0x56)) as i32)?;
+				}
+				// IF
+				0x58 => {
+					if self.pop()? == 0 {
+						next = skip_to_end_of_if(code, next, end, true)?;
+					}
+				}
+				// EIF
+				0x59 => {}
+				// AND, OR
+				0x5A => {
+					let b = self.pop()?;
+					let a = self.pop()?;
+					self.push((a != 0 && b != 0) as i32)?;
+				}
+				0x5B => {
+					let b = self.pop()?;
+					let a = self.pop()?;
+					self.push((a != 0 || b != 0) as i32)?;
+				}
+				// NOT
+				0x5C => {
+					let value = self.pop()?;
+					self.push((value == 0) as i32)?;
+				}
+				// DELTAP1, DELTAP2, DELTAP3
+				0x5D | 0x71 | 0x72 => {
+					let base = match opcode {0x5D => 0, 0x71 => 16, _ => 32};
+					let count = self.pop()?;
+					for _ in 0..count.max(0) {
+						let point = self.pop_index()?;
+						let argument = self.pop()?;
+						if let Some(distance) = self.delta_distance(argument, base) {
+							self.move_point(0, point, distance, true)?;
+						} else {
+							self.zone(0).check(point)?;
+						}
+					}
+				}
+				// SDB, SDS
+				0x5E => self.state.delta_base = self.pop()?,
+				0x5F => self.state.delta_shift = self.pop()?.clamp(0, 6),
+				// ADD, SUB, DIV, MUL
+				0x60..=0x63 => {
+					let b = self.pop()?;
+					let a = self.pop()?;
+					let result = match opcode {
+						0x60 => a.wrapping_add(b),
+						0x61 => a.wrapping_sub(b),
+						0x62 => {
+							if b == 0 {
+								return Err(FontError::Malformed("hinting divides by zero"));
+							}
+							(a as i64 * 64 / b as i64) as i32
+						}
+						_ => ((a as i64 * b as i64 + 32) >> 6) as i32,
+					};
+					self.push(result)?;
+				}
+				// ABS, NEG, FLOOR, CEILING
+				0x64 => {
+					let value = self.pop()?;
+					self.push(value.wrapping_abs())?;
+				}
+				0x65 => {
+					let value = self.pop()?;
+					self.push(value.wrapping_neg())?;
+				}
+				0x66 => {
+					let value = self.pop()?;
+					self.push(value & !63)?;
+				}
+				0x67 => {
+					let value = self.pop()?;
+					self.push(value.wrapping_add(63) & !63)?;
+				}
+				// ROUND, NROUND, engine compensation is always zero
+				0x68..=0x6B => {
+					let value = self.pop()?;
+					self.push(self.round(value))?;
+				}
+				0x6C..=0x6F => {}
+				// DELTAC1, DELTAC2, DELTAC3
+				0x73..=0x75 => {
+					let base = (opcode as i32 - 0x73) * 16;
+					let count = self.pop()?;
+					for _ in 0..count.max(0) {
+						let index = self.pop_index()?;
+						let argument = self.pop()?;
+						if let Some(distance) = self.delta_distance(argument, base) {
+							let value = self.control_value(index)?;
+							self.set_control_value(index, value.wrapping_add(distance))?;
+						}
+					}
+				}
+				// SROUND, S45ROUND
+				0x76 => {
+					let selector = self.pop()?;
+					self.super_round(selector, ONE_PIXEL as f32);
+				}
+				0x77 => {
+					let selector = self.pop()?;
+					self.super_round(selector, ONE_PIXEL as f32 * std::f32::consts::FRAC_1_SQRT_2);
+				}
+				// JROT, JROF
+				0x78 | 0x79 => {
+					let condition = self.pop()?;
+					let offset = self.pop()?;
+					if (condition != 0) == (opcode == 0x78) {
+						next = jump(ip, offset, end)?;
+					}
+				}
+				0x7A => self.state.round_state = RoundState::Off,
+				0x7C => self.state.round_state = RoundState::UpToGrid,
+				0x7D => self.state.round_state = RoundState::DownToGrid,
+				// SANGW, AA are obsolete and only take their argument
+				0x7E | 0x7F => {
+					self.pop()?;
+				}
+				// FLIPPT
+				0x80 => {
+					for _ in 0..self.take_loop() {
+						let point = self.pop_index()?;
+						let zone = &mut self.zones[GLYPH_ZONE];
+						zone.check(point)?;
+						zone.on_curve[point] = !zone.on_curve[point];
+					}
+				}
+				// FLIPRGON, FLIPRGOFF
+				0x81 | 0x82 => {
+					let last = self.pop_index()?;
+					let first = self.pop_index()?;
+					let zone = &mut self.zones[GLYPH_ZONE];
+					zone.check(last)?;
+					for point in first..=last {
+						zone.on_curve[point] = opcode == 0x81;
+					}
+				}
+				// SCANCTRL, SCANTYPE only matter to the rasterizer's dropout control
+				0x85 | 0x8D => {
+					self.pop()?;
+				}
+				// SDPVTL
+				0x86 | 0x87 => {
+					let stack_depth = self.stack.len();
+					let projection = self.vector_from_line(opcode & 1 != 0, false)?;
+					self.stack.truncate(stack_depth);
+					let dual = self.vector_from_line(opcode & 1 != 0, true)?;
+					self.state.projection_vector = projection;
+					self.state.dual_vector = dual;
+				}
+				// GETINFO, answering as a version 35 grayscale interpreter
+				0x88 => {
+					let selector = self.pop()?;
+					let mut result = 0;
+					if selector & 0x01 != 0 {
+						result |= 35;
+					}
+					if selector & 0x08 != 0 && !self.coordinates.is_empty() {
+						result |= 0x400;
+					}
+					if selector & 0x20 != 0 {
+						result |= 0x1000;
+					}
+					self.push(result)?;
+				}
+				// IDEF
+				0x89 => {
+					let defined = self.pop()?;
+					let definition_end = skip_to_endf(code, next, end)?;
+					self.instruction_defs.insert(defined as u8, Definition { program, start: next, end: definition_end });
+					next = definition_end + 1;
+				}
+				// ROLL
+				0x8A => {
+					let a = self.pop()?;
+					let b = self.pop()?;
+					let c = self.pop()?;
+					self.push(b)?;
+					self.push(a)?;
+					self.push(c)?;
+				}
+				// MAX, MIN
+				0x8B | 0x8C => {
+					let b = self.pop()?;
+					let a = self.pop()?;
+					self.push(if opcode == 0x8B {a.max(b)} else {a.min(b)})?;
+				}
+				// INSTCTRL, only prep may use it
+				0x8E => {
+					let selector = self.pop()?;
+					let value = self.pop()?;
+					if program == CONTROL_VALUE_PROGRAM && (1..=3).contains(&selector) {
+						let flag = 1 << (selector - 1);
+						self.state.instruct_control = if value != 0 {self.state.instruct_control | flag} else {self.state.instruct_control & !flag};
+					}
+				}
+				// GETVARIATION, the normalized coordinates as 2.14
+				0x91 => {
+					for i in 0..self.coordinates.len() {
+						self.push((self.coordinates[i] * 16384.0).round() as i32)?;
+					}
+				}
+				// GETDATA
+				0x92 => self.push(17)?,
+				// MDRP
+				0xC0..=0xDF => self.move_direct_relative(opcode)?,
+				// MIRP
+				0xE0..=0xFF => self.move_indirect_relative(opcode)?,
+				_ => {
+					let definition = *self.instruction_defs.get(&opcode).ok_or(FontError::Malformed("unknown hinting instruction"))?;
+					self.execute(definition.program, definition.start, definition.end, depth + 1)?;
+				}
+			}
+			ip = next;
+		}
+		Ok(())
+	}
+
+	fn take_loop(&mut self) -> i32 {
+		let count = self.state.loop_count;
+		self.state.loop_count = 1;
+		count
+	}
+
+	// the ppem a delta applies at and how far it moves, None at other sizes
+	fn delta_distance(&self, argument: i32, base: i32) -> Option<i32> {
+		let ppem = ((argument & 0xF0) >> 4).wrapping_add(base).wrapping_add(self.state.delta_base);
+		if ppem != self.ppem as i32 {
+			return None;
+		}
+		let mut steps = (argument & 0x0F) - 8;
+		if steps >= 0 {
+			steps += 1;
+		}
+		Some(steps * (1 << (6 - self.state.delta_shift)))
+	}
+
+	fn move_direct_relative(&mut self, opcode: u8) -> Result<(), FontError> {
+		let point = self.pop_index()?;
+		let rp0 = self.state.reference_points[0];
+		let (a, b) = (self.original_point(1, point)?, self.original_point(0, rp0)?);
+		let mut original_distance = self.dual_project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)));
+		if original_distance.wrapping_sub(self.state.single_width_value).abs() < self.state.single_width_cutin {
+			original_distance = if original_distance >= 0 {self.state.single_width_value} else {-self.state.single_width_value};
+		}
+		let mut distance = if opcode & 0x04 != 0 {self.round(original_distance)} else {original_distance};
+		if opcode & 0x08 != 0 {
+			distance = keep_minimum_distance(distance, original_distance, self.state.minimum_distance);
+		}
+		let (a, b) = (self.point(1, point)?, self.point(0, rp0)?);
+		let current = self.project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)));
+		self.move_point(1, point, distance.wrapping_sub(current), true)?;
+		self.state.reference_points[1] = rp0;
+		self.state.reference_points[2] = point;
+		if opcode & 0x10 != 0 {
+			self.state.reference_points[0] = point;
+		}
+		Ok(())
+	}
+
+	fn move_indirect_relative(&mut self, opcode: u8) -> Result<(), FontError> {
+		let index = self.pop()?;
+		let point = self.pop_index()?;
+		let rp0 = self.state.reference_points[0];
+		// -1 is allowed and stands for a distance of zero
+		let mut control_value = if index == -1 {0} else {self.control_value(usize::try_from(index).map_err(|_| FontError::Malformed("negative index in hinting program"))?)?};
+		if control_value.wrapping_sub(self.state.single_width_value).abs() < self.state.single_width_cutin {
+			control_value = if control_value >= 0 {self.state.single_width_value} else {-self.state.single_width_value};
+		}
+		// twilight points are placed at the control value from the reference point first
+		if self.state.zone_pointers[1] == TWILIGHT_ZONE {
+			let origin = self.original_point(0, rp0)?;
+			let (fx, fy) = self.state.freedom_vector;
+			let position = (origin.0.wrapping_add((control_value as f32 * fx).round() as i32), origin.1.wrapping_add((control_value as f32 * fy).round() as i32));
+			let zone = self.zone_mut(1);
+			zone.check(point)?;
+			zone.original[point] = position;
+			zone.current[point] = position;
+		}
+		let (a, b) = (self.original_point(1, point)?, self.original_point(0, rp0)?);
+		let original_distance = self.dual_project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)));
+		let (a, b) = (self.point(1, point)?, self.point(0, rp0)?);
+		let current = self.project((a.0.wrapping_sub(b.0), a.1.wrapping_sub(b.1)));
+		if self.state.auto_flip && (original_distance ^ control_value) < 0 {
+			control_value = control_value.wrapping_neg();
+		}
+		let mut distance = if opcode & 0x04 != 0 {
+			// the cut-in only applies when both points are in the same zone
+			if self.state.zone_pointers[0] == self.state.zone_pointers[1] && control_value.wrapping_sub(original_distance).abs() > self.state.control_value_cutin {
+				control_value = original_distance;
+			}
+			self.round(control_value)
+		} else {
+			control_value
+		};
+		if opcode & 0x08 != 0 {
+			distance = keep_minimum_distance(distance, original_distance, self.state.minimum_distance);
+		}
+		self.move_point(1, point, distance.wrapping_sub(current), true)?;
+		self.state.reference_points[1] = rp0;
+		self.state.reference_points[2] = point;
+		if opcode & 0x10 != 0 {
+			self.state.reference_points[0] = point;
+		}
+		Ok(())
+	}
+
+	// IP: keeps each point's relative position between rp1 and rp2 from the original outline
+	fn interpolate_points(&mut self) -> Result<(), FontError> {
+		let (rp1, rp2) = (self.state.reference_points[1], self.state.reference_points[2]);
+		let original_base = self.original_point(0, rp1)?;
+		let current_base = self.point(0, rp1)?;
+		let original_other = self.original_point(1, rp2)?;
+		let current_other = self.point(1, rp2)?;
+		let original_range = self.dual_project((original_other.0.wrapping_sub(original_base.0), original_other.1.wrapping_sub(original_base.1)));
+		let current_range = self.project((current_other.0.wrapping_sub(current_base.0), current_other.1.wrapping_sub(current_base.1)));
+		for _ in 0..self.take_loop() {
+			let point = self.pop_index()?;
+			let original = self.original_point(2, point)?;
+			let current = self.point(2, point)?;
+			let original_distance = self.dual_project((original.0.wrapping_sub(original_base.0), original.1.wrapping_sub(original_base.1)));
+			let current_distance = self.project((current.0.wrapping_sub(current_base.0), current.1.wrapping_sub(current_base.1)));
+			let new_distance = if original_distance == 0 {
+				0
+			} else if original_range != 0 {
+				(original_distance as i64 * current_range as i64 / original_range as i64) as i32
+			} else {
+				current_distance
+			};
+			self.move_point(2, point, new_distance.wrapping_sub(current_distance), true)?;
+		}
+		Ok(())
+	}
+
+	// IUP: untouched points follow the touched ones around them, along one axis
+	fn interpolate_untouched(&mut self, x_axis: bool) {
+		let flag = if x_axis {TOUCHED_X} else {TOUCHED_Y};
+		let axis = |point: (i32, i32)| if x_axis {point.0} else {point.1};
+		let zone = &mut self.zones[GLYPH_ZONE];
+		let mut start = 0;
+		for contour in 0..zone.end_points.len() {
+			let end = zone.end_points[contour];
+			if end < start || end >= zone.current.len() {
+				break;
+			}
+			let touched: Vec<usize> = (start..=end).filter(|&point| zone.touched[point] & flag != 0).collect();
+			if touched.len() == 1 {
+				let reference = touched[0];
+				let shift = axis(zone.current[reference]).wrapping_sub(axis(zone.original[reference]));
+				for point in (start..=end).filter(|&point| point != reference) {
+					let value = axis(zone.original[point]).wrapping_add(shift);
+					set_axis(&mut zone.current[point], x_axis, value);
+				}
+			} else if touched.len() > 1 {
+				for (i, &first) in touched.iter().enumerate() {
+					let second = touched[(i + 1) % touched.len()];
+					let mut point = if first == end {start} else {first + 1};
+					while point != second {
+						let value = interpolate_axis(axis(zone.original[point]), (axis(zone.original[first]), axis(zone.current[first])), (axis(zone.original[second]), axis(zone.current[second])));
+						set_axis(&mut zone.current[point], x_axis, value);
+						point = if point == end {start} else {point + 1};
+					}
+				}
+			}
+			start = end + 1;
+		}
+	}
+}
+
+fn set_axis(point: &mut (i32, i32), x_axis: bool, value: i32) {
+	if x_axis {
+		point.0 = value;
+	} else {
+		point.1 = value;
+	}
+}
+
+// references are (original, current)
+fn interpolate_axis(original: i32, first: (i32, i32), second: (i32, i32)) -> i32 {
+	let (low, high) = if first.0 <= second.0 {(first, second)} else {(second, first)};
+	if original <= low.0 {
+		original.wrapping_add(low.1.wrapping_sub(low.0))
+	} else if original >= high.0 {
+		original.wrapping_add(high.1.wrapping_sub(high.0))
+	} else {
+		let (offset, range) = (original as i64 - low.0 as i64, high.0 as i64 - low.0 as i64);
+		low.1.wrapping_add((offset * (high.1 as i64 - low.1 as i64) / range) as i32)
+	}
+}
+
+fn keep_minimum_distance(distance: i32, original_distance: i32, minimum_distance: i32) -> i32 {
+	if original_distance >= 0 {
+		distance.max(minimum_distance)
+	} else {
+		distance.min(minimum_distance.wrapping_neg())
+	}
+}
+
+fn normalize(x: f32, y: f32) -> (f32, f32) {
+	let length = (x * x + y * y).sqrt();
+	if length == 0.0 {
+		return (1.0, 0.0);
+	}
+	(x / length, y / length)
+}
+
+// jumps are relative to the jump instruction itself
+fn jump(ip: usize, offset: i32, end: usize) -> Result<usize, FontError> {
+	let target = ip as i64 + offset as i64;
+	if offset == 0 || target < 0 || target > end as i64 {
+		return Err(FontError::Malformed("hinting jumps out of its program"));
+	}
+	Ok(target as usize)
+}
+
+// push instructions carry their data inline
+fn instruction_length(code: &[u8], ip: usize) -> Result<usize, FontError> {
+	let length = match code[ip] {
+		0x40 => 2 + *code.get(ip + 1).ok_or(FontError::Malformed("truncated hinting instruction"))? as usize,
+		0x41 => 2 + *code.get(ip + 1).ok_or(FontError::Malformed("truncated hinting instruction"))? as usize * 2,
+		opcode @ 0xB0..=0xB7 => 2 + (opcode - 0xB0) as usize,
+		opcode @ 0xB8..=0xBF => 3 + (opcode - 0xB8) as usize * 2,
+		_ => 1,
+	};
+	if ip + length > code.len() {
+		return Err(FontError::Malformed("truncated hinting instruction"));
+	}
+	Ok(length)
+}
+
+// past the matching ELSE (when coming from a false IF) or EIF
+fn skip_to_end_of_if(code: &[u8], start: usize, end: usize, stop_at_else: bool) -> Result<usize, FontError> {
+	let mut nesting = 0;
+	let mut ip = start;
+	while ip < end {
+		match code[ip] {
+			0x58 => nesting += 1,
+			0x1B if nesting == 0 && stop_at_else => return Ok(ip + 1),
+			0x59 if nesting == 0 => return Ok(ip + 1),
+			0x59 => nesting -= 1,
+			_ => {}
+		}
+		ip += instruction_length(code, ip)?;
+	}
+	Err(FontError::Malformed("IF without EIF"))
+}
+
+fn skip_to_endf(code: &[u8], start: usize, end: usize) -> Result<usize, FontError> {
+	let mut ip = start;
+	while ip < end {
+		match code[ip] {
+			0x2D => return Ok(ip),
+			0x2C | 0x89 => return Err(FontError::Malformed("nested function definition")),
+			_ => {}
+		}
+		ip += instruction_length(code, ip)?;
+	}
+	Err(FontError::Malformed("FDEF without ENDF"))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// pushes 2^30 through F26Dot6 multiplies, PUSHW only takes 16 bit values
+	const PUSH_2_POW_30: [u8; 9] = [0xBA, 0x40, 0x00, 0x40, 0x00, 0x40, 0x00, 0x63, 0x63];
+	// DUP, ADD wraps around to -2^31
+	const DOUBLE: [u8; 2] = [0x20, 0x60];
+	const SHPIX: u8 = 0x38;
+
+	fn instance(font_program: Vec<u8>) -> Result<HintingInstance, FontError> {
+		let tables = HintingTables {
+			units_per_em: 16,
+			limits: MaxpLimits { max_zones: 2, max_twilight_points: 4, max_stack_elements: 16, ..Default::default() },
+			font_program,
+			control_value_program: vec![],
+			control_values: vec![],
+		};
+		HintingInstance::new(&tables, 12, &[])
+	}
+
+	#[test]
+	fn isect_of_points_moved_to_the_ends_of_the_range() {
+		let mut font_program = vec![0xB0, 0, 0x16]; // SZPS twilight
+		font_program.extend([0xB0, 1]);
+		font_program.extend(PUSH_2_POW_30);
+		font_program.extend(DOUBLE);
+		font_program.push(SHPIX);
+		font_program.extend([0xB0, 0]);
+		font_program.extend(PUSH_2_POW_30);
+		font_program.push(SHPIX);
+		// ISECT point 2 with a0 1, a1 0, b0 2 and b1 3
+		font_program.extend([0xB4, 2, 1, 0, 2, 3, 0x0F]);
+		assert!(instance(font_program).is_ok());
+	}
+
+	#[test]
+	fn loopcall_of_an_empty_function_runs_out() {
+		// FDEF 0 with only ENDF, then LOOPCALL it 2^30 times
+		let mut font_program = vec![0xB0, 0, 0x2C, 0x2D];
+		font_program.extend(PUSH_2_POW_30);
+		font_program.extend([0xB0, 0, 0x2A]);
+		assert!(matches!(instance(font_program), Err(FontError::Malformed("hinting program runs too long"))));
+	}
+
+	#[test]
+	fn delta_base_at_the_end_of_the_range() {
+		let mut font_program = vec![0xB0, 0, 0x16];
+		// SDB 2^31 - 1, then DELTAP1 on twilight point 0 with a ppem nibble of 15
+		font_program.extend(PUSH_2_POW_30);
+		font_program.extend(DOUBLE);
+		font_program.extend([0xB0, 1, 0x61, 0x5E]);
+		font_program.extend([0xB2, 0xF0, 0, 1, 0x5D]);
+		assert!(instance(font_program).is_ok());
+	}
+
+	#[test]
+	fn iup_shift_past_the_range() {
+		let hinting = instance(vec![]).unwrap();
+		// SHPIX point 0 by 2^31 - 1 then IUP[x], which shifts the untouched points of the contour with it
+		let mut instructions = vec![0xB0, 0];
+		instructions.extend(PUSH_2_POW_30);
+		instructions.extend(DOUBLE);
+		instructions.extend([0xB0, 1, 0x61, SHPIX, 0x31]);
+		let mut points = vec![(0.0, 0.0), (10.0, 0.0), (20.0, 10.0), (0.0, 0.0), (20.0, 0.0), (0.0, 0.0), (0.0, 0.0)];
+		let mut on_curve = vec![true; 7];
+		hinting.hint_glyph(&mut points, &mut on_curve, &[2], &instructions).unwrap();
+	}
+
+	#[test]
+	fn iup_interpolates_between_touched_points() {
+		let hinting = instance(vec![]).unwrap();
+		// SHPIX points 0 and 2 by one pixel then IUP[x], point 1 halfway between them moves with them
+		let instructions = [0xB0, 2, 0xB0, 0, 0xB0, 64, SHPIX, 0xB0, 64, SHPIX, 0x31];
+		let mut points = vec![(0.0, 0.0), (8.0, 0.0), (16.0, 0.0), (0.0, 0.0), (16.0, 0.0), (0.0, 0.0), (0.0, 0.0)];
+		let mut on_curve = vec![true; 7];
+		hinting.hint_glyph(&mut points, &mut on_curve, &[2], &instructions).unwrap();
+		// 12 ppem at 16 units per em is 0.75 pixels per unit, one pixel is 4 / 3 units
+		assert!(points[..3].iter().zip([0.0, 8.0, 16.0]).all(|(point, x)| (point.0 - x - 4.0 / 3.0).abs() < 0.01), "{:?}", points);
+	}
+}
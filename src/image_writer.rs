@@ -0,0 +1,133 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+use flate2::write::ZlibEncoder;
+use flate2::{Compression, Crc};
+
+use crate::rasterizer::Bitmap;
+
+pub type Color = [u8; 3];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+	Png,
+	// binary netpbm, P5 grey and P6 colour
+	Pgm,
+	Ppm,
+}
+
+impl ImageFormat {
+	pub fn from_extension(path: &Path) -> Option<ImageFormat> {
+		let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+		ImageFormat::from_name(&extension)
+	}
+
+	pub fn from_name(name: &str) -> Option<ImageFormat> {
+		match name {
+			"png" => Some(ImageFormat::Png),
+			"pgm" => Some(ImageFormat::Pgm),
+			"ppm" => Some(ImageFormat::Ppm),
+			_ => None,
+		}
+	}
+}
+
+// "#rrggbb", "rrggbb" or the short "#rgb"
+pub fn parse_color(text: &str) -> Option<Color> {
+	let hex = text.strip_prefix('#').unwrap_or(text);
+	if !hex.is_ascii() {
+		return None;
+	}
+	match hex.len() {
+		6 => Some([
+			u8::from_str_radix(&hex[0..2], 16).ok()?,
+			u8::from_str_radix(&hex[2..4], 16).ok()?,
+			u8::from_str_radix(&hex[4..6], 16).ok()?,
+		]),
+		3 => {
+			let mut color = [0; 3];
+			for (channel, digit) in color.iter_mut().zip(hex.chars()) {
+				*channel = digit.to_digit(16)? as u8 * 0x11;
+			}
+			Some(color)
+		},
+		_ => None,
+	}
+}
+
+// the coverage mixes the foreground over the background, rgb rows top to bottom
+pub fn colorize(bitmap: &Bitmap, foreground: Color, background: Color) -> Vec<u8> {
+	bitmap.pixels.iter().flat_map(|&alpha| {
+		let alpha = alpha as u32;
+		let mut pixel = [0; 3];
+		for channel in 0..3 {
+			pixel[channel] = ((foreground[channel] as u32 * alpha + background[channel] as u32 * (255 - alpha) + 127) / 255) as u8;
+		}
+		pixel
+	}).collect()
+}
+
+// rec. 601 luma, what a grey image of the same colours looks like
+fn to_grey(rgb: &[u8]) -> Vec<u8> {
+	rgb.chunks_exact(3).map(|pixel| {
+		((pixel[0] as u32 * 299 + pixel[1] as u32 * 587 + pixel[2] as u32 * 114 + 500) / 1000) as u8
+	}).collect()
+}
+
+// dpi is only recorded in png files, netpbm has nowhere to put it
+pub fn write_image(path: &Path, format: ImageFormat, width: u32, height: u32, rgb: &[u8], dpi: f32) -> io::Result<()> {
+	let mut writer = BufWriter::new(File::create(path)?);
+	match format {
+		ImageFormat::Png => write_png(&mut writer, width, height, rgb, dpi)?,
+		ImageFormat::Pgm => {
+			write!(writer, "P5\n{} {}\n255\n", width, height)?;
+			writer.write_all(&to_grey(rgb))?;
+		},
+		ImageFormat::Ppm => {
+			write!(writer, "P6\n{} {}\n255\n", width, height)?;
+			writer.write_all(rgb)?;
+		},
+	}
+	writer.flush()
+}
+
+// 8 bit truecolour without interlacing, every row uses filter type 0
+pub fn write_png(writer: &mut impl Write, width: u32, height: u32, rgb: &[u8], dpi: f32) -> io::Result<()> {
+	const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+	writer.write_all(&SIGNATURE)?;
+
+	let mut header: Vec<u8> = vec![];
+	header.extend(width.to_be_bytes());
+	header.extend(height.to_be_bytes());
+	header.extend([8, 2, 0, 0, 0]); // bit depth, colour type, compression, filter, interlace
+	write_png_chunk(writer, b"IHDR", &header)?;
+
+	// pixels per metre on both axes, unit 1 is the metre
+	let pixels_per_metre = (dpi / 0.0254).round() as u32;
+	let mut physical: Vec<u8> = vec![];
+	physical.extend(pixels_per_metre.to_be_bytes());
+	physical.extend(pixels_per_metre.to_be_bytes());
+	physical.push(1);
+	write_png_chunk(writer, b"pHYs", &physical)?;
+
+	let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+	let stride = width as usize * 3;
+	for row in rgb.chunks_exact(stride.max(1)).take(height as usize) {
+		encoder.write_all(&[0])?;
+		encoder.write_all(row)?;
+	}
+	write_png_chunk(writer, b"IDAT", &encoder.finish()?)?;
+	write_png_chunk(writer, b"IEND", &[])
+}
+
+// the crc covers the chunk type and data but not the length
+fn write_png_chunk(writer: &mut impl Write, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+	writer.write_all(&(data.len() as u32).to_be_bytes())?;
+	writer.write_all(chunk_type)?;
+	writer.write_all(data)?;
+	let mut crc = Crc::new();
+	crc.update(chunk_type);
+	crc.update(data);
+	writer.write_all(&crc.sum().to_be_bytes())
+}
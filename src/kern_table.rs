@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+
+use crate::font_error::FontError;
+use crate::reader::Reader;
+
+#[derive(Debug, Clone)]
+pub struct ClassKerning {
+	pub left_first_glyph: u16,
+	pub left_classes: Vec<u16>,
+	pub right_first_glyph: u16,
+	pub right_classes: Vec<u16>,
+	// class values are byte offsets from the start of the subtable, left ones already include the array offset
+	pub array_offset: usize,
+	pub values: Vec<i16>,
+}
+
+#[derive(Debug, Clone)]
+pub enum KernData {
+	Pairs(HashMap<(u16, u16), i16>),
+	Classes(ClassKerning),
+}
+
+#[derive(Debug, Clone)]
+pub struct KernSubtable {
+	pub horizontal: bool,
+	pub cross_stream: bool,
+	pub variation: bool,
+	pub minimum: bool,
+	pub replace: bool,
+	pub data: KernData,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct KernTable {
+	pub subtables: Vec<KernSubtable>,
+}
+
+impl KernTable {
+	// windows tables start with a 16 bit version of 0, apple ones with a 32 bit version of 1.0
+	pub fn parse(kern: Reader) -> Result<KernTable, FontError> {
+		let mut reader = kern;
+		let version = reader.read_u16()?;
+		let mut subtables: Vec<KernSubtable> = vec![];
+		match version {
+			0 => {
+				let n_tables = reader.read_u16()?;
+				for _ in 0..n_tables {
+					let start = reader.offset();
+					reader.skip(2)?; //skip version
+					let length = reader.read_u16()? as usize;
+					let format = reader.read_u8()?;
+					let coverage = reader.read_u8()?;
+					// a lone subtable may be longer than its 16 bit length field can say
+					let subtable = if n_tables == 1 {kern.slice_from(start)?} else {kern.sub_reader(start, length)?};
+					if let Some(data) = parse_kern_data(format, subtable, 6)? {
+						subtables.push(KernSubtable {
+							horizontal: 	coverage & 0x01 != 0,
+							minimum: 		coverage & 0x02 != 0,
+							cross_stream: 	coverage & 0x04 != 0,
+							replace: 		coverage & 0x08 != 0,
+							variation: 		false,
+							data,
+						});
+					}
+					if n_tables > 1 {
+						reader.seek(start + length)?;
+					}
+				}
+			}
+			1 => {
+				reader.skip(2)?; //skip the rest of the fixed version
+				let n_tables = reader.read_u32()?;
+				for _ in 0..n_tables {
+					let start = reader.offset();
+					let length = reader.read_u32()? as usize;
+					let coverage = reader.read_u8()?;
+					let format = reader.read_u8()?;
+					reader.skip(2)?; //skip tupleIndex
+					let subtable = kern.sub_reader(start, length)?;
+					// format 1 is a state machine and 3 a compact class table, neither is applied here
+					if let Some(data) = parse_kern_data(format, subtable, 8)? {
+						subtables.push(KernSubtable {
+							horizontal: 	coverage & 0x80 == 0,
+							cross_stream: 	coverage & 0x40 != 0,
+							variation: 		coverage & 0x20 != 0,
+							minimum: 		false,
+							replace: 		false,
+							data,
+						});
+					}
+					reader.seek(start + length)?;
+				}
+			}
+			_ => return Err(FontError::UnsupportedFormat { table: "kern", format: version as u32 }),
+		}
+		Ok(KernTable { subtables })
+	}
+
+	// sum of every plain horizontal subtable, an override subtable replaces what came before it
+	pub fn get_kerning(&self, left: u16, right: u16) -> i16 {
+		let mut kerning: i16 = 0;
+		for subtable in &self.subtables {
+			if !subtable.horizontal || subtable.cross_stream || subtable.variation || subtable.minimum {
+				continue;
+			}
+			let Some(value) = subtable.get_value(left, right) else {
+				continue;
+			};
+			kerning = if subtable.replace {value} else {kerning.saturating_add(value)};
+		}
+		kerning
+	}
+}
+
+impl KernSubtable {
+	pub fn get_value(&self, left: u16, right: u16) -> Option<i16> {
+		match &self.data {
+			KernData::Pairs(pairs) => pairs.get(&(left, right)).copied(),
+			KernData::Classes(classes) => {
+				let left_class = get_class(classes.left_first_glyph, &classes.left_classes, left) as usize;
+				let right_class = get_class(classes.right_first_glyph, &classes.right_classes, right) as usize;
+				// glyphs without a left class point before the array and aren't kerned
+				let index = (left_class + right_class).checked_sub(classes.array_offset)?;
+				classes.values.get(index / 2).copied()
+			}
+		}
+	}
+}
+
+fn get_class(first_glyph: u16, classes: &[u16], glyph: u16) -> u16 {
+	glyph.checked_sub(first_glyph).and_then(|index| classes.get(index as usize)).copied().unwrap_or(0)
+}
+
+fn parse_kern_data(format: u8, subtable: Reader, header_size: usize) -> Result<Option<KernData>, FontError> {
+	let mut reader = subtable;
+	reader.seek(header_size)?;
+	match format {
+		0 => {
+			let n_pairs = reader.read_u16()?;
+			reader.skip(6)?; //skip searchRange, entrySelector, rangeShift
+			let mut pairs: HashMap<(u16, u16), i16> = HashMap::new();
+			for _ in 0..n_pairs {
+				let left = reader.read_u16()?;
+				let right = reader.read_u16()?;
+				pairs.insert((left, right), reader.read_i16()?);
+			}
+			Ok(Some(KernData::Pairs(pairs)))
+		}
+		2 => {
+			reader.skip(2)?; //skip rowWidth
+			let (left_first_glyph, left_classes) = parse_class_table(subtable.at(reader.read_offset16()?)?)?;
+			let (right_first_glyph, right_classes) = parse_class_table(subtable.at(reader.read_offset16()?)?)?;
+			let array_offset = reader.read_offset16()?;
+			let mut array = subtable.at(array_offset)?;
+			let mut values: Vec<i16> = vec![];
+			while array.remaining() >= 2 {
+				values.push(array.read_i16()?);
+			}
+			Ok(Some(KernData::Classes(ClassKerning {
+				left_first_glyph,
+				left_classes,
+				right_first_glyph,
+				right_classes,
+				array_offset,
+				values,
+			})))
+		}
+		_ => Ok(None),
+	}
+}
+
+fn parse_class_table(class_table: Reader) -> Result<(u16, Vec<u16>), FontError> {
+	let mut reader = class_table;
+	let first_glyph = reader.read_u16()?;
+	let n_glyphs = reader.read_u16()?;
+	let mut classes: Vec<u16> = vec![];
+	for _ in 0..n_glyphs {
+		classes.push(reader.read_u16()?);
+	}
+	Ok((first_glyph, classes))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sfnt_builder::words;
+
+	// format 0
+	fn pairs(pairs: &[(u16, u16, i16)]) -> Vec<u8> {
+		let mut body = words(&[pairs.len() as u16, 0, 0, 0]);
+		for &(left, right, value) in pairs {
+			body.extend(words(&[left, right, value as u16]));
+		}
+		body
+	}
+
+	// format 2, left glyphs 10 to 12 and right glyphs 20 to 22 in a two by three array, glyph 12 has no class
+	// offsets are from the start of the subtable so they depend on the header in front of the body
+	fn classes(header_size: usize, values: [i16; 6]) -> Vec<u8> {
+		let left = header_size + 8;
+		let right = left + 10;
+		let array = (right + 10) as u16;
+		let mut body = words(&[6, left as u16, right as u16, array]);
+		body.extend(words(&[10, 3, array, array + 6, 0]));
+		body.extend(words(&[20, 3, 0, 2, 4]));
+		body.extend(words(&values.map(|value| value as u16)));
+		body
+	}
+
+	// subtables are (format, coverage, body)
+	fn windows_kern(subtables: &[(u8, u8, Vec<u8>)]) -> Vec<u8> {
+		let mut kern = words(&[0, subtables.len() as u16]);
+		for (format, coverage, body) in subtables {
+			kern.extend(words(&[0, (6 + body.len()) as u16]));
+			kern.extend([*format, *coverage]);
+			kern.extend(body);
+		}
+		kern
+	}
+
+	// subtables are (coverage, format, body)
+	fn apple_kern(subtables: &[(u8, u8, Vec<u8>)]) -> Vec<u8> {
+		let mut kern = 0x00010000u32.to_be_bytes().to_vec();
+		kern.extend((subtables.len() as u32).to_be_bytes());
+		for (coverage, format, body) in subtables {
+			kern.extend(((8 + body.len()) as u32).to_be_bytes());
+			kern.extend([*coverage, *format, 0, 0]);
+			kern.extend(body);
+		}
+		kern
+	}
+
+	fn flags(subtable: &KernSubtable) -> (bool, bool, bool, bool, bool) {
+		(subtable.horizontal, subtable.minimum, subtable.cross_stream, subtable.replace, subtable.variation)
+	}
+
+	#[test]
+	fn windows_coverage_and_override() {
+		let kern = windows_kern(&[
+			(0, 0x01, pairs(&[(1, 2, -50), (3, 4, 20)])),
+			(0, 0x05, pairs(&[(1, 2, 100)])),
+			(0, 0x03, pairs(&[(3, 4, 100)])),
+			(0, 0x09, pairs(&[(1, 2, -10)])),
+		]);
+		let kern_table = KernTable::parse(Reader::new(&kern)).unwrap();
+		assert_eq!(kern_table.subtables.iter().map(flags).collect::<Vec<_>>(), vec![
+			(true, false, false, false, false),
+			(true, false, true, false, false),
+			(true, true, false, false, false),
+			(true, false, false, true, false),
+		]);
+		// cross stream and minimum subtables are left out, the override replaces the -50
+		assert_eq!(kern_table.get_kerning(1, 2), -10);
+		assert_eq!(kern_table.get_kerning(3, 4), 20);
+		assert_eq!(kern_table.get_kerning(2, 1), 0);
+	}
+
+	#[test]
+	fn apple_coverage_and_formats() {
+		let kern = apple_kern(&[
+			(0x00, 2, classes(8, [-10, -20, -30, -40, -50, -60])),
+			(0x80, 0, pairs(&[(1, 2, -50)])),
+			(0x60, 0, pairs(&[(1, 2, -50)])),
+			// a state machine, skipped
+			(0x00, 1, vec![0; 8]),
+		]);
+		let kern_table = KernTable::parse(Reader::new(&kern)).unwrap();
+		assert_eq!(kern_table.subtables.iter().map(flags).collect::<Vec<_>>(), vec![
+			(true, false, false, false, false),
+			(false, false, false, false, false),
+			(true, false, true, false, true),
+		]);
+		assert_eq!(kern_table.get_kerning(11, 21), -50);
+		assert_eq!(kern_table.get_kerning(1, 2), 0);
+	}
+
+	#[test]
+	fn class_offsets_index_the_array() {
+		// the left class is the row's offset including the array offset, the right class the column's offset within the row
+		let kern = windows_kern(&[(2, 0x01, classes(6, [-10, -20, -30, -40, -50, -60]))]);
+		let subtable = &KernTable::parse(Reader::new(&kern)).unwrap().subtables[0];
+		let values = [(10, 20), (10, 22), (11, 20), (11, 21), (11, 22)].map(|(left, right)| subtable.get_value(left, right));
+		assert_eq!(values, [Some(-10), Some(-30), Some(-40), Some(-50), Some(-60)]);
+		// left glyphs with class 0, in the table or past it, point before the array
+		assert_eq!(subtable.get_value(12, 20), None);
+		assert_eq!(subtable.get_value(13, 21), None);
+	}
+
+	#[test]
+	fn lone_subtable_past_its_length_field() {
+		// 11000 pairs don't fit the 16 bit length, a table with one subtable reads it to the end anyway
+		let many: Vec<(u16, u16, i16)> = (0..11000).map(|i| (i, i + 1, -(i as i16 % 100) - 1)).collect();
+		let kern = windows_kern(&[(0, 0x01, pairs(&many))]);
+		assert!(kern.len() > 0xFFFF);
+		let kern_table = KernTable::parse(Reader::new(&kern)).unwrap();
+		assert_eq!(kern_table.get_kerning(0, 1), -1);
+		assert_eq!(kern_table.get_kerning(10999, 11000), -100);
+	}
+}
@@ -0,0 +1,853 @@
+use crate::font_error::FontError;
+use crate::reader::{Reader, Tag};
+use crate::variations::ItemVariationStore;
+
+// lookup flags
+pub const RIGHT_TO_LEFT: u16 = 0x0001;
+pub const IGNORE_BASE_GLYPHS: u16 = 0x0002;
+pub const IGNORE_LIGATURES: u16 = 0x0004;
+pub const IGNORE_MARKS: u16 = 0x0008;
+pub const USE_MARK_FILTERING_SET: u16 = 0x0010;
+pub const MARK_ATTACHMENT_TYPE: u16 = 0xFF00;
+
+// on unless turned off, substitutions first, then positioning
+pub const DEFAULT_FEATURES: [Tag; 14] = [
+	*b"ccmp", *b"locl", *b"rlig", *b"liga", *b"clig", *b"calt", *b"rclt",
+	*b"kern", *b"mark", *b"mkmk", *b"curs", *b"dist", *b"abvm", *b"blwm",
+];
+
+// a feature and its value, 0 is off and anything higher picks an alternate for features that have them
+pub type FeatureSetting = (Tag, u32);
+
+// GDEF glyph classes
+pub const BASE_GLYPH: u16 = 1;
+pub const LIGATURE_GLYPH: u16 = 2;
+pub const MARK_GLYPH: u16 = 3;
+pub const COMPONENT_GLYPH: u16 = 4;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RangeRecord {
+	pub start_glyph: u16,
+	pub end_glyph: u16,
+	// start coverage index for coverage tables, the class for class definitions
+	pub value: u16,
+}
+
+#[derive(Debug, Clone)]
+pub enum Coverage {
+	Glyphs(Vec<u16>),
+	Ranges(Vec<RangeRecord>),
+}
+
+impl Coverage {
+	pub fn parse(coverage: Reader) -> Result<Coverage, FontError> {
+		let mut reader = coverage;
+		let format = reader.read_u16()?;
+		let count = reader.read_u16()?;
+		match format {
+			1 => Ok(Coverage::Glyphs(read_u16_array(&mut reader, count)?)),
+			2 => Ok(Coverage::Ranges(read_range_records(&mut reader, count)?)),
+			_ => Err(FontError::UnsupportedFormat { table: "Coverage", format: format as u32 }),
+		}
+	}
+
+	// both formats are sorted by glyph id
+	pub fn get_index(&self, glyph: u16) -> Option<u16> {
+		match self {
+			Coverage::Glyphs(glyphs) => glyphs.binary_search(&glyph).ok().map(|index| index as u16),
+			Coverage::Ranges(ranges) => find_range(ranges, glyph).map(|range| range.value.wrapping_add(glyph - range.start_glyph)),
+		}
+	}
+
+	pub fn contains(&self, glyph: u16) -> bool {
+		self.get_index(glyph).is_some()
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum ClassDef {
+	Glyphs { start_glyph: u16, classes: Vec<u16> },
+	Ranges(Vec<RangeRecord>),
+}
+
+impl Default for ClassDef {
+	fn default() -> Self {
+		ClassDef::Ranges(vec![])
+	}
+}
+
+impl ClassDef {
+	pub fn parse(class_def: Reader) -> Result<ClassDef, FontError> {
+		let mut reader = class_def;
+		let format = reader.read_u16()?;
+		match format {
+			1 => {
+				let start_glyph = reader.read_u16()?;
+				let count = reader.read_u16()?;
+				Ok(ClassDef::Glyphs { start_glyph, classes: read_u16_array(&mut reader, count)? })
+			}
+			2 => {
+				let count = reader.read_u16()?;
+				Ok(ClassDef::Ranges(read_range_records(&mut reader, count)?))
+			}
+			_ => Err(FontError::UnsupportedFormat { table: "ClassDef", format: format as u32 }),
+		}
+	}
+
+	// a null offset is a class definition that puts everything in class 0
+	pub fn parse_optional(parent: Reader, offset: usize) -> Result<ClassDef, FontError> {
+		if offset == 0 {
+			return Ok(ClassDef::default());
+		}
+		ClassDef::parse(parent.slice_from(offset)?)
+	}
+
+	pub fn get_class(&self, glyph: u16) -> u16 {
+		match self {
+			ClassDef::Glyphs { start_glyph, classes } => glyph.checked_sub(*start_glyph)
+				.and_then(|index| classes.get(index as usize))
+				.copied()
+				.unwrap_or(0),
+			ClassDef::Ranges(ranges) => find_range(ranges, glyph).map_or(0, |range| range.value),
+		}
+	}
+}
+
+#[derive(Debug, Clone)]
+pub enum Device {
+	// pixel size specific adjustments for hinted rendering
+	Hinting { start_size: u16, end_size: u16, deltas: Vec<i8> },
+	VariationIndex { outer_index: u16, inner_index: u16 },
+}
+
+impl Device {
+	pub fn parse(device: Reader) -> Result<Option<Device>, FontError> {
+		let mut reader = device;
+		let first = reader.read_u16()?;
+		let second = reader.read_u16()?;
+		let format = reader.read_u16()?;
+		let bits_per_delta = match format {
+			1 => 2,
+			2 => 4,
+			3 => 8,
+			0x8000 => return Ok(Some(Device::VariationIndex { outer_index: first, inner_index: second })),
+			_ => return Ok(None),
+		};
+		// deltas are packed into 16 bit words, most significant bits first
+		let count = second.saturating_sub(first) as usize + 1;
+		let mut deltas: Vec<i8> = vec![];
+		let mut word = 0u16;
+		for i in 0..count {
+			let position = i * bits_per_delta % 16;
+			if position == 0 {
+				word = reader.read_u16()?;
+			}
+			let bits = (word << position) as i16 >> (16 - bits_per_delta);
+			deltas.push(bits as i8);
+		}
+		Ok(Some(Device::Hinting { start_size: first, end_size: second, deltas }))
+	}
+
+	pub fn parse_optional(parent: Reader, offset: usize) -> Result<Option<Device>, FontError> {
+		if offset == 0 {
+			return Ok(None);
+		}
+		Device::parse(parent.slice_from(offset)?)
+	}
+
+	// hinting deltas only mean something at a pixel size, variation deltas at a position in the design space
+	pub fn get_delta(&self, ppem: Option<u16>, coordinates: &[f32], variation_store: Option<&ItemVariationStore>) -> f32 {
+		match self {
+			Device::Hinting { start_size, end_size, deltas } => match ppem {
+				Some(ppem) if ppem >= *start_size && ppem <= *end_size => deltas[(ppem - start_size) as usize] as f32,
+				_ => 0.0,
+			},
+			Device::VariationIndex { outer_index, inner_index } => variation_store
+				.map_or(0.0, |store| store.get_delta(*outer_index, *inner_index, coordinates)),
+		}
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct LangSys {
+	pub required_feature_index: Option<u16>,
+	pub feature_indices: Vec<u16>,
+}
+
+impl LangSys {
+	fn parse(lang_sys: Reader) -> Result<LangSys, FontError> {
+		let mut reader = lang_sys;
+		reader.skip(2)?; //skip lookupOrderOffset
+		let required_feature_index = reader.read_u16()?;
+		let count = reader.read_u16()?;
+		Ok(LangSys {
+			required_feature_index: if required_feature_index == 0xFFFF {None} else {Some(required_feature_index)},
+			feature_indices: read_u16_array(&mut reader, count)?,
+		})
+	}
+}
+
+#[derive(Debug, Clone)]
+pub struct Script {
+	pub tag: Tag,
+	pub default_lang_sys: Option<LangSys>,
+	pub lang_sys_records: Vec<(Tag, LangSys)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Feature {
+	pub tag: Tag,
+	pub lookup_indices: Vec<u16>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Lookup<T> {
+	pub lookup_type: u16,
+	pub lookup_flag: u16,
+	pub mark_filtering_set: Option<u16>,
+	pub subtables: Vec<T>,
+}
+
+// the part GSUB and GPOS share, generic over their subtables
+#[derive(Debug, Clone)]
+pub struct LayoutTable<T> {
+	pub scripts: Vec<Script>,
+	pub features: Vec<Feature>,
+	pub lookups: Vec<Lookup<T>>,
+}
+
+impl<T> Default for LayoutTable<T> {
+	fn default() -> Self {
+		Self {
+			scripts: vec![],
+			features: vec![],
+			lookups: vec![],
+		}
+	}
+}
+
+// subtable parsers return None for formats they don't know, those subtables are left out
+pub type SubtableParser<T> = fn(u16, Reader) -> Result<Option<T>, FontError>;
+
+impl<T> LayoutTable<T> {
+	pub fn parse(table: Reader, extension_type: u16, parse_subtable: SubtableParser<T>) -> Result<LayoutTable<T>, FontError> {
+		let mut reader = table;
+		let major_version = reader.read_u16()?;
+		if major_version != 1 {
+			return Err(FontError::UnsupportedFormat { table: "layout table", format: major_version as u32 });
+		}
+		reader.skip(2)?; //skip minorVersion
+		let script_list_offset = reader.read_offset16()?;
+		let feature_list_offset = reader.read_offset16()?;
+		let lookup_list_offset = reader.read_offset16()?;
+
+		let mut scripts: Vec<Script> = vec![];
+		if script_list_offset != 0 {
+			let script_list = table.slice_from(script_list_offset)?;
+			let mut records = script_list;
+			let count = records.read_u16()?;
+			for _ in 0..count {
+				let tag = records.read_tag()?;
+				let script = script_list.slice_from(records.read_offset16()?)?;
+				let mut script_reader = script;
+				let default_lang_sys_offset = script_reader.read_offset16()?;
+				let default_lang_sys = if default_lang_sys_offset == 0 {None} else {Some(LangSys::parse(script.slice_from(default_lang_sys_offset)?)?)};
+				let lang_sys_count = script_reader.read_u16()?;
+				let mut lang_sys_records: Vec<(Tag, LangSys)> = vec![];
+				for _ in 0..lang_sys_count {
+					let lang_sys_tag = script_reader.read_tag()?;
+					lang_sys_records.push((lang_sys_tag, LangSys::parse(script.slice_from(script_reader.read_offset16()?)?)?));
+				}
+				scripts.push(Script { tag, default_lang_sys, lang_sys_records });
+			}
+		}
+
+		let mut features: Vec<Feature> = vec![];
+		if feature_list_offset != 0 {
+			let feature_list = table.slice_from(feature_list_offset)?;
+			let mut records = feature_list;
+			let count = records.read_u16()?;
+			for _ in 0..count {
+				let tag = records.read_tag()?;
+				let mut feature = feature_list.slice_from(records.read_offset16()?)?;
+				feature.skip(2)?; //skip featureParamsOffset
+				let lookup_index_count = feature.read_u16()?;
+				features.push(Feature { tag, lookup_indices: read_u16_array(&mut feature, lookup_index_count)? });
+			}
+		}
+
+		let mut lookups: Vec<Lookup<T>> = vec![];
+		if lookup_list_offset != 0 {
+			let lookup_list = table.slice_from(lookup_list_offset)?;
+			let mut records = lookup_list;
+			let count = records.read_u16()?;
+			for _ in 0..count {
+				let lookup = lookup_list.slice_from(records.read_offset16()?)?;
+				let mut lookup_reader = lookup;
+				let mut lookup_type = lookup_reader.read_u16()?;
+				let lookup_flag = lookup_reader.read_u16()?;
+				let subtable_count = lookup_reader.read_u16()?;
+				let is_extension = lookup_type == extension_type;
+				let mut subtables: Vec<T> = vec![];
+				for _ in 0..subtable_count {
+					let mut subtable = lookup.slice_from(lookup_reader.read_offset16()?)?;
+					// extension subtables only exist to reach past 16 bit offsets, every one in a lookup has the same real type
+					if is_extension {
+						let extension = subtable;
+						subtable.skip(2)?; //skip format
+						lookup_type = subtable.read_u16()?;
+						subtable = extension.slice_from(subtable.read_offset32()?)?;
+					}
+					if let Some(parsed) = parse_subtable(lookup_type, subtable)? {
+						subtables.push(parsed);
+					}
+				}
+				let mark_filtering_set = if lookup_flag & USE_MARK_FILTERING_SET != 0 {Some(lookup_reader.read_u16()?)} else {None};
+				lookups.push(Lookup { lookup_type, lookup_flag, mark_filtering_set, subtables });
+			}
+		}
+
+		Ok(LayoutTable {
+			scripts,
+			features,
+			lookups,
+		})
+	}
+
+	// falls back to the default script, then latin, when the font has nothing for the one asked for
+	pub fn get_lang_sys(&self, script: Tag, language: Option<Tag>) -> Option<&LangSys> {
+		let script = [script, *b"DFLT", *b"dflt", *b"latn"].iter()
+			.find_map(|&tag| self.scripts.iter().find(|candidate| candidate.tag == tag))?;
+		language
+			.and_then(|language| script.lang_sys_records.iter().find(|(tag, _)| *tag == language))
+			.map(|(_, lang_sys)| lang_sys)
+			.or(script.default_lang_sys.as_ref())
+	}
+
+	// lookups run in lookup list order, not in the order the features were asked for,
+	// each with the value of the feature that turned it on
+	pub fn get_lookup_indices(&self, script: Tag, language: Option<Tag>, features: &[FeatureSetting]) -> Vec<(u16, u32)> {
+		let Some(lang_sys) = self.get_lang_sys(script, language) else {
+			return vec![];
+		};
+		let mut lookup_indices: Vec<(u16, u32)> = vec![];
+		let feature_indices = lang_sys.required_feature_index.iter().chain(lang_sys.feature_indices.iter());
+		for &feature_index in feature_indices {
+			let Some(feature) = self.features.get(feature_index as usize) else {
+				continue;
+			};
+			let value = if Some(feature_index) == lang_sys.required_feature_index {
+				1
+			} else {
+				features.iter().find(|(tag, _)| *tag == feature.tag).map_or(0, |&(_, value)| value)
+			};
+			if value == 0 {
+				continue;
+			}
+			for &lookup_index in &feature.lookup_indices {
+				if (lookup_index as usize) < self.lookups.len() {
+					lookup_indices.push((lookup_index, value));
+				}
+			}
+		}
+		lookup_indices.sort_unstable_by_key(|&(lookup_index, _)| lookup_index);
+		lookup_indices.dedup_by_key(|&mut (lookup_index, _)| lookup_index);
+		lookup_indices
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Gdef {
+	pub glyph_class_def: ClassDef,
+	pub mark_attach_class_def: ClassDef,
+	pub mark_glyph_sets: Vec<Coverage>,
+	pub variation_store: Option<ItemVariationStore>,
+}
+
+impl Gdef {
+	pub fn parse(gdef: Reader) -> Result<Gdef, FontError> {
+		let mut reader = gdef;
+		reader.skip(2)?; //skip majorVersion
+		let minor_version = reader.read_u16()?;
+		let glyph_class_def = ClassDef::parse_optional(gdef, reader.read_offset16()?)?;
+		reader.skip(4)?; //skip attachListOffset, ligCaretListOffset
+		let mark_attach_class_def = ClassDef::parse_optional(gdef, reader.read_offset16()?)?;
+
+		let mut mark_glyph_sets: Vec<Coverage> = vec![];
+		if minor_version >= 2 {
+			let mark_glyph_sets_offset = reader.read_offset16()?;
+			if mark_glyph_sets_offset != 0 {
+				let mark_glyph_sets_table = gdef.slice_from(mark_glyph_sets_offset)?;
+				let mut sets = mark_glyph_sets_table;
+				sets.skip(2)?; //skip format
+				let count = sets.read_u16()?;
+				for _ in 0..count {
+					mark_glyph_sets.push(Coverage::parse(mark_glyph_sets_table.slice_from(sets.read_offset32()?)?)?);
+				}
+			}
+		}
+
+		let mut variation_store = None;
+		if minor_version >= 3 {
+			let variation_store_offset = reader.read_offset32()?;
+			if variation_store_offset != 0 {
+				variation_store = Some(ItemVariationStore::parse(gdef.slice_from(variation_store_offset)?)?);
+			}
+		}
+
+		Ok(Gdef {
+			glyph_class_def,
+			mark_attach_class_def,
+			mark_glyph_sets,
+			variation_store,
+		})
+	}
+
+	pub fn get_glyph_class(&self, glyph: u16) -> u16 {
+		self.glyph_class_def.get_class(glyph)
+	}
+
+	pub fn is_mark(&self, glyph: u16) -> bool {
+		self.get_glyph_class(glyph) == MARK_GLYPH
+	}
+
+	// whether a lookup with these flags passes over the glyph as if it weren't there
+	pub fn should_skip(&self, lookup_flag: u16, mark_filtering_set: Option<u16>, glyph: u16) -> bool {
+		match self.get_glyph_class(glyph) {
+			BASE_GLYPH => lookup_flag & IGNORE_BASE_GLYPHS != 0,
+			LIGATURE_GLYPH => lookup_flag & IGNORE_LIGATURES != 0,
+			MARK_GLYPH => {
+				if lookup_flag & IGNORE_MARKS != 0 {
+					return true;
+				}
+				if let Some(set) = mark_filtering_set {
+					return !self.mark_glyph_sets.get(set as usize).is_some_and(|coverage| coverage.contains(glyph));
+				}
+				let mark_attachment_type = (lookup_flag & MARK_ATTACHMENT_TYPE) >> 8;
+				mark_attachment_type != 0 && self.mark_attach_class_def.get_class(glyph) != mark_attachment_type
+			}
+			_ => false,
+		}
+	}
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct SequenceLookupRecord {
+	pub sequence_index: u16,
+	pub lookup_index: u16,
+}
+
+// glyph ids or classes depending on the format, the input leaves out the first glyph which the coverage already matched
+#[derive(Debug, Clone)]
+pub struct SequenceRule {
+	pub input: Vec<u16>,
+	pub lookups: Vec<SequenceLookupRecord>,
+}
+
+// backtrack runs from the glyph before the input backwards
+#[derive(Debug, Clone)]
+pub struct ChainedSequenceRule {
+	pub backtrack: Vec<u16>,
+	pub input: Vec<u16>,
+	pub lookahead: Vec<u16>,
+	pub lookups: Vec<SequenceLookupRecord>,
+}
+
+#[derive(Debug, Clone)]
+pub enum SequenceContext {
+	Glyphs { coverage: Coverage, rule_sets: Vec<Vec<SequenceRule>> },
+	Classes { coverage: Coverage, class_def: ClassDef, rule_sets: Vec<Vec<SequenceRule>> },
+	Coverages { coverages: Vec<Coverage>, lookups: Vec<SequenceLookupRecord> },
+}
+
+#[derive(Debug, Clone)]
+pub enum ChainedSequenceContext {
+	Glyphs { coverage: Coverage, rule_sets: Vec<Vec<ChainedSequenceRule>> },
+	Classes {
+		coverage: Coverage,
+		backtrack_class_def: ClassDef,
+		input_class_def: ClassDef,
+		lookahead_class_def: ClassDef,
+		rule_sets: Vec<Vec<ChainedSequenceRule>>,
+	},
+	Coverages { backtrack: Vec<Coverage>, input: Vec<Coverage>, lookahead: Vec<Coverage>, lookups: Vec<SequenceLookupRecord> },
+}
+
+// positions of the matched input glyphs, and the lookups to run on them
+pub type ContextMatch<'a> = (Vec<usize>, &'a [SequenceLookupRecord]);
+
+impl SequenceContext {
+	pub fn parse(context: Reader) -> Result<Option<SequenceContext>, FontError> {
+		let mut reader = context;
+		let format = reader.read_u16()?;
+		match format {
+			1 | 2 => {
+				let coverage = Coverage::parse(context.slice_from(reader.read_offset16()?)?)?;
+				let class_def = if format == 2 {Some(ClassDef::parse_optional(context, reader.read_offset16()?)?)} else {None};
+				let rule_set_count = reader.read_u16()?;
+				let mut rule_sets: Vec<Vec<SequenceRule>> = vec![];
+				for _ in 0..rule_set_count {
+					let rule_set_offset = reader.read_offset16()?;
+					let mut rules: Vec<SequenceRule> = vec![];
+					if rule_set_offset != 0 {
+						let rule_set = context.slice_from(rule_set_offset)?;
+						let mut rule_offsets = rule_set;
+						let rule_count = rule_offsets.read_u16()?;
+						for _ in 0..rule_count {
+							let mut rule = rule_set.slice_from(rule_offsets.read_offset16()?)?;
+							let glyph_count = rule.read_u16()?;
+							let lookup_count = rule.read_u16()?;
+							let input = read_u16_array(&mut rule, glyph_count.saturating_sub(1))?;
+							let lookups = read_sequence_lookup_records(&mut rule, lookup_count)?;
+							rules.push(SequenceRule { input, lookups });
+						}
+					}
+					rule_sets.push(rules);
+				}
+				Ok(Some(match class_def {
+					Some(class_def) => SequenceContext::Classes { coverage, class_def, rule_sets },
+					None => SequenceContext::Glyphs { coverage, rule_sets },
+				}))
+			}
+			3 => {
+				let glyph_count = reader.read_u16()?;
+				let lookup_count = reader.read_u16()?;
+				let coverages = read_coverages(context, &mut reader, glyph_count)?;
+				let lookups = read_sequence_lookup_records(&mut reader, lookup_count)?;
+				Ok(Some(SequenceContext::Coverages { coverages, lookups }))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	pub fn matches(&self, glyphs: &[u16], index: usize, skip: &dyn Fn(u16) -> bool) -> Option<ContextMatch<'_>> {
+		match self {
+			SequenceContext::Glyphs { coverage, rule_sets } => {
+				let rules = rule_sets.get(coverage.get_index(glyphs[index])? as usize)?;
+				rules.iter().find_map(|rule| {
+					let positions = match_input(glyphs, index, rule.input.len(), skip, |i, glyph| glyph == rule.input[i])?;
+					Some((positions, rule.lookups.as_slice()))
+				})
+			}
+			SequenceContext::Classes { coverage, class_def, rule_sets } => {
+				coverage.get_index(glyphs[index])?;
+				let rules = rule_sets.get(class_def.get_class(glyphs[index]) as usize)?;
+				rules.iter().find_map(|rule| {
+					let positions = match_input(glyphs, index, rule.input.len(), skip, |i, glyph| class_def.get_class(glyph) == rule.input[i])?;
+					Some((positions, rule.lookups.as_slice()))
+				})
+			}
+			SequenceContext::Coverages { coverages, lookups } => {
+				let (first, rest) = coverages.split_first()?;
+				first.get_index(glyphs[index])?;
+				let positions = match_input(glyphs, index, rest.len(), skip, |i, glyph| rest[i].contains(glyph))?;
+				Some((positions, lookups.as_slice()))
+			}
+		}
+	}
+}
+
+impl ChainedSequenceContext {
+	pub fn parse(context: Reader) -> Result<Option<ChainedSequenceContext>, FontError> {
+		let mut reader = context;
+		let format = reader.read_u16()?;
+		match format {
+			1 | 2 => {
+				let coverage = Coverage::parse(context.slice_from(reader.read_offset16()?)?)?;
+				let class_defs = if format == 2 {
+					Some((
+						ClassDef::parse_optional(context, reader.read_offset16()?)?,
+						ClassDef::parse_optional(context, reader.read_offset16()?)?,
+						ClassDef::parse_optional(context, reader.read_offset16()?)?,
+					))
+				} else {
+					None
+				};
+				let rule_set_count = reader.read_u16()?;
+				let mut rule_sets: Vec<Vec<ChainedSequenceRule>> = vec![];
+				for _ in 0..rule_set_count {
+					let rule_set_offset = reader.read_offset16()?;
+					let mut rules: Vec<ChainedSequenceRule> = vec![];
+					if rule_set_offset != 0 {
+						let rule_set = context.slice_from(rule_set_offset)?;
+						let mut rule_offsets = rule_set;
+						let rule_count = rule_offsets.read_u16()?;
+						for _ in 0..rule_count {
+							let mut rule = rule_set.slice_from(rule_offsets.read_offset16()?)?;
+							let backtrack_count = rule.read_u16()?;
+							let backtrack = read_u16_array(&mut rule, backtrack_count)?;
+							let input_count = rule.read_u16()?;
+							let input = read_u16_array(&mut rule, input_count.saturating_sub(1))?;
+							let lookahead_count = rule.read_u16()?;
+							let lookahead = read_u16_array(&mut rule, lookahead_count)?;
+							let lookup_count = rule.read_u16()?;
+							let lookups = read_sequence_lookup_records(&mut rule, lookup_count)?;
+							rules.push(ChainedSequenceRule { backtrack, input, lookahead, lookups });
+						}
+					}
+					rule_sets.push(rules);
+				}
+				Ok(Some(match class_defs {
+					Some((backtrack_class_def, input_class_def, lookahead_class_def)) => ChainedSequenceContext::Classes {
+						coverage,
+						backtrack_class_def,
+						input_class_def,
+						lookahead_class_def,
+						rule_sets,
+					},
+					None => ChainedSequenceContext::Glyphs { coverage, rule_sets },
+				}))
+			}
+			3 => {
+				let backtrack_count = reader.read_u16()?;
+				let backtrack = read_coverages(context, &mut reader, backtrack_count)?;
+				let input_count = reader.read_u16()?;
+				let input = read_coverages(context, &mut reader, input_count)?;
+				let lookahead_count = reader.read_u16()?;
+				let lookahead = read_coverages(context, &mut reader, lookahead_count)?;
+				let lookup_count = reader.read_u16()?;
+				let lookups = read_sequence_lookup_records(&mut reader, lookup_count)?;
+				Ok(Some(ChainedSequenceContext::Coverages { backtrack, input, lookahead, lookups }))
+			}
+			_ => Ok(None),
+		}
+	}
+
+	pub fn matches(&self, glyphs: &[u16], index: usize, skip: &dyn Fn(u16) -> bool) -> Option<ContextMatch<'_>> {
+		match self {
+			ChainedSequenceContext::Glyphs { coverage, rule_sets } => {
+				let rules = rule_sets.get(coverage.get_index(glyphs[index])? as usize)?;
+				rules.iter().find_map(|rule| {
+					match_chained(glyphs, index, skip, rule,
+						|i, glyph| glyph == rule.backtrack[i],
+						|i, glyph| glyph == rule.input[i],
+						|i, glyph| glyph == rule.lookahead[i])
+				})
+			}
+			ChainedSequenceContext::Classes { coverage, backtrack_class_def, input_class_def, lookahead_class_def, rule_sets } => {
+				coverage.get_index(glyphs[index])?;
+				let rules = rule_sets.get(input_class_def.get_class(glyphs[index]) as usize)?;
+				rules.iter().find_map(|rule| {
+					match_chained(glyphs, index, skip, rule,
+						|i, glyph| backtrack_class_def.get_class(glyph) == rule.backtrack[i],
+						|i, glyph| input_class_def.get_class(glyph) == rule.input[i],
+						|i, glyph| lookahead_class_def.get_class(glyph) == rule.lookahead[i])
+				})
+			}
+			ChainedSequenceContext::Coverages { backtrack, input, lookahead, lookups } => {
+				let (first, rest) = input.split_first()?;
+				first.get_index(glyphs[index])?;
+				let positions = match_input(glyphs, index, rest.len(), skip, |i, glyph| rest[i].contains(glyph))?;
+				let last = *positions.last()?;
+				match_forward(glyphs, last, lookahead.len(), skip, |i, glyph| lookahead[i].contains(glyph))?;
+				match_backward(glyphs, index, backtrack.len(), skip, |i, glyph| backtrack[i].contains(glyph))?;
+				Some((positions, lookups.as_slice()))
+			}
+		}
+	}
+}
+
+fn match_chained<'a>(
+	glyphs: &[u16],
+	index: usize,
+	skip: &dyn Fn(u16) -> bool,
+	rule: &'a ChainedSequenceRule,
+	backtrack: impl Fn(usize, u16) -> bool,
+	input: impl Fn(usize, u16) -> bool,
+	lookahead: impl Fn(usize, u16) -> bool,
+) -> Option<ContextMatch<'a>> {
+	let positions = match_input(glyphs, index, rule.input.len(), skip, input)?;
+	let last = *positions.last()?;
+	match_forward(glyphs, last, rule.lookahead.len(), skip, lookahead)?;
+	match_backward(glyphs, index, rule.backtrack.len(), skip, backtrack)?;
+	Some((positions, rule.lookups.as_slice()))
+}
+
+// the first input glyph plus the rest of the input
+fn match_input(glyphs: &[u16], index: usize, count: usize, skip: &dyn Fn(u16) -> bool, matches: impl Fn(usize, u16) -> bool) -> Option<Vec<usize>> {
+	let mut positions = vec![index];
+	positions.extend(match_forward(glyphs, index, count, skip, matches)?);
+	Some(positions)
+}
+
+pub fn match_forward(glyphs: &[u16], index: usize, count: usize, skip: &dyn Fn(u16) -> bool, matches: impl Fn(usize, u16) -> bool) -> Option<Vec<usize>> {
+	let mut positions: Vec<usize> = vec![];
+	let mut position = index;
+	while positions.len() < count {
+		position += 1;
+		let &glyph = glyphs.get(position)?;
+		if skip(glyph) {
+			continue;
+		}
+		if !matches(positions.len(), glyph) {
+			return None;
+		}
+		positions.push(position);
+	}
+	Some(positions)
+}
+
+pub fn match_backward(glyphs: &[u16], index: usize, count: usize, skip: &dyn Fn(u16) -> bool, matches: impl Fn(usize, u16) -> bool) -> Option<Vec<usize>> {
+	let mut positions: Vec<usize> = vec![];
+	let mut position = index;
+	while positions.len() < count {
+		position = position.checked_sub(1)?;
+		let glyph = glyphs[position];
+		if skip(glyph) {
+			continue;
+		}
+		if !matches(positions.len(), glyph) {
+			return None;
+		}
+		positions.push(position);
+	}
+	Some(positions)
+}
+
+fn find_range(ranges: &[RangeRecord], glyph: u16) -> Option<&RangeRecord> {
+	let index = ranges.partition_point(|range| range.end_glyph < glyph);
+	ranges.get(index).filter(|range| range.start_glyph <= glyph)
+}
+
+pub fn read_u16_array(reader: &mut Reader, count: u16) -> Result<Vec<u16>, FontError> {
+	let mut values: Vec<u16> = vec![];
+	for _ in 0..count {
+		values.push(reader.read_u16()?);
+	}
+	Ok(values)
+}
+
+fn read_range_records(reader: &mut Reader, count: u16) -> Result<Vec<RangeRecord>, FontError> {
+	let mut ranges: Vec<RangeRecord> = vec![];
+	for _ in 0..count {
+		ranges.push(RangeRecord {
+			start_glyph: 	reader.read_u16()?,
+			end_glyph: 		reader.read_u16()?,
+			value: 			reader.read_u16()?,
+		});
+	}
+	Ok(ranges)
+}
+
+pub fn read_coverages(parent: Reader, reader: &mut Reader, count: u16) -> Result<Vec<Coverage>, FontError> {
+	let mut coverages: Vec<Coverage> = vec![];
+	for _ in 0..count {
+		coverages.push(Coverage::parse(parent.slice_from(reader.read_offset16()?)?)?);
+	}
+	Ok(coverages)
+}
+
+fn read_sequence_lookup_records(reader: &mut Reader, count: u16) -> Result<Vec<SequenceLookupRecord>, FontError> {
+	let mut records: Vec<SequenceLookupRecord> = vec![];
+	for _ in 0..count {
+		records.push(SequenceLookupRecord {
+			sequence_index: reader.read_u16()?,
+			lookup_index: 	reader.read_u16()?,
+		});
+	}
+	Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sfnt_builder::*;
+
+	fn lang_sys(required_feature_index: u16, feature_indices: &[u16]) -> Vec<u8> {
+		let mut lang_sys = words(&[0, required_feature_index, feature_indices.len() as u16]);
+		lang_sys.extend(words(feature_indices));
+		lang_sys
+	}
+
+	// latn has kern by default, Turkish also requires locl
+	// kern runs lookups 2 and 0, locl lookup 1, lookup 2 has a mark filtering set
+	fn layout() -> LayoutTable<()> {
+		let script = offset_table(&[
+			Field::Offset(lang_sys(0xFFFF, &[0])),
+			Field::U16(1),
+			Field::Tag(*b"TRK "),
+			Field::Offset(lang_sys(1, &[0])),
+		]);
+		let table = offset_table(&[
+			Field::U16(1),
+			Field::U16(0),
+			Field::Offset(offset_table(&[Field::U16(1), Field::Tag(*b"latn"), Field::Offset(script)])),
+			Field::Offset(offset_table(&[
+				Field::U16(2),
+				Field::Tag(*b"kern"),
+				Field::Offset(words(&[0, 2, 2, 0])),
+				Field::Tag(*b"locl"),
+				Field::Offset(words(&[0, 1, 1])),
+			])),
+			Field::Offset(offset_table(&[
+				Field::U16(3),
+				Field::Offset(words(&[1, 0, 0])),
+				Field::Offset(words(&[4, 0, 0])),
+				Field::Offset(words(&[2, USE_MARK_FILTERING_SET, 0, 3])),
+			])),
+		]);
+		LayoutTable::parse(Reader::new(&table), 0, |_, _| Ok(None)).unwrap()
+	}
+
+	#[test]
+	fn script_feature_and_lookup_lists() {
+		let layout = layout();
+		assert_eq!(layout.scripts.len(), 1);
+		assert_eq!(layout.scripts[0].lang_sys_records[0].1.required_feature_index, Some(1));
+		assert_eq!(layout.features.iter().map(|feature| feature.tag).collect::<Vec<Tag>>(), vec![*b"kern", *b"locl"]);
+		assert_eq!(layout.lookups.iter().map(|lookup| (lookup.lookup_type, lookup.mark_filtering_set)).collect::<Vec<(u16, Option<u16>)>>(), vec![
+			(1, None), (4, None), (2, Some(3)),
+		]);
+	}
+
+	#[test]
+	fn lookups_in_lookup_list_order() {
+		let layout = layout();
+		let kern = [(*b"kern", 1)];
+		assert_eq!(layout.get_lookup_indices(*b"latn", None, &kern), vec![(0, 1), (2, 1)]);
+		assert_eq!(layout.get_lookup_indices(*b"latn", None, &[]), vec![]);
+		// the required feature is on whatever was asked for
+		assert_eq!(layout.get_lookup_indices(*b"latn", Some(*b"TRK "), &[]), vec![(1, 1)]);
+		assert_eq!(layout.get_lookup_indices(*b"latn", Some(*b"TRK "), &[(*b"kern", 2)]), vec![(0, 2), (1, 1), (2, 2)]);
+		// unknown languages use the default language system, unknown scripts fall back to latin
+		assert_eq!(layout.get_lookup_indices(*b"latn", Some(*b"DEU "), &kern), vec![(0, 1), (2, 1)]);
+		assert_eq!(layout.get_lookup_indices(*b"cyrl", None, &kern), vec![(0, 1), (2, 1)]);
+	}
+
+	#[test]
+	fn coverage_formats() {
+		let glyphs = Coverage::parse(Reader::new(&coverage(&[3, 5, 9]))).unwrap();
+		assert_eq!([3, 4, 5, 9, 10].map(|glyph| glyphs.get_index(glyph)), [Some(0), None, Some(1), Some(2), None]);
+		// the second range starts at coverage index 3
+		let ranges = Coverage::parse(Reader::new(&words(&[2, 2, 10, 12, 0, 20, 20, 3]))).unwrap();
+		assert_eq!([9, 10, 12, 13, 20].map(|glyph| ranges.get_index(glyph)), [None, Some(0), Some(2), None, Some(3)]);
+		assert!(Coverage::parse(Reader::new(&words(&[3, 0]))).is_err());
+	}
+
+	#[test]
+	fn class_def_formats() {
+		let glyphs = ClassDef::parse(Reader::new(&words(&[1, 5, 3, 1, 2, 0]))).unwrap();
+		assert_eq!([4, 5, 6, 7, 8].map(|glyph| glyphs.get_class(glyph)), [0, 1, 2, 0, 0]);
+		let ranges = ClassDef::parse(Reader::new(&class_def(&[(1, 3, 4), (10, 10, 2)]))).unwrap();
+		assert_eq!([0, 2, 5, 10].map(|glyph| ranges.get_class(glyph)), [0, 4, 0, 2]);
+		// a null offset puts everything in class 0
+		let empty = ClassDef::parse_optional(Reader::new(&[0, 0]), 0).unwrap();
+		assert_eq!(empty.get_class(2), 0);
+	}
+
+	#[test]
+	fn device_deltas_at_each_width() {
+		let delta = |device: &[u16], ppem: u16| Device::parse(Reader::new(&words(device))).unwrap().unwrap().get_delta(Some(ppem), &[], None);
+		// 2 bit deltas 1, -1, 0, -2 for sizes 8 to 11
+		let two_bit = [8, 11, 1, 0x7200];
+		assert_eq!([7, 8, 9, 10, 11, 12].map(|ppem| delta(&two_bit, ppem)), [0.0, 1.0, -1.0, 0.0, -2.0, 0.0]);
+		// 8 bit deltas -3, 4 for sizes 12 and 13
+		let eight_bit = [12, 13, 3, 0xFD04];
+		assert_eq!([12, 13].map(|ppem| delta(&eight_bit, ppem)), [-3.0, 4.0]);
+
+		let variation = Device::parse(Reader::new(&words(&[1, 2, 0x8000]))).unwrap();
+		assert!(matches!(variation, Some(Device::VariationIndex { outer_index: 1, inner_index: 2 })));
+		assert_eq!(variation.unwrap().get_delta(Some(12), &[], None), 0.0);
+		assert!(Device::parse(Reader::new(&words(&[1, 2, 4]))).unwrap().is_none());
+	}
+}
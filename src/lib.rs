@@ -0,0 +1,22 @@
+pub mod cff;
+pub mod cli;
+pub mod font_error;
+pub mod font_loader;
+pub mod gpos;
+pub mod gsub;
+pub mod hinting;
+pub mod image_writer;
+pub mod kern_table;
+pub mod layout;
+pub mod name_table;
+pub mod os2_table;
+pub mod post_table;
+pub mod rasterizer;
+pub mod reader;
+pub mod shaping;
+pub mod text_renderer;
+pub mod utils;
+pub mod variations;
+pub mod woff;
+#[cfg(test)]
+mod sfnt_builder;
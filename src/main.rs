@@ -1,19 +1,47 @@
 mod window_manager;
 mod render_manager;
-mod font_loader;
-mod utils;
 
 use std::{env::Args, ops::Deref};
 
-use font_loader::{FontFile, TableDirectory};
+use font_render::cli;
+use font_render::font_loader::{FontFile, TableDirectory};
 use winit::event_loop::{ControlFlow, EventLoop};
 use window_manager::App;
 
 fn main() {
     let args: Args = std::env::args();
-    let mut args = args.skip(1);
-    let file_path = args.next().unwrap();
-    let font_file = FontFile::new(&file_path);
+    let mut args = args.skip(1).peekable();
+    // "render" draws to an image file instead of opening a window
+    if args.peek().map(String::as_str) == Some("render") {
+        args.next();
+        let result = cli::RenderOptions::parse(args).and_then(|options| cli::run_render(&options));
+        if let Err(e) = result {
+            eprintln!("Error: {}", e);
+            eprintln!("{}", cli::RENDER_USAGE);
+            std::process::exit(1);
+        }
+        return;
+    }
+    let Some(file_path) = args.next() else {
+        eprintln!("usage: font_render <font> [face index] [features] [variation]");
+        eprintln!("{}", cli::RENDER_USAGE);
+        return;
+    };
+    // collections (.ttc/.otc) take an optional face index, the first face is used otherwise
+    let face_index = args.next().and_then(|arg| arg.parse().ok()).unwrap_or(0);
+    // then a comma separated feature list like "ss01,zero,-liga,cv01=2"
+    let features = args.next().unwrap_or_default();
+    // and a variation like "wght=650,wdth=80" for variable fonts
+    let variation = args.next().unwrap_or_default();
+    let mut font_file = match FontFile::from_collection(&file_path, face_index) {
+        Ok(font_file) => font_file,
+        Err(e) => {
+            eprintln!("Error: failed to load {}: {}", file_path, e);
+            return;
+        }
+    };
+    cli::apply_features(&mut font_file, &features);
+    cli::apply_variation(&mut font_file, &variation);
     
     let event_loop = EventLoop::new().unwrap();
     event_loop.set_control_flow(ControlFlow::Wait);
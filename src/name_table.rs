@@ -0,0 +1,238 @@
+use crate::font_error::FontError;
+use crate::reader::Reader;
+use crate::utils::mac_roman_to_char;
+
+pub const COPYRIGHT: u16 = 0;
+pub const FAMILY: u16 = 1;
+pub const SUBFAMILY: u16 = 2;
+pub const UNIQUE_ID: u16 = 3;
+pub const FULL_NAME: u16 = 4;
+pub const VERSION: u16 = 5;
+pub const POSTSCRIPT_NAME: u16 = 6;
+pub const LICENSE: u16 = 13;
+pub const LICENSE_URL: u16 = 14;
+pub const TYPOGRAPHIC_FAMILY: u16 = 16;
+pub const TYPOGRAPHIC_SUBFAMILY: u16 = 17;
+
+// the more common windows LCIDs and mac language codes, anything else keeps only its numeric id
+const WINDOWS_LANGUAGES: [(u16, &str); 24] = [
+	(0x0409, "en-US"), (0x0809, "en-GB"), (0x0407, "de-DE"), (0x040C, "fr-FR"), (0x0410, "it-IT"), (0x0C0A, "es-ES"),
+	(0x0416, "pt-BR"), (0x0816, "pt-PT"), (0x0413, "nl-NL"), (0x041D, "sv-SE"), (0x0406, "da-DK"), (0x0414, "nb-NO"),
+	(0x040B, "fi-FI"), (0x0415, "pl-PL"), (0x0405, "cs-CZ"), (0x040E, "hu-HU"), (0x0419, "ru-RU"), (0x041F, "tr-TR"),
+	(0x0408, "el-GR"), (0x0411, "ja-JP"), (0x0412, "ko-KR"), (0x0804, "zh-CN"), (0x0404, "zh-TW"), (0x0C04, "zh-HK"),
+];
+const MAC_LANGUAGES: [(u16, &str); 16] = [
+	(0, "en"), (1, "fr"), (2, "de"), (3, "it"), (4, "nl"), (5, "sv"), (6, "es"), (7, "da"),
+	(8, "pt"), (9, "no"), (11, "ja"), (14, "el"), (17, "tr"), (19, "zh-Hant"), (23, "ko"), (33, "zh-Hans"),
+];
+
+#[derive(Debug, Clone)]
+pub struct NameRecord {
+	pub platform_id: u16,
+	pub encoding_id: u16,
+	pub language_id: u16,
+	pub name_id: u16,
+	pub value: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NameTable {
+	pub records: Vec<NameRecord>,
+	pub language_tags: Vec<String>,
+}
+
+impl NameTable {
+	pub fn parse(name: Reader) -> Result<NameTable, FontError> {
+		let mut reader = name;
+		let version = reader.read_u16()?;
+		let count = reader.read_u16()?;
+		let storage = name.slice_from(reader.read_offset16()?)?;
+
+		let mut records: Vec<NameRecord> = vec![];
+		for _ in 0..count {
+			let platform_id = reader.read_u16()?;
+			let encoding_id = reader.read_u16()?;
+			let language_id = reader.read_u16()?;
+			let name_id = reader.read_u16()?;
+			let length = reader.read_u16()? as usize;
+			let offset = reader.read_offset16()?;
+			// a record running past the storage is dropped, the others are still good
+			let Ok(string) = storage.sub_reader(offset, length) else {
+				continue;
+			};
+			// records in legacy cjk encodings are skipped, there are no tables here to decode them
+			if let Some(value) = decode_name(platform_id, encoding_id, string.data()) {
+				records.push(NameRecord { platform_id, encoding_id, language_id, name_id, value });
+			}
+		}
+
+		// version 1 names languages with BCP 47 tags, referenced by language ids from 0x8000 up
+		let mut language_tags: Vec<String> = vec![];
+		if version >= 1 {
+			let lang_tag_count = reader.read_u16()?;
+			for _ in 0..lang_tag_count {
+				let length = reader.read_u16()? as usize;
+				let offset = reader.read_offset16()?;
+				// a tag past the storage stays empty so the ones after it keep their ids
+				language_tags.push(storage.sub_reader(offset, length).map_or(String::new(), |tag| decode_utf16(tag.data())));
+			}
+		}
+
+		Ok(NameTable {
+			records,
+			language_tags,
+		})
+	}
+
+	pub fn get_language(&self, record: &NameRecord) -> Option<String> {
+		if record.language_id >= 0x8000 {
+			return self.language_tags.get((record.language_id - 0x8000) as usize).filter(|tag| !tag.is_empty()).cloned();
+		}
+		let languages: &[(u16, &str)] = match record.platform_id {
+			1 => &MAC_LANGUAGES,
+			3 => &WINDOWS_LANGUAGES,
+			_ => &[],
+		};
+		languages.iter().find(|&&(id, _)| id == record.language_id).map(|&(_, tag)| tag.to_string())
+	}
+
+	// english first, windows before unicode before mac, then whatever is left
+	pub fn get_name(&self, name_id: u16) -> Option<&str> {
+		let preferences = [(3, Some(0x0409)), (0, None), (1, Some(0)), (3, None), (1, None)];
+		preferences.iter()
+			.find_map(|&(platform_id, language_id)| self.records.iter().find(|record| {
+				record.name_id == name_id
+					&& record.platform_id == platform_id
+					&& language_id.is_none_or(|language_id| record.language_id == language_id)
+			}))
+			.or_else(|| self.records.iter().find(|record| record.name_id == name_id))
+			.map(|record| record.value.as_str())
+	}
+
+	// language is a BCP 47 tag, "de" also matches "de-DE"
+	pub fn get_name_for_language(&self, name_id: u16, language: &str) -> Option<&str> {
+		self.records.iter()
+			.filter(|record| record.name_id == name_id)
+			.find(|record| self.get_language(record).is_some_and(|tag| {
+				tag.eq_ignore_ascii_case(language) || tag.split('-').next().is_some_and(|primary| primary.eq_ignore_ascii_case(language))
+			}))
+			.map(|record| record.value.as_str())
+	}
+}
+
+fn decode_name(platform_id: u16, encoding_id: u16, bytes: &[u8]) -> Option<String> {
+	match (platform_id, encoding_id) {
+		(0, _) | (3, 0) | (3, 1) | (3, 10) | (2, 1) => Some(decode_utf16(bytes)),
+		(1, 0) => Some(bytes.iter().map(|&byte| mac_roman_to_char(byte)).collect()),
+		(2, 0) | (2, 2) => Some(bytes.iter().map(|&byte| byte as char).collect()),
+		_ => None,
+	}
+}
+
+fn decode_utf16(bytes: &[u8]) -> String {
+	let units: Vec<u16> = bytes.chunks_exact(2).map(|pair| u16::from_be_bytes([pair[0], pair[1]])).collect();
+	String::from_utf16_lossy(&units)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sfnt_builder::words;
+
+	type Record = (u16, u16, u16, u16, Vec<u8>);
+
+	// records are (platform id, encoding id, language id, name id, string), the strings stored in record order
+	fn name_table(version: u16, records: &[Record], language_tags: &[&str]) -> Vec<u8> {
+		let header_len = 6 + 12 * records.len() + if version >= 1 {2 + 4 * language_tags.len()} else {0};
+		let mut table = words(&[version, records.len() as u16, header_len as u16]);
+		let mut storage: Vec<u8> = vec![];
+		for (platform_id, encoding_id, language_id, name_id, string) in records {
+			table.extend(words(&[*platform_id, *encoding_id, *language_id, *name_id, string.len() as u16, storage.len() as u16]));
+			storage.extend(string);
+		}
+		if version >= 1 {
+			table.extend(words(&[language_tags.len() as u16]));
+			for tag in language_tags {
+				let tag = utf16(tag);
+				table.extend(words(&[tag.len() as u16, storage.len() as u16]));
+				storage.extend(tag);
+			}
+		}
+		table.extend(storage);
+		table
+	}
+
+	fn utf16(string: &str) -> Vec<u8> {
+		string.encode_utf16().flat_map(|unit| unit.to_be_bytes()).collect()
+	}
+
+	fn parse(table: &[u8]) -> NameTable {
+		NameTable::parse(Reader::new(table)).unwrap()
+	}
+
+	#[test]
+	fn utf16_and_mac_roman_strings() {
+		let table = name_table(0, &[
+			(3, 1, 0x0409, FAMILY, utf16("Caf\u{E9} \u{1D509}")),
+			// 0x8E is e acute in mac roman
+			(1, 0, 0, FAMILY, vec![0x43, 0x61, 0x66, 0x8E]),
+			(0, 3, 0, FAMILY, utf16("Unicode")),
+			// shift jis, nothing here decodes it
+			(3, 2, 0x0411, FAMILY, vec![0x82, 0xA0]),
+		], &[]);
+		let values: Vec<String> = parse(&table).records.into_iter().map(|record| record.value).collect();
+		assert_eq!(values, vec!["Caf\u{E9} \u{1D509}", "Caf\u{E9}", "Unicode"]);
+	}
+
+	#[test]
+	fn get_name_prefers_english_windows_names() {
+		let mac = (1, 0, 0, FAMILY, b"Mac".to_vec());
+		let mac_french = (1, 0, 1, FAMILY, b"Mac French".to_vec());
+		let german = (3, 1, 0x0407, FAMILY, utf16("German"));
+		let unicode = (0, 3, 0, FAMILY, utf16("Unicode"));
+		let english = (3, 1, 0x0409, FAMILY, utf16("English"));
+		let other = (2, 0, 0, FAMILY, b"ISO".to_vec());
+		let name = |records: &[Record]| parse(&name_table(0, records, &[])).get_name(FAMILY).map(str::to_string);
+
+		assert_eq!(name(&[mac.clone(), german.clone(), unicode.clone(), english]), Some("English".to_string()));
+		assert_eq!(name(&[mac.clone(), german.clone(), unicode]), Some("Unicode".to_string()));
+		// an english mac name beats other windows languages, a mac name in another language doesn't
+		assert_eq!(name(&[german.clone(), mac]), Some("Mac".to_string()));
+		assert_eq!(name(&[mac_french.clone(), german]), Some("German".to_string()));
+		assert_eq!(name(&[other, mac_french]), Some("Mac French".to_string()));
+		assert_eq!(name(&[(3, 1, 0x0409, SUBFAMILY, utf16("Bold"))]), None);
+	}
+
+	#[test]
+	fn version_1_language_tags() {
+		let table = name_table(1, &[
+			(3, 1, 0x8001, FAMILY, utf16("Schweiz")),
+			(3, 1, 0x0409, FAMILY, utf16("English")),
+			(3, 1, 0x8005, FAMILY, utf16("Unknown")),
+		], &["en", "de-CH"]);
+		let name_table = parse(&table);
+		assert_eq!(name_table.language_tags, vec!["en", "de-CH"]);
+		let languages: Vec<Option<String>> = name_table.records.iter().map(|record| name_table.get_language(record)).collect();
+		assert_eq!(languages, vec![Some("de-CH".to_string()), Some("en-US".to_string()), None]);
+		assert_eq!(name_table.get_name_for_language(FAMILY, "de"), Some("Schweiz"));
+		assert_eq!(name_table.get_name_for_language(FAMILY, "en-us"), Some("English"));
+		assert_eq!(name_table.get_name_for_language(FAMILY, "fr"), None);
+	}
+
+	#[test]
+	fn strings_past_the_storage_are_skipped() {
+		let mut table = name_table(1, &[
+			(3, 1, 0x0409, FAMILY, utf16("Family")),
+			(3, 1, 0x0409, SUBFAMILY, utf16("Regular")),
+			(3, 1, 0x8001, FULL_NAME, utf16("Voll")),
+		], &["xx", "de"]);
+		// the second record's length and the first tag's offset reach past the end
+		table[6 + 12 + 8..][..2].copy_from_slice(&0x1000u16.to_be_bytes());
+		table[6 + 36 + 2 + 2..][..2].copy_from_slice(&0x1000u16.to_be_bytes());
+		let name_table = parse(&table);
+		assert_eq!(name_table.get_name(FAMILY), Some("Family"));
+		assert_eq!(name_table.get_name(SUBFAMILY), None);
+		assert_eq!(name_table.language_tags, vec!["", "de"]);
+		assert_eq!(name_table.get_name_for_language(FULL_NAME, "de"), Some("Voll"));
+	}
+}
@@ -0,0 +1,242 @@
+use crate::font_error::FontError;
+use crate::reader::{Reader, Tag};
+
+// fsSelection bits
+pub const ITALIC: u16 = 1 << 0;
+pub const UNDERSCORE: u16 = 1 << 1;
+pub const NEGATIVE: u16 = 1 << 2;
+pub const OUTLINED: u16 = 1 << 3;
+pub const STRIKEOUT: u16 = 1 << 4;
+pub const BOLD: u16 = 1 << 5;
+pub const REGULAR: u16 = 1 << 6;
+pub const USE_TYPO_METRICS: u16 = 1 << 7;
+pub const WWS: u16 = 1 << 8;
+pub const OBLIQUE: u16 = 1 << 9;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingPermission {
+	Installable,
+	Restricted,
+	PreviewAndPrint,
+	Editable,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TypoMetrics {
+	pub ascender: i16,
+	pub descender: i16,
+	pub line_gap: i16,
+	pub win_ascent: u16,
+	pub win_descent: u16,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Os2Table {
+	pub version: u16,
+	pub x_avg_char_width: i16,
+	pub weight_class: u16,
+	pub width_class: u16,
+	pub fs_type: u16,
+	pub subscript_size: (i16, i16),
+	pub subscript_offset: (i16, i16),
+	pub superscript_size: (i16, i16),
+	pub superscript_offset: (i16, i16),
+	pub strikeout_size: i16,
+	pub strikeout_position: i16,
+	pub family_class: i16,
+	pub panose: [u8; 10],
+	pub unicode_range: [u32; 4],
+	pub vendor_id: Tag,
+	pub fs_selection: u16,
+	pub first_char_index: u16,
+	pub last_char_index: u16,
+	// apple's original version 0 table ends before the typographic metrics
+	pub typo_metrics: Option<TypoMetrics>,
+	// version 1
+	pub code_page_range: Option<[u32; 2]>,
+	// version 2
+	pub x_height: Option<i16>,
+	pub cap_height: Option<i16>,
+	pub default_char: Option<u16>,
+	pub break_char: Option<u16>,
+	pub max_context: Option<u16>,
+	// version 5, in points
+	pub optical_point_size_range: Option<(f32, f32)>,
+}
+
+impl Os2Table {
+	pub fn parse(os2: Reader) -> Result<Os2Table, FontError> {
+		let mut reader = os2;
+		let mut table = Os2Table {
+			version: 				reader.read_u16()?,
+			x_avg_char_width: 		reader.read_i16()?,
+			weight_class: 			reader.read_u16()?,
+			width_class: 			reader.read_u16()?,
+			fs_type: 				reader.read_u16()?,
+			subscript_size: 		(reader.read_i16()?, reader.read_i16()?),
+			subscript_offset: 		(reader.read_i16()?, reader.read_i16()?),
+			superscript_size: 		(reader.read_i16()?, reader.read_i16()?),
+			superscript_offset: 	(reader.read_i16()?, reader.read_i16()?),
+			strikeout_size: 		reader.read_i16()?,
+			strikeout_position: 	reader.read_i16()?,
+			family_class: 			reader.read_i16()?,
+			..Default::default()
+		};
+		table.panose.copy_from_slice(reader.read_bytes(10)?);
+		for range in table.unicode_range.iter_mut() {
+			*range = reader.read_u32()?;
+		}
+		table.vendor_id = reader.read_tag()?;
+		table.fs_selection = reader.read_u16()?;
+		table.first_char_index = reader.read_u16()?;
+		table.last_char_index = reader.read_u16()?;
+
+		if reader.remaining() == 0 {
+			return Ok(table);
+		}
+		table.typo_metrics = Some(TypoMetrics {
+			ascender: 		reader.read_i16()?,
+			descender: 		reader.read_i16()?,
+			line_gap: 		reader.read_i16()?,
+			win_ascent: 	reader.read_u16()?,
+			win_descent: 	reader.read_u16()?,
+		});
+
+		if table.version >= 1 {
+			table.code_page_range = Some([reader.read_u32()?, reader.read_u32()?]);
+		}
+		if table.version >= 2 {
+			table.x_height = Some(reader.read_i16()?);
+			table.cap_height = Some(reader.read_i16()?);
+			table.default_char = Some(reader.read_u16()?);
+			table.break_char = Some(reader.read_u16()?);
+			table.max_context = Some(reader.read_u16()?);
+		}
+		if table.version >= 5 {
+			// stored in TWIPs, twentieths of a point
+			let lower = reader.read_u16()? as f32 / 20.0;
+			let upper = reader.read_u16()? as f32 / 20.0;
+			table.optical_point_size_range = Some((lower, upper));
+		}
+		Ok(table)
+	}
+
+	pub fn has_selection(&self, flag: u16) -> bool {
+		self.fs_selection & flag != 0
+	}
+
+	pub fn use_typo_metrics(&self) -> bool {
+		self.has_selection(USE_TYPO_METRICS)
+	}
+
+	// the lowest permission bit set wins, fonts are not supposed to set more than one
+	pub fn embedding_permission(&self) -> EmbeddingPermission {
+		if self.fs_type & 0x0002 != 0 {
+			EmbeddingPermission::Restricted
+		} else if self.fs_type & 0x0004 != 0 {
+			EmbeddingPermission::PreviewAndPrint
+		} else if self.fs_type & 0x0008 != 0 {
+			EmbeddingPermission::Editable
+		} else {
+			EmbeddingPermission::Installable
+		}
+	}
+
+	pub fn allows_subsetting(&self) -> bool {
+		self.fs_type & 0x0100 == 0
+	}
+
+	pub fn bitmap_embedding_only(&self) -> bool {
+		self.fs_type & 0x0200 != 0
+	}
+
+	// bit numbers as listed in the OS/2 ulUnicodeRange table, 0 is Basic Latin
+	pub fn supports_unicode_range(&self, bit: u32) -> bool {
+		bit < 128 && self.unicode_range[(bit / 32) as usize] & (1 << (bit % 32)) != 0
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sfnt_builder::words;
+
+	// a version 5 layout with every field set, cut off where the version being tested ends
+	fn os2(version: u16, length: usize) -> Vec<u8> {
+		let mut table = words(&[version, 500, 700, 5, 0]);
+		table.extend(words(&[0, 0, 0, 0, 0, 0, 0, 0, 50, 300, 0]));
+		table.extend([2, 11, 5, 2, 2, 2, 2, 2, 2, 4]);
+		// basic latin and latin extended-a, bits 0 and 33
+		table.extend(words(&[0, 1, 0, 2, 0, 0, 0, 0]));
+		table.extend(b"TEST");
+		table.extend(words(&[USE_TYPO_METRICS | REGULAR, 0x20, 0xFFFF]));
+		table.extend(words(&[800, -200i16 as u16, 100, 1000, 300]));
+		table.extend(words(&[0, 1, 0x8000, 0]));
+		table.extend(words(&[500, 700, 0, 0x20, 3]));
+		// 9 to 72 points
+		table.extend(words(&[180, 1440]));
+		table.truncate(length);
+		table
+	}
+
+	fn parse(version: u16, length: usize) -> Os2Table {
+		Os2Table::parse(Reader::new(&os2(version, length))).unwrap()
+	}
+
+	#[test]
+	fn apple_version_0_ends_before_the_typographic_metrics() {
+		let short = parse(0, 68);
+		assert_eq!((short.weight_class, short.strikeout_size, short.strikeout_position), (700, 50, 300));
+		assert_eq!(&short.vendor_id, b"TEST");
+		assert_eq!((short.first_char_index, short.last_char_index), (0x20, 0xFFFF));
+		assert!(short.typo_metrics.is_none());
+		assert!(short.use_typo_metrics());
+		assert_eq!([0, 1, 33, 127, 128].map(|bit| short.supports_unicode_range(bit)), [true, false, true, false, false]);
+
+		let long = parse(0, 78);
+		let typo_metrics = long.typo_metrics.unwrap();
+		assert_eq!((typo_metrics.ascender, typo_metrics.descender, typo_metrics.line_gap), (800, -200, 100));
+		assert_eq!((typo_metrics.win_ascent, typo_metrics.win_descent), (1000, 300));
+		assert!(long.code_page_range.is_none());
+	}
+
+	#[test]
+	fn version_1_code_pages() {
+		let table = parse(1, 86);
+		assert_eq!(table.code_page_range, Some([1, 0x80000000]));
+		assert!(table.x_height.is_none());
+		assert!(Os2Table::parse(Reader::new(&os2(1, 82))).is_err());
+	}
+
+	#[test]
+	fn version_2_heights() {
+		for version in [2, 3, 4] {
+			let table = parse(version, 96);
+			assert_eq!((table.x_height, table.cap_height), (Some(500), Some(700)));
+			assert_eq!((table.default_char, table.break_char, table.max_context), (Some(0), Some(0x20), Some(3)));
+			assert!(table.optical_point_size_range.is_none());
+		}
+	}
+
+	#[test]
+	fn version_5_optical_size_in_twips() {
+		assert_eq!(parse(5, 100).optical_point_size_range, Some((9.0, 72.0)));
+	}
+
+	#[test]
+	fn embedding_permission_precedence() {
+		let permission = |fs_type: u16| Os2Table { fs_type, ..Default::default() }.embedding_permission();
+		assert_eq!(permission(0), EmbeddingPermission::Installable);
+		assert_eq!(permission(0x0002), EmbeddingPermission::Restricted);
+		assert_eq!(permission(0x0004), EmbeddingPermission::PreviewAndPrint);
+		assert_eq!(permission(0x0008), EmbeddingPermission::Editable);
+		// with several bits set the most restrictive one wins
+		assert_eq!(permission(0x000E), EmbeddingPermission::Restricted);
+		assert_eq!(permission(0x000C), EmbeddingPermission::PreviewAndPrint);
+		// the subsetting and bitmap bits leave the permission alone
+		let table = Os2Table { fs_type: 0x0300, ..Default::default() };
+		assert_eq!(table.embedding_permission(), EmbeddingPermission::Installable);
+		assert!(!table.allows_subsetting());
+		assert!(table.bitmap_embedding_only());
+	}
+}
@@ -0,0 +1,155 @@
+use crate::font_error::FontError;
+use crate::reader::Reader;
+
+// the standard macintosh glyph order, formats 1 and 2 index into this before their own strings
+pub const MAC_GLYPH_NAMES: [&str; 258] = [
+	".notdef", ".null", "nonmarkingreturn", "space", "exclam", "quotedbl", "numbersign", "dollar", "percent",
+	"ampersand", "quotesingle", "parenleft", "parenright", "asterisk", "plus", "comma", "hyphen", "period",
+	"slash", "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "colon",
+	"semicolon", "less", "equal", "greater", "question", "at", "A", "B", "C", "D", "E", "F", "G", "H", "I", "J",
+	"K", "L", "M", "N", "O", "P", "Q", "R", "S", "T", "U", "V", "W", "X", "Y", "Z", "bracketleft", "backslash",
+	"bracketright", "asciicircum", "underscore", "grave", "a", "b", "c", "d", "e", "f", "g", "h", "i", "j", "k",
+	"l", "m", "n", "o", "p", "q", "r", "s", "t", "u", "v", "w", "x", "y", "z", "braceleft", "bar", "braceright",
+	"asciitilde", "Adieresis", "Aring", "Ccedilla", "Eacute", "Ntilde", "Odieresis", "Udieresis", "aacute",
+	"agrave", "acircumflex", "adieresis", "atilde", "aring", "ccedilla", "eacute", "egrave", "ecircumflex",
+	"edieresis", "iacute", "igrave", "icircumflex", "idieresis", "ntilde", "oacute", "ograve", "ocircumflex",
+	"odieresis", "otilde", "uacute", "ugrave", "ucircumflex", "udieresis", "dagger", "degree", "cent",
+	"sterling", "section", "bullet", "paragraph", "germandbls", "registered", "copyright", "trademark", "acute",
+	"dieresis", "notequal", "AE", "Oslash", "infinity", "plusminus", "lessequal", "greaterequal", "yen", "mu",
+	"partialdiff", "summation", "product", "pi", "integral", "ordfeminine", "ordmasculine", "Omega", "ae",
+	"oslash", "questiondown", "exclamdown", "logicalnot", "radical", "florin", "approxequal", "Delta",
+	"guillemotleft", "guillemotright", "ellipsis", "nonbreakingspace", "Agrave", "Atilde", "Otilde", "OE", "oe",
+	"endash", "emdash", "quotedblleft", "quotedblright", "quoteleft", "quoteright", "divide", "lozenge",
+	"ydieresis", "Ydieresis", "fraction", "currency", "guilsinglleft", "guilsinglright", "fi", "fl",
+	"daggerdbl", "periodcentered", "quotesinglbase", "quotedblbase", "perthousand", "Acircumflex",
+	"Ecircumflex", "Aacute", "Edieresis", "Egrave", "Iacute", "Icircumflex", "Idieresis", "Igrave", "Oacute",
+	"Ocircumflex", "apple", "Ograve", "Uacute", "Ucircumflex", "Ugrave", "dotlessi", "circumflex", "tilde",
+	"macron", "breve", "dotaccent", "ring", "cedilla", "hungarumlaut", "ogonek", "caron", "Lslash", "lslash",
+	"Scaron", "scaron", "Zcaron", "zcaron", "brokenbar", "Eth", "eth", "Yacute", "yacute", "Thorn", "thorn",
+	"minus", "multiply", "onesuperior", "twosuperior", "threesuperior", "onehalf", "onequarter",
+	"threequarters", "franc", "Gbreve", "gbreve", "Idotaccent", "Scedilla", "scedilla", "Cacute", "cacute",
+	"Ccaron", "ccaron", "dcroat",
+];
+
+#[derive(Debug, Clone, Default)]
+pub struct PostTable {
+	pub version: u32,
+	pub italic_angle: f32,
+	pub underline_position: i16,
+	pub underline_thickness: i16,
+	pub is_fixed_pitch: bool,
+	// indexed by glyph id, empty when the font doesn't name the glyph
+	pub glyph_names: Vec<String>,
+}
+
+impl PostTable {
+	pub fn parse(post: Reader) -> Result<PostTable, FontError> {
+		let mut reader = post;
+		let version = reader.read_u32()?;
+		let italic_angle = reader.read_fixed()?;
+		let underline_position = reader.read_i16()?;
+		let underline_thickness = reader.read_i16()?;
+		let is_fixed_pitch = reader.read_u32()? != 0;
+		reader.skip(16)?; //skip minMemType42, maxMemType42, minMemType1, maxMemType1
+
+		let glyph_names = match version {
+			0x00010000 => MAC_GLYPH_NAMES.iter().map(|name| name.to_string()).collect(),
+			0x00020000 => {
+				let num_glyphs = reader.read_u16()? as usize;
+				let mut name_indexes: Vec<u16> = vec![];
+				for _ in 0..num_glyphs {
+					name_indexes.push(reader.read_u16()?);
+				}
+				// pascal strings, one after another
+				let mut strings: Vec<String> = vec![];
+				while reader.remaining() > 0 {
+					let length = reader.read_u8()? as usize;
+					strings.push(String::from_utf8_lossy(reader.read_bytes(length)?).into_owned());
+				}
+				// an index past the strings leaves that glyph unnamed
+				name_indexes.iter().map(|&index| {
+					let index = index as usize;
+					if index < MAC_GLYPH_NAMES.len() {
+						MAC_GLYPH_NAMES[index].to_string()
+					} else {
+						strings.get(index - MAC_GLYPH_NAMES.len()).cloned().unwrap_or_default()
+					}
+				}).collect()
+			}
+			// deprecated, every glyph is a signed offset into the standard order
+			0x00025000 => {
+				let num_glyphs = reader.read_u16()?;
+				let mut names: Vec<String> = vec![];
+				for glyph_index in 0..num_glyphs {
+					let index = glyph_index as i32 + reader.read_i8()? as i32;
+					let name = usize::try_from(index).ok().and_then(|index| MAC_GLYPH_NAMES.get(index)).map_or("", |name| name);
+					names.push(name.to_string());
+				}
+				names
+			}
+			// format 3 has no names, CFF fonts keep theirs in the charset
+			// 4.0 maps glyphs to character codes in old apple fonts, it and unknown versions still have the metrics above
+			_ => vec![],
+		};
+
+		Ok(PostTable {
+			version,
+			italic_angle,
+			underline_position,
+			underline_thickness,
+			is_fixed_pitch,
+			glyph_names,
+		})
+	}
+
+	pub fn glyph_name(&self, glyph_index: u16) -> Option<&str> {
+		self.glyph_names.get(glyph_index as usize).map(|name| name.as_str()).filter(|name| !name.is_empty())
+	}
+
+	pub fn glyph_by_name(&self, name: &str) -> Option<u16> {
+		self.glyph_names.iter().position(|glyph_name| glyph_name == name).map(|index| index as u16)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// italic angle -12, underline at -100 and 50 units thick, then the fields after the header
+	fn post(version: u32, names: &[u8]) -> Vec<u8> {
+		let mut post: Vec<u8> = vec![];
+		post.extend(version.to_be_bytes());
+		post.extend((-12i32 << 16).to_be_bytes());
+		post.extend((-100i16).to_be_bytes());
+		post.extend(50i16.to_be_bytes());
+		post.extend([0; 20]);
+		post.extend(names);
+		post
+	}
+
+	#[test]
+	fn version_4_keeps_the_metrics() {
+		let post = post(0x00040000, &[0, 65, 0, 66]);
+		let table = PostTable::parse(Reader::new(&post)).unwrap();
+		assert_eq!((table.italic_angle, table.underline_position, table.underline_thickness), (-12.0, -100, 50));
+		assert_eq!(table.glyph_name(0), None);
+	}
+
+	#[test]
+	fn name_index_past_the_strings_leaves_the_glyph_unnamed() {
+		// three glyphs, .notdef, the first custom string and one past the last string
+		let mut names: Vec<u8> = vec![];
+		names.extend(3u16.to_be_bytes());
+		for index in [0u16, 258, 259] {
+			names.extend(index.to_be_bytes());
+		}
+		names.push(5);
+		names.extend(b"alpha");
+		let post = post(0x00020000, &names);
+		let table = PostTable::parse(Reader::new(&post)).unwrap();
+		assert_eq!(table.glyph_name(0), Some(".notdef"));
+		assert_eq!(table.glyph_name(1), Some("alpha"));
+		assert_eq!(table.glyph_name(2), None);
+		assert_eq!(table.glyph_by_name("alpha"), Some(1));
+	}
+}
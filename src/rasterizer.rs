@@ -0,0 +1,271 @@
+use crate::font_loader::{GlyphDescription, Point};
+use crate::utils::*;
+
+// horizontal coverage is exact, vertically every pixel row is sampled on this many scanlines
+const SCANLINES_PER_PIXEL: usize = 16;
+// flattened curves stay within this many pixels of the real curve
+const FLATNESS: f32 = 0.05;
+const MAX_CURVE_STEPS: usize = 64;
+// 8192 by 8192, outlines scaled past this give an empty bitmap instead of allocating gigabytes
+pub const MAX_BITMAP_PIXELS: usize = 1 << 26;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FillRule {
+	// fragmentshader.frag counts crossings and fills odd counts
+	#[default]
+	EvenOdd,
+	NonZero,
+}
+
+// 8 bit alpha, rows top to bottom
+// left and top place the top left corner in pixels from the glyph origin, y grows upwards like font units
+#[derive(Debug, Clone, Default)]
+pub struct Bitmap {
+	pub width: u32,
+	pub height: u32,
+	pub left: i32,
+	pub top: i32,
+	pub pixels: Vec<u8>,
+}
+
+impl Bitmap {
+	pub fn get(&self, x: u32, y: u32) -> u8 {
+		self.pixels[(y * self.width + x) as usize]
+	}
+}
+
+// a line in pixels, y grows upwards
+#[derive(Debug, Clone, Copy)]
+struct Edge {
+	x0: f32,
+	y0: f32,
+	x1: f32,
+	y1: f32,
+}
+
+// renders the outline at ppem pixels per em
+// hinted glyphs should be rasterized at the ppem they were hinted for
+pub fn rasterize_glyph(glyph: &GlyphDescription, units_per_em: u16, ppem: f32, fill_rule: FillRule) -> Bitmap {
+	let scale = ppem / units_per_em.max(1) as f32;
+	let edges = flatten_glyph(glyph, scale);
+	if edges.is_empty() {
+		return Bitmap::default();
+	}
+
+	let (mut xmin, mut ymin, mut xmax, mut ymax) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+	for edge in &edges {
+		xmin = xmin.min(edge.x0.min(edge.x1));
+		ymin = ymin.min(edge.y0.min(edge.y1));
+		xmax = xmax.max(edge.x0.max(edge.x1));
+		ymax = ymax.max(edge.y0.max(edge.y1));
+	}
+	let left = xmin.floor() as i32;
+	let top = ymax.ceil() as i32;
+	let width = (i64::from(xmax.ceil() as i32) - i64::from(left)).max(1);
+	let height = (i64::from(top) - i64::from(ymin.floor() as i32)).max(1);
+	let Some(size) = bitmap_size(width, height) else {
+		return Bitmap::default();
+	};
+	let (width, height) = (width as u32, height as u32);
+
+	let mut pixels: Vec<u8> = vec![0; size];
+	let mut coverage: Vec<f32> = vec![0.0; width as usize];
+	let mut crossings: Vec<(f32, i32)> = vec![];
+	let weight = 1.0 / SCANLINES_PER_PIXEL as f32;
+	for row in 0..height {
+		coverage.fill(0.0);
+		for scanline in 0..SCANLINES_PER_PIXEL {
+			let y = top as f32 - row as f32 - (scanline as f32 + 0.5) * weight;
+			scanline_crossings(&edges, y, left as f32, &mut crossings);
+			let mut winding = 0;
+			for pair in crossings.windows(2) {
+				winding += pair[0].1;
+				let inside = match fill_rule {
+					FillRule::EvenOdd => winding % 2 != 0,
+					FillRule::NonZero => winding != 0,
+				};
+				if inside {
+					add_span(&mut coverage, pair[0].0, pair[1].0, weight);
+				}
+			}
+		}
+		let start = (row * width) as usize;
+		for (pixel, &covered) in pixels[start..start + width as usize].iter_mut().zip(&coverage) {
+			*pixel = (covered.clamp(0.0, 1.0) * 255.0).round() as u8;
+		}
+	}
+
+	Bitmap {
+		width,
+		height,
+		left,
+		top,
+		pixels,
+	}
+}
+
+// width * height, or None when it doesn't fit in MAX_BITMAP_PIXELS
+pub fn bitmap_size(width: i64, height: i64) -> Option<usize> {
+	let size = usize::try_from(width).ok()?.checked_mul(usize::try_from(height).ok()?)?;
+	(size <= MAX_BITMAP_PIXELS).then_some(size)
+}
+
+// x positions relative to left where the edges cross y, sorted, with +1 for upward edges and -1 for downward ones
+// the lower end point counts and the upper one doesn't, the same half open rule the shader uses for lines
+fn scanline_crossings(edges: &[Edge], y: f32, left: f32, crossings: &mut Vec<(f32, i32)>) {
+	crossings.clear();
+	for edge in edges {
+		let direction = if edge.y0 <= y && edge.y1 > y {
+			1
+		} else if edge.y1 <= y && edge.y0 > y {
+			-1
+		} else {
+			continue;
+		};
+		let x = edge.x0 + (y - edge.y0) * (edge.x1 - edge.x0) / (edge.y1 - edge.y0);
+		crossings.push((x - left, direction));
+	}
+	crossings.sort_by(|a, b| a.0.total_cmp(&b.0));
+}
+
+// adds how much of each pixel lies between start and end
+fn add_span(coverage: &mut [f32], start: f32, end: f32, weight: f32) {
+	let width = coverage.len() as f32;
+	let (start, end) = (start.clamp(0.0, width), end.clamp(0.0, width));
+	if end <= start {
+		return;
+	}
+	let (first, last) = (start.floor() as usize, end.floor() as usize);
+	if first == last {
+		coverage[first] += (end - start) * weight;
+		return;
+	}
+	coverage[first] += (first as f32 + 1.0 - start) * weight;
+	for pixel in &mut coverage[first + 1..last] {
+		*pixel += weight;
+	}
+	if last < coverage.len() {
+		coverage[last] += (end - last as f32) * weight;
+	}
+}
+
+// walks the contours like fragmentshader.frag, every on-curve point starts a line, quadratic or cubic segment
+fn flatten_glyph(glyph: &GlyphDescription, scale: f32) -> Vec<Edge> {
+	let segment_kinds = get_segment_kinds(glyph);
+	let to_pixels = |point: Point| (point.x * scale, point.y * scale);
+	let mut edges: Vec<Edge> = vec![];
+	let mut start = 0;
+	for &end in &glyph.end_pts_of_contours {
+		let end = end as usize;
+		if start >= end || end >= glyph.coordinates.len() {
+			start = end + 1;
+			continue;
+		}
+		let next = |i: usize| if i == end {start} else {i + 1};
+		for (offset, &segment_kind) in segment_kinds[start..=end].iter().enumerate() {
+			let i = start + offset;
+			let p0 = to_pixels(glyph.coordinates[i]);
+			let p1 = to_pixels(glyph.coordinates[next(i)]);
+			match segment_kind {
+				SEGMENT_LINE => push_edge(&mut edges, p0, p1),
+				SEGMENT_QUADRATIC => {
+					let p2 = to_pixels(glyph.coordinates[next(next(i))]);
+					flatten_quadratic(&mut edges, p0, p1, p2);
+				},
+				SEGMENT_CUBIC => {
+					let p2 = to_pixels(glyph.coordinates[next(next(i))]);
+					let p3 = to_pixels(glyph.coordinates[next(next(next(i)))]);
+					flatten_cubic(&mut edges, p0, p1, p2, p3);
+				},
+				_ => {}
+			}
+		}
+		start = end + 1;
+	}
+	edges
+}
+
+// horizontal edges never cross a scanline
+fn push_edge(edges: &mut Vec<Edge>, from: (f32, f32), to: (f32, f32)) {
+	if from.1 != to.1 {
+		edges.push(Edge { x0: from.0, y0: from.1, x1: to.0, y1: to.1 });
+	}
+}
+
+// enough steps that the chords stay within FLATNESS of the curve, which bends by at most deviation / steps^2
+fn curve_steps(deviation: f32) -> usize {
+	((deviation / FLATNESS).sqrt().ceil() as usize).clamp(1, MAX_CURVE_STEPS)
+}
+
+fn flatten_quadratic(edges: &mut Vec<Edge>, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32)) {
+	let deviation = (p0.0 - 2.0 * p1.0 + p2.0).hypot(p0.1 - 2.0 * p1.1 + p2.1) / 4.0;
+	let steps = curve_steps(deviation);
+	let mut previous = p0;
+	for step in 1..=steps {
+		let t = step as f32 / steps as f32;
+		let mt = 1.0 - t;
+		let point = (
+			mt * mt * p0.0 + 2.0 * mt * t * p1.0 + t * t * p2.0,
+			mt * mt * p0.1 + 2.0 * mt * t * p1.1 + t * t * p2.1,
+		);
+		push_edge(edges, previous, point);
+		previous = point;
+	}
+}
+
+fn flatten_cubic(edges: &mut Vec<Edge>, p0: (f32, f32), p1: (f32, f32), p2: (f32, f32), p3: (f32, f32)) {
+	let first = (p0.0 - 2.0 * p1.0 + p2.0).hypot(p0.1 - 2.0 * p1.1 + p2.1);
+	let second = (p1.0 - 2.0 * p2.0 + p3.0).hypot(p1.1 - 2.0 * p2.1 + p3.1);
+	let steps = curve_steps(first.max(second) * 3.0 / 4.0);
+	let mut previous = p0;
+	for step in 1..=steps {
+		let t = step as f32 / steps as f32;
+		let mt = 1.0 - t;
+		let point = (
+			mt * mt * mt * p0.0 + 3.0 * mt * mt * t * p1.0 + 3.0 * mt * t * t * p2.0 + t * t * t * p3.0,
+			mt * mt * mt * p0.1 + 3.0 * mt * mt * t * p1.1 + 3.0 * mt * t * t * p2.1 + t * t * t * p3.1,
+		);
+		push_edge(edges, previous, point);
+		previous = point;
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn triangle(extent: f32) -> GlyphDescription {
+		let on_curve = |x: f32, y: f32| Point { x, y, flags: 1 };
+		GlyphDescription {
+			number_of_contours: 1,
+			end_pts_of_contours: vec![2],
+			num_points: 3,
+			coordinates: vec![on_curve(-extent, -extent), on_curve(extent, -extent), on_curve(0.0, extent)],
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn triangle_covers_its_middle() {
+		let bitmap = rasterize_glyph(&triangle(8.0), 16, 16.0, FillRule::NonZero);
+		assert_eq!((bitmap.width, bitmap.height, bitmap.left, bitmap.top), (16, 16, -8, 8));
+		assert_eq!(bitmap.get(8, 12), 255);
+		assert_eq!(bitmap.get(0, 0), 0);
+	}
+
+	#[test]
+	fn oversized_outline_gives_an_empty_bitmap() {
+		// 32767 units at 3 pixels per unit is about 196000 pixels on each side
+		let bitmap = rasterize_glyph(&triangle(32767.0), 16, 48.0, FillRule::NonZero);
+		assert!(bitmap.pixels.is_empty());
+		assert_eq!((bitmap.width, bitmap.height), (0, 0));
+	}
+
+	#[test]
+	fn bitmap_sizes() {
+		assert_eq!(bitmap_size(8192, 8192), Some(MAX_BITMAP_PIXELS));
+		assert_eq!(bitmap_size(8193, 8192), None);
+		assert_eq!(bitmap_size(i64::MAX, i64::MAX), None);
+		assert_eq!(bitmap_size(-1, 4), None);
+	}
+}
@@ -0,0 +1,141 @@
+use crate::font_error::FontError;
+
+pub type Tag = [u8; 4];
+
+// big-endian cursor over a single table, every read is checked against the table's own bytes
+#[derive(Debug, Clone, Copy)]
+pub struct Reader<'a> {
+	data: &'a [u8],
+	offset: usize,
+}
+
+impl<'a> Reader<'a> {
+	pub fn new(data: &'a [u8]) -> Self {
+		Self {
+			data,
+			offset: 0,
+		}
+	}
+
+	pub fn data(&self) -> &'a [u8] {
+		self.data
+	}
+
+	pub fn len(&self) -> usize {
+		self.data.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.data.is_empty()
+	}
+
+	pub fn offset(&self) -> usize {
+		self.offset
+	}
+
+	pub fn remaining(&self) -> usize {
+		self.data.len() - self.offset
+	}
+
+	pub fn seek(&mut self, offset: usize) -> Result<(), FontError> {
+		if offset > self.data.len() {
+			return Err(FontError::OutOfBounds { offset, len: 0 });
+		}
+		self.offset = offset;
+		Ok(())
+	}
+
+	pub fn skip(&mut self, count: usize) -> Result<(), FontError> {
+		self.read_bytes(count)?;
+		Ok(())
+	}
+
+	// same bytes, independent cursor
+	pub fn at(&self, offset: usize) -> Result<Reader<'a>, FontError> {
+		let mut reader = *self;
+		reader.seek(offset)?;
+		Ok(reader)
+	}
+
+	pub fn sub_reader(&self, offset: usize, len: usize) -> Result<Reader<'a>, FontError> {
+		let data = offset.checked_add(len)
+			.and_then(|end| self.data.get(offset..end))
+			.ok_or(FontError::OutOfBounds { offset, len })?;
+		Ok(Reader::new(data))
+	}
+
+	pub fn slice_from(&self, offset: usize) -> Result<Reader<'a>, FontError> {
+		let data = self.data.get(offset..).ok_or(FontError::OutOfBounds { offset, len: 0 })?;
+		Ok(Reader::new(data))
+	}
+
+	pub fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], FontError> {
+		let bytes = self.offset.checked_add(count)
+			.and_then(|end| self.data.get(self.offset..end))
+			.ok_or(FontError::OutOfBounds { offset: self.offset, len: count })?;
+		self.offset += count;
+		Ok(bytes)
+	}
+
+	fn read_array<const N: usize>(&mut self) -> Result<[u8; N], FontError> {
+		let mut bytes = [0; N];
+		bytes.copy_from_slice(self.read_bytes(N)?);
+		Ok(bytes)
+	}
+
+	pub fn read_u8(&mut self) -> Result<u8, FontError> {
+		Ok(u8::from_be_bytes(self.read_array()?))
+	}
+
+	pub fn read_i8(&mut self) -> Result<i8, FontError> {
+		Ok(i8::from_be_bytes(self.read_array()?))
+	}
+
+	pub fn read_u16(&mut self) -> Result<u16, FontError> {
+		Ok(u16::from_be_bytes(self.read_array()?))
+	}
+
+	pub fn read_i16(&mut self) -> Result<i16, FontError> {
+		Ok(i16::from_be_bytes(self.read_array()?))
+	}
+
+	pub fn read_u24(&mut self) -> Result<u32, FontError> {
+		let [b0, b1, b2] = self.read_array()?;
+		Ok(u32::from_be_bytes([0, b0, b1, b2]))
+	}
+
+	pub fn read_u32(&mut self) -> Result<u32, FontError> {
+		Ok(u32::from_be_bytes(self.read_array()?))
+	}
+
+	pub fn read_i32(&mut self) -> Result<i32, FontError> {
+		Ok(i32::from_be_bytes(self.read_array()?))
+	}
+
+	// 16.16 fixed point
+	pub fn read_fixed(&mut self) -> Result<f32, FontError> {
+		Ok(self.read_i32()? as f32 / 65536.0)
+	}
+
+	// 2.14 fixed point, used for scales and normalized variation coordinates
+	pub fn read_f2dot14(&mut self) -> Result<f32, FontError> {
+		Ok(self.read_i16()? as f32 / 16384.0)
+	}
+
+	// seconds since 1904-01-01 00:00
+	pub fn read_long_datetime(&mut self) -> Result<i64, FontError> {
+		Ok(i64::from_be_bytes(self.read_array()?))
+	}
+
+	pub fn read_tag(&mut self) -> Result<Tag, FontError> {
+		self.read_array()
+	}
+
+	pub fn read_offset16(&mut self) -> Result<usize, FontError> {
+		Ok(self.read_u16()? as usize)
+	}
+
+	pub fn read_offset32(&mut self) -> Result<usize, FontError> {
+		Ok(self.read_u32()? as usize)
+	}
+}
@@ -11,9 +11,12 @@ use raw_window_handle::{HasDisplayHandle, HasWindowHandle};
 use winit::window::Window;
 use std::{char, ffi::CString};
 use std::fs;
-use crate::{font_loader::GlyphDescription, utils::*};
-use crate::font_loader::FontFile;
+use font_render::{font_loader::GlyphDescription, utils::*};
+use font_render::font_loader::FontFile;
+use font_render::shaping::{shape, Direction, DEFAULT_SCRIPT};
 
+// pixels per font unit on screen
+pub const TEXT_SCALE: f32 = 0.1;
 
 pub struct RenderManager {
 	pub gl: glow::Context,
@@ -69,7 +72,7 @@ impl RenderManager {
 					.map_err(|e| e.to_string())
 					.unwrap()
 					.as_raw(),
-				DisplayApiPreference::Wgl(None)
+				DisplayApiPreference::Egl
 			)
 			.expect("Failed to create display")
 		};
@@ -152,27 +155,31 @@ impl RenderManager {
 			self.gl.line_width(2.0);
 	        self.gl.clear(glow::COLOR_BUFFER_BIT);
 			
-			let mut offset_x = 0;
+			let features = font_file.get_feature_settings();
+			let mut placed_glyphs: Vec<(u16, i32, i32)> = vec![];
 			let mut offset_y = 0;
-			for char in text.chars() {
-				if char == '\r' {
-					offset_x = 0;
-					offset_y -= 1000;
+			for line in text.split('\r') {
+				let mut offset_x = 0;
+				for glyph in shape(font_file, line, DEFAULT_SCRIPT, None, Direction::LeftToRight, &features) {
+					placed_glyphs.push((glyph.gid, offset_x + glyph.x_offset, offset_y + glyph.y_offset));
+					offset_x += glyph.x_advance;
+				}
+				offset_y -= 1000;
+			}
+
+			for (index, offset_x, offset_y) in placed_glyphs {
+				// gids past the glyph table draw .notdef
+				let Some(glyph) = font_file.glyphs.get(index as usize).or(font_file.glyphs.first()) else {
 					continue;
-				}else if char == ' ' {
-					let index = font_file.unicode_to_glyph_index_map.get(&(char as u16)).unwrap(); // change this with data from hmtx table
-					let glyph = &font_file.glyphs[*index as usize];
-					offset_x += glyph.xmax as i32;
+				};
+				if glyph.coordinates.is_empty() {
 					continue;
 				}
-				let index = font_file.unicode_to_glyph_index_map.get(&(char as u16)).unwrap();
-				let glyph = &font_file.glyphs[*index as usize];
 				dbg!(glyph.xmax, glyph.ymax, glyph.xmin, glyph.ymin);
 
 				let indices: Vec<Vec<u32>> = get_indices(glyph);
 				
 				let scaled_points = scale_points(glyph, size, offset_x, offset_y);
-				offset_x += glyph.xmax as i32;
 
 	    		let vbo = create_outline_vbo(&self.gl, scaled_points);
 	    		let vao = create_outline_vao(&self.gl, vbo);
@@ -200,32 +207,28 @@ impl RenderManager {
 	}
 
 	fn render_full(&self, size: (u32, u32), text: &str, font_file: &FontFile) {
-		let font_dim = font_file.get_dimensions();
+		let Ok(font_dim) = font_file.get_dimensions() else {
+			return;
+		};
 		let max_dim = (font_dim.0 - font_dim.2, font_dim.1 - font_dim.3);
-		let mut offsets: Vec<(u32, u32)> = vec![];
+		let mut offsets: Vec<(i32, i32)> = vec![];
 		let mut glyphs:Vec<GlyphDescription> = vec![];
-		let mut next_offset = (0, 0);
-		let scale = 0.1;
-
-		for char in text.chars() {
-			if char == '\r' {
-				next_offset.1 += max_dim.1 as u32;
-				next_offset.0 = 0;
-			}else if char == ' ' {
-				let index = font_file.unicode_to_glyph_index_map.get(&(char as u16)).unwrap(); // change this with data from hmtx table
-				let glyph = &font_file.glyphs[*index as usize];
-				next_offset.0 += glyph.xmax as u32;
-			} else if char == '\t' {
-				let index = font_file.unicode_to_glyph_index_map.get(&32).unwrap(); // change this with data from hmtx table
-				let glyph = &font_file.glyphs[*index as usize];
-				next_offset.0 += glyph.xmax as u32 * 4;
-			}
-			else {
-				let index = font_file.unicode_to_glyph_index_map.get(&(char as u16)).unwrap();
-				let glyph = &font_file.glyphs[*index as usize];
-				offsets.push(next_offset);
-				next_offset.0 += glyph.xmax as u32;
-				glyphs.push(glyph.clone());
+		let scale = TEXT_SCALE;
+
+		// offsets grow downwards from the top of the first line
+		let features = font_file.get_feature_settings();
+		for (line_number, line) in text.split('\r').enumerate() {
+			let line_y = line_number as i32 * max_dim.1 as i32;
+			let mut pen_x = 0;
+			for shaped in shape(font_file, line, DEFAULT_SCRIPT, None, Direction::LeftToRight, &features) {
+				let Some(glyph) = font_file.glyphs.get(shaped.gid as usize).or(font_file.glyphs.first()) else {
+					continue;
+				};
+				if !glyph.coordinates.is_empty() {
+					offsets.push((pen_x + shaped.x_offset, line_y - shaped.y_offset));
+					glyphs.push(glyph.clone());
+				}
+				pen_x += shaped.x_advance;
 			}
 		}
 
@@ -0,0 +1,561 @@
+// builds small fonts in memory for the parser tests, only the tables the decoders under test read
+
+use crate::reader::Tag;
+
+// one contour is a list of (x, y, on curve) in font units
+pub type Contour = Vec<(i16, i16, bool)>;
+
+#[derive(Debug, Clone, Default)]
+pub struct SfntBuilder {
+	tables: Vec<(Tag, Vec<u8>)>,
+}
+
+impl SfntBuilder {
+	// a table with a tag that's already there replaces the earlier one
+	pub fn table(mut self, tag: &Tag, data: Vec<u8>) -> Self {
+		self.tables.retain(|(existing, _)| existing != tag);
+		self.tables.push((*tag, data));
+		self
+	}
+
+	// tables sorted by tag and padded to four bytes, checksums are left at zero since nothing reads them
+	pub fn build(mut self) -> Vec<u8> {
+		self.tables.sort_by_key(|(tag, _)| *tag);
+		let num_tables = self.tables.len() as u16;
+		let mut font: Vec<u8> = vec![];
+		font.extend(0x00010000u32.to_be_bytes());
+		font.extend(num_tables.to_be_bytes());
+		font.extend([0; 6]); // searchRange, entrySelector, rangeShift
+
+		let mut offset = 12 + 16 * self.tables.len();
+		for (tag, data) in &self.tables {
+			font.extend(tag);
+			font.extend([0; 4]);
+			font.extend((offset as u32).to_be_bytes());
+			font.extend((data.len() as u32).to_be_bytes());
+			offset += data.len().next_multiple_of(4);
+		}
+		for (_, data) in &self.tables {
+			font.extend(data);
+			font.resize(font.len().next_multiple_of(4), 0);
+		}
+		font
+	}
+}
+
+// a TTC holding the faces in order, a table with the same tag and data in several faces is stored once and shared
+// version 2 headers end with an empty DSIG record
+pub fn collection(major_version: u16, faces: &[SfntBuilder]) -> Vec<u8> {
+	let header_len = 12 + 4 * faces.len() + if major_version >= 2 {12} else {0};
+	let directories_len: usize = faces.iter().map(|face| 12 + 16 * face.tables.len()).sum();
+	let mut header = b"ttcf".to_vec();
+	header.extend(words(&[major_version, 0]));
+	header.extend((faces.len() as u32).to_be_bytes());
+
+	let mut directories: Vec<u8> = vec![];
+	let mut data: Vec<u8> = vec![];
+	let mut stored: Vec<(Tag, Vec<u8>, usize)> = vec![];
+	for face in faces {
+		header.extend(((header_len + directories.len()) as u32).to_be_bytes());
+		let mut tables = face.tables.clone();
+		tables.sort_by_key(|(tag, _)| *tag);
+		directories.extend(0x00010000u32.to_be_bytes());
+		directories.extend(words(&[tables.len() as u16, 0, 0, 0]));
+		for (tag, table) in tables {
+			let offset = match stored.iter().find(|(stored_tag, stored_table, _)| *stored_tag == tag && *stored_table == table) {
+				Some(&(_, _, offset)) => offset,
+				None => {
+					let offset = header_len + directories_len + data.len();
+					data.extend(&table);
+					data.resize(data.len().next_multiple_of(4), 0);
+					stored.push((tag, table.clone(), offset));
+					offset
+				}
+			};
+			directories.extend(tag);
+			directories.extend([0; 4]);
+			directories.extend((offset as u32).to_be_bytes());
+			directories.extend((table.len() as u32).to_be_bytes());
+		}
+	}
+	if major_version >= 2 {
+		header.extend([0; 12]); // ulDsigTag, ulDsigLength, ulDsigOffset
+	}
+	header.extend(directories);
+	header.extend(data);
+	header
+}
+
+// a font with head, hhea, maxp, hmtx, loca, glyf and cmap, every glyph 500 units wide
+pub fn minimal_font(glyphs: &[Vec<u8>], long_loca: bool, cmap_subtable: Vec<u8>) -> Vec<u8> {
+	minimal_font_builder(glyphs, long_loca, cmap_subtable).build()
+}
+
+// the same tables, left open for tests that add layout or metrics tables
+pub fn minimal_font_builder(glyphs: &[Vec<u8>], long_loca: bool, cmap_subtable: Vec<u8>) -> SfntBuilder {
+	// short offsets are stored halved, so every glyph is padded to an even length
+	let mut glyf: Vec<u8> = vec![];
+	let mut offsets: Vec<u32> = vec![];
+	for glyph in glyphs {
+		offsets.push(glyf.len() as u32);
+		glyf.extend(glyph);
+		glyf.resize(glyf.len().next_multiple_of(2), 0);
+	}
+	offsets.push(glyf.len() as u32);
+	let loca: Vec<u8> = offsets.iter().flat_map(|&offset| if long_loca {
+		offset.to_be_bytes().to_vec()
+	} else {
+		((offset / 2) as u16).to_be_bytes().to_vec()
+	}).collect();
+
+	common_tables(glyphs.len() as u16, long_loca, cmap_subtable)
+		.table(b"loca", loca)
+		.table(b"glyf", glyf)
+}
+
+// the same font with its outlines in a CFF table instead of glyf and loca
+pub fn minimal_cff_font(cff: Vec<u8>, num_glyphs: u16) -> Vec<u8> {
+	common_tables(num_glyphs, false, cmap_format_4(&[]))
+		.table(b"CFF ", cff)
+		.build()
+}
+
+fn common_tables(num_glyphs: u16, long_loca: bool, cmap_subtable: Vec<u8>) -> SfntBuilder {
+	let mut head = vec![0; 54];
+	head[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+	head[12..16].copy_from_slice(&0x5F0F3CF5u32.to_be_bytes());
+	head[18..20].copy_from_slice(&1000u16.to_be_bytes());
+	head[50..52].copy_from_slice(&(long_loca as i16).to_be_bytes());
+
+	let mut hhea = vec![0; 36];
+	hhea[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+	hhea[4..6].copy_from_slice(&800i16.to_be_bytes());
+	hhea[6..8].copy_from_slice(&(-200i16).to_be_bytes());
+	hhea[34..36].copy_from_slice(&num_glyphs.to_be_bytes());
+
+	let mut maxp = vec![0; 32];
+	maxp[0..4].copy_from_slice(&0x00010000u32.to_be_bytes());
+	maxp[4..6].copy_from_slice(&num_glyphs.to_be_bytes());
+
+	let mut hmtx: Vec<u8> = vec![];
+	for _ in 0..num_glyphs {
+		hmtx.extend(500u16.to_be_bytes());
+		hmtx.extend(0i16.to_be_bytes());
+	}
+
+	SfntBuilder::default()
+		.table(b"head", head)
+		.table(b"hhea", hhea)
+		.table(b"maxp", maxp)
+		.table(b"hmtx", hmtx)
+		// a single windows unicode bmp encoding record
+		.table(b"cmap", cmap_table(&[(3, 1, cmap_subtable)]))
+}
+
+// a CFF INDEX with four byte offsets
+pub fn cff_index(objects: &[Vec<u8>]) -> Vec<u8> {
+	let mut index: Vec<u8> = vec![];
+	index.extend((objects.len() as u16).to_be_bytes());
+	if objects.is_empty() {
+		return index;
+	}
+	index.push(4);
+	let mut offset = 1u32;
+	index.extend(offset.to_be_bytes());
+	for object in objects {
+		offset += object.len() as u32;
+		index.extend(offset.to_be_bytes());
+	}
+	index.extend(objects.concat());
+	index
+}
+
+// a name-keyed CFF table, charset holds the string ids of glyph 1 on
+// dict offsets are written as five byte integers so the top dict's size doesn't depend on them
+pub fn cff_table(char_strings: &[Vec<u8>], local_subrs: &[Vec<u8>], charset: &[u16]) -> Vec<u8> {
+	let dict_integer = |value: usize| {
+		let mut bytes = vec![29];
+		bytes.extend((value as i32).to_be_bytes());
+		bytes
+	};
+	let header = [1, 0, 4, 4];
+	let name_index = cff_index(&[b"Test".to_vec()]);
+	let string_index = cff_index(&[]);
+	let global_subrs = cff_index(&[]);
+	// charset, CharStrings and Private, each operand five bytes and each operator one
+	let top_dict_len = 5 + 1 + 5 + 1 + 10 + 1;
+	let top_dict_index_len = cff_index(&[vec![0; top_dict_len]]).len();
+
+	let charset_offset = header.len() + name_index.len() + top_dict_index_len + string_index.len() + global_subrs.len();
+	let mut charset_data = vec![0];
+	for sid in charset {
+		charset_data.extend(sid.to_be_bytes());
+	}
+	let char_strings_offset = charset_offset + charset_data.len();
+	let char_strings = cff_index(char_strings);
+	let private_offset = char_strings_offset + char_strings.len();
+	// Subrs counts from the start of the private dict, which is only the Subrs entry
+	let mut private_dict = dict_integer(6);
+	private_dict.push(19);
+
+	let mut top_dict = dict_integer(charset_offset);
+	top_dict.push(15);
+	top_dict.extend(dict_integer(char_strings_offset));
+	top_dict.push(17);
+	top_dict.extend(dict_integer(private_dict.len()));
+	top_dict.extend(dict_integer(private_offset));
+	top_dict.push(18);
+
+	[
+		header.to_vec(),
+		name_index,
+		cff_index(&[top_dict]),
+		string_index,
+		global_subrs,
+		charset_data,
+		char_strings,
+		private_dict,
+		cff_index(local_subrs),
+	].concat()
+}
+
+// header and bounds for a glyph whose contour data is written by hand
+pub fn glyph_header(number_of_contours: i16, bounds: [i16; 4]) -> Vec<u8> {
+	let mut glyph: Vec<u8> = vec![];
+	glyph.extend(number_of_contours.to_be_bytes());
+	for value in bounds {
+		glyph.extend(value.to_be_bytes());
+	}
+	glyph
+}
+
+// encodes the way font tools do, short vectors for small deltas, same-as-previous for zero ones
+// and runs of equal flags folded into a repeat
+pub fn encode_simple_glyph(contours: &[Contour]) -> Vec<u8> {
+	let points: Vec<(i16, i16, bool)> = contours.iter().flatten().copied().collect();
+	let (mut xmin, mut ymin, mut xmax, mut ymax) = (i16::MAX, i16::MAX, i16::MIN, i16::MIN);
+	for &(x, y, _) in &points {
+		xmin = xmin.min(x);
+		ymin = ymin.min(y);
+		xmax = xmax.max(x);
+		ymax = ymax.max(y);
+	}
+	let mut glyph = glyph_header(contours.len() as i16, [xmin, ymin, xmax, ymax]);
+
+	let mut end_point = 0;
+	for contour in contours {
+		end_point += contour.len() as u16;
+		glyph.extend((end_point - 1).to_be_bytes());
+	}
+	glyph.extend(0u16.to_be_bytes()); // instructionLength
+
+	let mut flags: Vec<u8> = vec![];
+	let (mut x_data, mut y_data): (Vec<u8>, Vec<u8>) = (vec![], vec![]);
+	let (mut previous_x, mut previous_y) = (0i32, 0i32);
+	for &(x, y, on_curve) in &points {
+		let mut flag = on_curve as u8;
+		flag |= encode_delta(x as i32 - previous_x, 1, 4, &mut x_data);
+		flag |= encode_delta(y as i32 - previous_y, 2, 5, &mut y_data);
+		flags.push(flag);
+		(previous_x, previous_y) = (x as i32, y as i32);
+	}
+
+	let mut i = 0;
+	while i < flags.len() {
+		let run = flags[i..].iter().take(256).take_while(|&&flag| flag == flags[i]).count();
+		if run > 1 {
+			glyph.extend([flags[i] | 0x08, (run - 1) as u8]);
+		} else {
+			glyph.push(flags[i]);
+		}
+		i += run;
+	}
+	glyph.extend(x_data);
+	glyph.extend(y_data);
+	glyph
+}
+
+// returns the flag bits for one axis, short_bit marks a byte and same_bit its sign, or a zero delta without one
+fn encode_delta(delta: i32, short_bit: u8, same_bit: u8, data: &mut Vec<u8>) -> u8 {
+	if delta == 0 {
+		1 << same_bit
+	} else if delta.abs() <= 255 {
+		data.push(delta.unsigned_abs() as u8);
+		(1 << short_bit) | if delta > 0 {1 << same_bit} else {0}
+	} else {
+		data.extend((delta as i16).to_be_bytes());
+		0
+	}
+}
+
+// a cmap format 4 subtable, each segment is (start, end, id delta, glyph ids)
+// segments with glyph ids go through idRangeOffset into the glyph array, the others use only the delta
+// the closing 0xFFFF segment is added here
+pub fn cmap_format_4(segments: &[(u16, u16, i16, Option<Vec<u16>>)]) -> Vec<u8> {
+	let mut segments = segments.to_vec();
+	segments.push((0xFFFF, 0xFFFF, 1, None));
+	let seg_count = segments.len();
+
+	let mut glyph_id_array: Vec<u16> = vec![];
+	let mut id_range_offsets: Vec<u16> = vec![];
+	for (i, (_, _, _, glyph_ids)) in segments.iter().enumerate() {
+		match glyph_ids {
+			// counted in bytes from this segment's own idRangeOffset entry
+			Some(glyph_ids) => {
+				id_range_offsets.push((2 * (seg_count - i + glyph_id_array.len())) as u16);
+				glyph_id_array.extend(glyph_ids);
+			},
+			None => id_range_offsets.push(0),
+		}
+	}
+
+	let mut subtable: Vec<u8> = vec![];
+	subtable.extend(4u16.to_be_bytes());
+	subtable.extend(((16 + 8 * seg_count + 2 * glyph_id_array.len()) as u16).to_be_bytes());
+	subtable.extend(0u16.to_be_bytes()); // language
+	subtable.extend(((2 * seg_count) as u16).to_be_bytes());
+	subtable.extend([0; 6]); // searchRange, entrySelector, rangeShift
+	for &(_, end, _, _) in &segments {
+		subtable.extend(end.to_be_bytes());
+	}
+	subtable.extend(0u16.to_be_bytes()); // reservedPad
+	for &(start, _, _, _) in &segments {
+		subtable.extend(start.to_be_bytes());
+	}
+	for &(_, _, delta, _) in &segments {
+		subtable.extend(delta.to_be_bytes());
+	}
+	for id_range_offset in id_range_offsets {
+		subtable.extend(id_range_offset.to_be_bytes());
+	}
+	for glyph_id in glyph_id_array {
+		subtable.extend(glyph_id.to_be_bytes());
+	}
+	subtable
+}
+
+// encoding records are (platform id, encoding id, subtable)
+pub fn cmap_table(encodings: &[(u16, u16, Vec<u8>)]) -> Vec<u8> {
+	let mut cmap = words(&[0, encodings.len() as u16]);
+	let mut subtables: Vec<u8> = vec![];
+	for (platform_id, encoding_id, subtable) in encodings {
+		cmap.extend(words(&[*platform_id, *encoding_id]));
+		cmap.extend(((4 + 8 * encodings.len() + subtables.len()) as u32).to_be_bytes());
+		subtables.extend(subtable);
+	}
+	cmap.extend(subtables);
+	cmap
+}
+
+pub fn cmap_format_0(glyph_ids: &[u8; 256]) -> Vec<u8> {
+	let mut subtable = words(&[0, 262, 0]);
+	subtable.extend(glyph_ids);
+	subtable
+}
+
+// a subheader's first code, id delta and glyph ids
+pub type SubHeader = (u16, i16, Vec<u16>);
+
+// the first subheader maps single byte codes, the others the second byte after their high byte
+pub fn cmap_format_2(single_byte: SubHeader, two_byte: &[(u8, SubHeader)]) -> Vec<u8> {
+	let mut sub_header_keys = [0u16; 256];
+	let mut sub_headers = vec![single_byte];
+	for (i, (high_byte, sub_header)) in two_byte.iter().enumerate() {
+		sub_header_keys[*high_byte as usize] = 8 * (i as u16 + 1);
+		sub_headers.push(sub_header.clone());
+	}
+
+	let mut headers: Vec<u8> = vec![];
+	let mut glyph_id_array: Vec<u16> = vec![];
+	for (i, (first_code, id_delta, glyph_ids)) in sub_headers.iter().enumerate() {
+		// counted in bytes from this subheader's own idRangeOffset field
+		let id_range_offset = 8 * (sub_headers.len() - i) - 6 + 2 * glyph_id_array.len();
+		headers.extend(words(&[*first_code, glyph_ids.len() as u16, *id_delta as u16, id_range_offset as u16]));
+		glyph_id_array.extend(glyph_ids);
+	}
+
+	let mut subtable = words(&[2, (518 + headers.len() + 2 * glyph_id_array.len()) as u16, 0]);
+	subtable.extend(words(&sub_header_keys));
+	subtable.extend(headers);
+	subtable.extend(words(&glyph_id_array));
+	subtable
+}
+
+pub fn cmap_format_6(first_code: u16, glyph_ids: &[u16]) -> Vec<u8> {
+	let mut subtable = words(&[6, (10 + 2 * glyph_ids.len()) as u16, 0, first_code, glyph_ids.len() as u16]);
+	subtable.extend(words(glyph_ids));
+	subtable
+}
+
+pub fn cmap_format_10(start_char_code: u32, glyph_ids: &[u16]) -> Vec<u8> {
+	let mut subtable = words(&[10, 0]);
+	for value in [(20 + 2 * glyph_ids.len()) as u32, 0, start_char_code, glyph_ids.len() as u32] {
+		subtable.extend(value.to_be_bytes());
+	}
+	subtable.extend(words(glyph_ids));
+	subtable
+}
+
+// format 12 or 13, groups are (start char code, end char code, glyph id)
+pub fn cmap_groups(format: u16, groups: &[(u32, u32, u32)]) -> Vec<u8> {
+	let mut subtable = words(&[format, 0]);
+	subtable.extend((16 + 12 * groups.len() as u32).to_be_bytes());
+	subtable.extend(0u32.to_be_bytes());
+	subtable.extend((groups.len() as u32).to_be_bytes());
+	for &(start, end, glyph_id) in groups {
+		subtable.extend([start, end, glyph_id].iter().flat_map(|value| value.to_be_bytes()));
+	}
+	subtable
+}
+
+// one record per variation selector, with its default ranges as (start, additional count)
+// and its non-default mappings as (unicode value, glyph id)
+pub type VariationSelector = (u32, Vec<(u32, u8)>, Vec<(u32, u16)>);
+
+pub fn cmap_format_14(selectors: &[VariationSelector]) -> Vec<u8> {
+	let header_len = 10 + 11 * selectors.len();
+	let mut records: Vec<u8> = vec![];
+	let mut tables: Vec<u8> = vec![];
+	for (var_selector, default_ranges, mappings) in selectors {
+		let mut default_uvs = (default_ranges.len() as u32).to_be_bytes().to_vec();
+		for &(start, additional_count) in default_ranges {
+			default_uvs.extend(&start.to_be_bytes()[1..]);
+			default_uvs.push(additional_count);
+		}
+		let mut non_default_uvs = (mappings.len() as u32).to_be_bytes().to_vec();
+		for &(unicode_value, glyph_id) in mappings {
+			non_default_uvs.extend(&unicode_value.to_be_bytes()[1..]);
+			non_default_uvs.extend(glyph_id.to_be_bytes());
+		}
+
+		records.extend(&var_selector.to_be_bytes()[1..]);
+		// a selector without ranges or without mappings has a null offset for them
+		for (table, present) in [(default_uvs, !default_ranges.is_empty()), (non_default_uvs, !mappings.is_empty())] {
+			let offset = if present {header_len + tables.len()} else {0};
+			records.extend((offset as u32).to_be_bytes());
+			if present {
+				tables.extend(table);
+			}
+		}
+	}
+	let mut subtable = 14u16.to_be_bytes().to_vec();
+	subtable.extend(((header_len + tables.len()) as u32).to_be_bytes());
+	subtable.extend((selectors.len() as u32).to_be_bytes());
+	subtable.extend(records);
+	subtable.extend(tables);
+	subtable
+}
+
+// a header of 16 bit fields and tags followed by the tables its offsets point at, in field order
+// offsets count from the start of the header, which is how every layout table nests its subtables
+#[derive(Debug, Clone)]
+pub enum Field {
+	U16(u16),
+	Tag(Tag),
+	Offset(Vec<u8>),
+	Null,
+}
+
+pub fn offset_table(fields: &[Field]) -> Vec<u8> {
+	let header_len: usize = fields.iter().map(|field| if let Field::Tag(_) = field {4} else {2}).sum();
+	let mut header: Vec<u8> = vec![];
+	let mut children: Vec<u8> = vec![];
+	for field in fields {
+		match field {
+			Field::U16(value) => header.extend(value.to_be_bytes()),
+			Field::Tag(tag) => header.extend(tag),
+			Field::Offset(child) => {
+				header.extend(((header_len + children.len()) as u16).to_be_bytes());
+				children.extend(child);
+			},
+			Field::Null => header.extend(0u16.to_be_bytes()),
+		}
+	}
+	header.extend(children);
+	header
+}
+
+pub fn words(values: &[u16]) -> Vec<u8> {
+	values.iter().flat_map(|value| value.to_be_bytes()).collect()
+}
+
+// a GSUB or GPOS table with one DFLT script whose default language system has every feature
+// features are (tag, lookup indices), lookups are (type, flag, subtables)
+pub fn layout_table(features: &[(Tag, Vec<u16>)], lookups: &[(u16, u16, Vec<Vec<u8>>)]) -> Vec<u8> {
+	let mut lang_sys = words(&[0, 0xFFFF, features.len() as u16]);
+	lang_sys.extend(words(&(0..features.len() as u16).collect::<Vec<u16>>()));
+	let script = offset_table(&[Field::Offset(lang_sys), Field::U16(0)]);
+	let script_list = offset_table(&[Field::U16(1), Field::Tag(*b"DFLT"), Field::Offset(script)]);
+
+	let mut feature_fields = vec![Field::U16(features.len() as u16)];
+	for (tag, lookup_indices) in features {
+		let mut feature = words(&[0, lookup_indices.len() as u16]);
+		feature.extend(words(lookup_indices));
+		feature_fields.extend([Field::Tag(*tag), Field::Offset(feature)]);
+	}
+
+	let mut lookup_fields = vec![Field::U16(lookups.len() as u16)];
+	for (lookup_type, lookup_flag, subtables) in lookups {
+		let mut fields = vec![Field::U16(*lookup_type), Field::U16(*lookup_flag), Field::U16(subtables.len() as u16)];
+		fields.extend(subtables.iter().map(|subtable| Field::Offset(subtable.clone())));
+		lookup_fields.push(Field::Offset(offset_table(&fields)));
+	}
+
+	offset_table(&[
+		Field::U16(1),
+		Field::U16(0),
+		Field::Offset(script_list),
+		Field::Offset(offset_table(&feature_fields)),
+		Field::Offset(offset_table(&lookup_fields)),
+	])
+}
+
+// format 1, glyphs sorted
+pub fn coverage(glyphs: &[u16]) -> Vec<u8> {
+	let mut coverage = words(&[1, glyphs.len() as u16]);
+	coverage.extend(words(glyphs));
+	coverage
+}
+
+// format 2, each range is (start, end, class)
+pub fn class_def(ranges: &[(u16, u16, u16)]) -> Vec<u8> {
+	let mut class_def = words(&[2, ranges.len() as u16]);
+	for &(start, end, class) in ranges {
+		class_def.extend(words(&[start, end, class]));
+	}
+	class_def
+}
+
+pub fn anchor(x: i16, y: i16) -> Vec<u8> {
+	words(&[1, x as u16, y as u16])
+}
+
+// version 1.0 with only glyph classes, ranges are (start, end, class)
+pub fn gdef_table(glyph_classes: &[(u16, u16, u16)]) -> Vec<u8> {
+	offset_table(&[Field::U16(1), Field::U16(0), Field::Offset(class_def(glyph_classes)), Field::Null, Field::Null, Field::Null])
+}
+
+// xorshift, enough randomness for property tests and the same cases on every run
+pub struct TestRng(u64);
+
+impl TestRng {
+	pub fn new(seed: u64) -> Self {
+		Self(seed.max(1))
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.0 ^= self.0 << 13;
+		self.0 ^= self.0 >> 7;
+		self.0 ^= self.0 << 17;
+		self.0
+	}
+
+	// in low..high
+	pub fn range(&mut self, low: i32, high: i32) -> i32 {
+		low + (self.next_u64() % (high - low) as u64) as i32
+	}
+
+	pub fn chance(&mut self, percent: u64) -> bool {
+		self.next_u64() % 100 < percent
+	}
+}
@@ -0,0 +1,156 @@
+use crate::font_loader::FontFile;
+use crate::gpos::{GlyphPosition, PositioningOptions};
+use crate::gsub::GlyphBuffer;
+use crate::layout::FeatureSetting;
+use crate::reader::Tag;
+use crate::utils::is_variation_selector;
+
+// without script detection everything is laid out as latin, which falls back to DFLT where latn is missing
+pub const DEFAULT_SCRIPT: Tag = *b"latn";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Direction {
+	#[default]
+	LeftToRight,
+	RightToLeft,
+}
+
+// cluster is the byte offset in the text of the first character the glyph came from
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShapedGlyph {
+	pub gid: u16,
+	pub cluster: usize,
+	pub x_advance: i32,
+	pub y_advance: i32,
+	pub x_offset: i32,
+	pub y_offset: i32,
+}
+
+// cmap, then GSUB, then hmtx advances adjusted by GPOS or the kern table, in font units
+// right to left runs are shaped in logical order and come back in visual order
+pub fn shape(font_file: &FontFile, text: &str, script: Tag, language: Option<Tag>, direction: Direction, features: &[FeatureSetting]) -> Vec<ShapedGlyph> {
+	let mut features = features.to_vec();
+	if direction == Direction::RightToLeft {
+		features.extend([(*b"rtla", 1), (*b"rtlm", 1)]);
+	}
+
+	let mut buffer = map_characters(font_file, text);
+	if !font_file.gsub.lookups.is_empty() {
+		let lookup_indices = font_file.gsub.get_lookup_indices(script, language, &features);
+		font_file.gsub.apply(&font_file.gdef, &mut buffer, &lookup_indices);
+		for glyph in &mut buffer.glyphs {
+			*glyph = font_file.glyph_or_notdef(*glyph);
+		}
+	}
+	let positions = position_glyphs(font_file, &buffer.glyphs, script, language, direction, &features);
+
+	let mut shaped: Vec<ShapedGlyph> = buffer.glyphs.iter().zip(&buffer.clusters).zip(&positions).map(|((&gid, &cluster), position)| ShapedGlyph {
+		gid,
+		cluster,
+		x_advance: position.x_advance,
+		y_advance: position.y_advance,
+		x_offset: position.x_offset,
+		y_offset: position.y_offset,
+	}).collect();
+	if direction == Direction::RightToLeft {
+		shaped.reverse();
+	}
+	shaped
+}
+
+// a tab is as wide as four spaces, variation selectors fold into the character before them
+fn map_characters(font_file: &FontFile, text: &str) -> GlyphBuffer {
+	let mut buffer = GlyphBuffer::default();
+	let mut chars = text.char_indices().peekable();
+	while let Some((cluster, char)) = chars.next() {
+		if char == '\t' {
+			buffer.glyphs.extend([font_file.get_glyph_index(' '); 4]);
+			buffer.clusters.extend([cluster; 4]);
+			continue;
+		}
+		let variation_selector = chars.next_if(|&(_, next)| is_variation_selector(next)).map(|(_, next)| next);
+		buffer.glyphs.push(font_file.get_glyph_index_with_variation(char, variation_selector));
+		buffer.clusters.push(cluster);
+	}
+	buffer
+}
+
+fn position_glyphs(font_file: &FontFile, glyph_indices: &[u16], script: Tag, language: Option<Tag>, direction: Direction, features: &[FeatureSetting]) -> Vec<GlyphPosition> {
+	let mut positions: Vec<GlyphPosition> = glyph_indices.iter().map(|&index| GlyphPosition {
+		x_advance: font_file.glyphs.get(index as usize).map_or(0, |glyph| glyph.advance_width as i32),
+		..Default::default()
+	}).collect();
+	if !font_file.gpos.lookups.is_empty() {
+		let lookup_indices = font_file.gpos.get_lookup_indices(script, language, features);
+		font_file.gpos.apply(&font_file.gdef, glyph_indices, &mut positions, &lookup_indices, PositioningOptions {
+			coordinates: &font_file.normalized_coordinates,
+			ppem: None,
+			direction,
+		});
+	} else if features.iter().any(|&(tag, value)| tag == *b"kern" && value != 0) {
+		// fonts without GPOS may still have the older kern table
+		for (i, pair) in glyph_indices.windows(2).enumerate() {
+			positions[i].x_advance += font_file.get_kerning(pair[0], pair[1]) as i32;
+		}
+	}
+	positions
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::sfnt_builder::*;
+
+	// glyph 1 is a base for 'A', 2 a mark for U+0301, 3 and 4 join cursively for 'B' and 'C'
+	fn font_with_gpos(feature: Tag, lookups: &[(u16, u16, Vec<Vec<u8>>)]) -> FontFile {
+		let cmap = cmap_format_4(&[(0x41, 0x41, 1 - 0x41, None), (0x42, 0x43, 3 - 0x42, None), (0x301, 0x301, 2 - 0x301, None)]);
+		let font = minimal_font_builder(&[vec![], vec![], vec![], vec![], vec![]], false, cmap)
+			.table(b"GDEF", gdef_table(&[(1, 1, 1), (2, 2, 3), (3, 4, 1)]))
+			.table(b"GPOS", layout_table(&[(feature, (0..lookups.len() as u16).collect())], lookups))
+			.build();
+		FontFile::parse(&font).unwrap()
+	}
+
+	// where each glyph is drawn, walking the pen left to right in visual order
+	fn placed(font_file: &FontFile, text: &str, direction: Direction) -> Vec<(u16, i32, i32)> {
+		let mut pen_x = 0;
+		shape(font_file, text, DEFAULT_SCRIPT, None, direction, &font_file.get_feature_settings()).iter().map(|glyph| {
+			let origin = (glyph.gid, pen_x + glyph.x_offset, glyph.y_offset);
+			pen_x += glyph.x_advance;
+			origin
+		}).collect()
+	}
+
+	#[test]
+	fn mark_sits_on_its_base_in_both_directions() {
+		// the mark's anchor at its origin goes to (250, 600) on the base
+		let mark_base = offset_table(&[
+			Field::U16(1),
+			Field::Offset(coverage(&[2])),
+			Field::Offset(coverage(&[1])),
+			Field::U16(1),
+			Field::Offset(offset_table(&[Field::U16(1), Field::U16(0), Field::Offset(anchor(0, 0))])),
+			Field::Offset(offset_table(&[Field::U16(1), Field::Offset(anchor(250, 600))])),
+		]);
+		let font_file = font_with_gpos(*b"mark", &[(4, 0, vec![mark_base])]);
+		assert_eq!(placed(&font_file, "A\u{301}", Direction::LeftToRight), vec![(1, 0, 0), (2, 250, 600)]);
+		assert_eq!(placed(&font_file, "A\u{301}", Direction::RightToLeft), vec![(2, 250, 600), (1, 0, 0)]);
+		// a second base before it in logical order moves both to the right of it
+		assert_eq!(placed(&font_file, "AA\u{301}", Direction::RightToLeft), vec![(2, 250, 600), (1, 0, 0), (1, 500, 0)]);
+	}
+
+	#[test]
+	fn cursive_exit_meets_the_next_entry_right_to_left() {
+		// right to left glyphs leave on their left and are entered on their right
+		let entry_exit = |entry: (i16, i16), exit: (i16, i16)| [Field::Offset(anchor(entry.0, entry.1)), Field::Offset(anchor(exit.0, exit.1))];
+		let mut fields = vec![Field::U16(1), Field::Offset(coverage(&[3, 4])), Field::U16(2)];
+		fields.extend(entry_exit((480, 0), (20, 100)));
+		fields.extend(entry_exit((480, 0), (20, 100)));
+		let font_file = font_with_gpos(*b"curs", &[(3, 0, vec![offset_table(&fields)])]);
+		let glyphs = placed(&font_file, "BC", Direction::RightToLeft);
+		// C is drawn first, its entry at x 480 meets the exit of B at x 20 and it's raised to the exit's height
+		assert_eq!(glyphs.iter().map(|glyph| glyph.0).collect::<Vec<u16>>(), vec![4, 3]);
+		assert_eq!(glyphs[0].1 + 480, glyphs[1].1 + 20);
+		assert_eq!(glyphs[0].2, glyphs[1].2 + 100);
+	}
+}
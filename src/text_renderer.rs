@@ -0,0 +1,123 @@
+use std::collections::hash_map::{Entry, HashMap};
+
+use crate::font_error::FontError;
+use crate::font_loader::FontFile;
+use crate::rasterizer::{bitmap_size, Bitmap, FillRule};
+use crate::shaping::{shape, Direction, DEFAULT_SCRIPT};
+
+// lines are laid out like the window does, shaped left to right and stacked by the font's line metrics
+// the canvas holds every line's ascender to descender and any ink that reaches past them, plus padding on each side
+pub fn render_text(font_file: &FontFile, text: &str, ppem: f32, fill_rule: FillRule, padding: u32) -> Result<Bitmap, FontError> {
+	let units_per_em = font_file.units_per_em()?;
+	let scale = ppem / units_per_em.max(1) as f32;
+	let (ascender, descender, line_gap) = font_file.line_metrics();
+	let line_height = match ascender as i32 - descender as i32 + line_gap as i32 {
+		height if height > 0 => height,
+		_ => units_per_em as i32,
+	};
+
+	// glyph bitmaps with the pixel origin they're drawn at, y grows upwards from the first baseline
+	let features = font_file.get_feature_settings();
+	let mut cache: HashMap<u16, Bitmap> = HashMap::new();
+	let mut placed: Vec<(u16, i32, i32)> = vec![];
+	let mut widest_line = 0;
+	let mut line_count = 0;
+	for (line_number, line) in text.lines().enumerate() {
+		let baseline = -(line_number as i32) * line_height;
+		let mut pen_x = 0;
+		for shaped in shape(font_file, line, DEFAULT_SCRIPT, None, Direction::LeftToRight, &features) {
+			if let Entry::Vacant(entry) = cache.entry(shaped.gid) {
+				entry.insert(font_file.rasterize_glyph(shaped.gid, ppem, fill_rule)?);
+			}
+			let origin_x = ((pen_x + shaped.x_offset) as f32 * scale).round() as i32;
+			let origin_y = ((baseline + shaped.y_offset) as f32 * scale).round() as i32;
+			placed.push((shaped.gid, origin_x, origin_y));
+			pen_x += shaped.x_advance;
+		}
+		widest_line = widest_line.max(pen_x);
+		line_count = line_number + 1;
+	}
+
+	let mut xmin = 0;
+	let mut xmax = (widest_line as f32 * scale).ceil() as i32;
+	let mut ymax = (ascender as f32 * scale).ceil() as i32;
+	let mut ymin = ((descender as i32 - (line_count as i32 - 1) * line_height) as f32 * scale).floor() as i32;
+	for &(gid, origin_x, origin_y) in &placed {
+		let glyph = &cache[&gid];
+		if glyph.pixels.is_empty() {
+			continue;
+		}
+		xmin = xmin.min(origin_x + glyph.left);
+		xmax = xmax.max(origin_x + glyph.left + glyph.width as i32);
+		ymax = ymax.max(origin_y + glyph.top);
+		ymin = ymin.min(origin_y + glyph.top - glyph.height as i32);
+	}
+
+	let width = (i64::from(xmax) - i64::from(xmin) + 2 * i64::from(padding)).max(1);
+	let height = (i64::from(ymax) - i64::from(ymin) + 2 * i64::from(padding)).max(1);
+	let Some(size) = bitmap_size(width, height) else {
+		return Err(FontError::ImageTooLarge { width, height });
+	};
+	// the canvas spans 0, so with its size in bounds none of these overflow
+	let padding = padding as i32;
+	let left = xmin - padding;
+	let top = ymax + padding;
+	let (width, height) = (width as u32, height as u32);
+	let mut pixels: Vec<u8> = vec![0; size];
+	// overlapping glyphs cover a pixel as much as the most covering one, like the shader's union of quads
+	for &(gid, origin_x, origin_y) in &placed {
+		let glyph = &cache[&gid];
+		let column = (origin_x + glyph.left - left) as usize;
+		let first_row = (top - origin_y - glyph.top) as usize;
+		for row in 0..glyph.height as usize {
+			let source = &glyph.pixels[row * glyph.width as usize..(row + 1) * glyph.width as usize];
+			let start = (first_row + row) * width as usize + column;
+			for (pixel, &alpha) in pixels[start..start + glyph.width as usize].iter_mut().zip(source) {
+				*pixel = (*pixel).max(alpha);
+			}
+		}
+	}
+
+	Ok(Bitmap {
+		width,
+		height,
+		left,
+		top,
+		pixels,
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Arc;
+
+	use super::*;
+	use crate::sfnt_builder::*;
+
+	fn empty_font() -> FontFile {
+		let mut font_file = FontFile::from_buffer(Arc::new(minimal_font(&[vec![]], false, cmap_format_4(&[]))), 0).unwrap();
+		font_file.get_glyphs().unwrap();
+		font_file
+	}
+
+	#[test]
+	fn padding_surrounds_the_lines() {
+		let bitmap = render_text(&empty_font(), "", 16.0, FillRule::NonZero, 3).unwrap();
+		assert_eq!((bitmap.left, bitmap.width), (-3, 6));
+		assert!(bitmap.pixels.iter().all(|&pixel| pixel == 0));
+	}
+
+	#[test]
+	fn crlf_is_one_line_break() {
+		let font_file = empty_font();
+		let height = |text: &str| render_text(&font_file, text, 16.0, FillRule::NonZero, 0).unwrap().height;
+		assert_eq!(height("a\r\nb"), height("a\nb"));
+		assert!(height("a\nb") > height("a"));
+	}
+
+	#[test]
+	fn oversized_padding_is_an_error() {
+		let result = render_text(&empty_font(), "", 16.0, FillRule::NonZero, u32::MAX);
+		assert!(matches!(result, Err(FontError::ImageTooLarge { .. })));
+	}
+}
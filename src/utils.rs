@@ -1,24 +1,46 @@
 use std::fs::File;
 use std::io::Read;
 use std::path::PathBuf;
-use std::str::{self, FromStr};
 use glow::*;
+use crate::cff::CUBIC_CONTROL_FLAG;
+use crate::font_error::FontError;
+use crate::reader::Reader;
 use crate::font_loader::{GlyphDescription, Point, TableDirectory};
 
-pub fn get_u32(buffer: &Vec<u8>, offset: usize) -> u32 {
-	u32::from_be_bytes(buffer[offset..offset + 4].try_into().unwrap())
+pub fn bit_set(byte: u8, bit: u8) -> bool {
+	((byte >> bit) & 1) != 0
 }
 
-pub fn get_u16(buffer: &Vec<u8>, offset: usize) -> u16 {
-	u16::from_be_bytes(buffer[offset..offset + 2].try_into().unwrap())
-}
+// upper half of the mac roman encoding, the lower half matches ascii
+const MAC_ROMAN: [char; 128] = [
+	'\u{00C4}', '\u{00C5}', '\u{00C7}', '\u{00C9}', '\u{00D1}', '\u{00D6}', '\u{00DC}', '\u{00E1}',
+	'\u{00E0}', '\u{00E2}', '\u{00E4}', '\u{00E3}', '\u{00E5}', '\u{00E7}', '\u{00E9}', '\u{00E8}',
+	'\u{00EA}', '\u{00EB}', '\u{00ED}', '\u{00EC}', '\u{00EE}', '\u{00EF}', '\u{00F1}', '\u{00F3}',
+	'\u{00F2}', '\u{00F4}', '\u{00F6}', '\u{00F5}', '\u{00FA}', '\u{00F9}', '\u{00FB}', '\u{00FC}',
+	'\u{2020}', '\u{00B0}', '\u{00A2}', '\u{00A3}', '\u{00A7}', '\u{2022}', '\u{00B6}', '\u{00DF}',
+	'\u{00AE}', '\u{00A9}', '\u{2122}', '\u{00B4}', '\u{00A8}', '\u{2260}', '\u{00C6}', '\u{00D8}',
+	'\u{221E}', '\u{00B1}', '\u{2264}', '\u{2265}', '\u{00A5}', '\u{00B5}', '\u{2202}', '\u{2211}',
+	'\u{220F}', '\u{03C0}', '\u{222B}', '\u{00AA}', '\u{00BA}', '\u{03A9}', '\u{00E6}', '\u{00F8}',
+	'\u{00BF}', '\u{00A1}', '\u{00AC}', '\u{221A}', '\u{0192}', '\u{2248}', '\u{2206}', '\u{00AB}',
+	'\u{00BB}', '\u{2026}', '\u{00A0}', '\u{00C0}', '\u{00C3}', '\u{00D5}', '\u{0152}', '\u{0153}',
+	'\u{2013}', '\u{2014}', '\u{201C}', '\u{201D}', '\u{2018}', '\u{2019}', '\u{00F7}', '\u{25CA}',
+	'\u{00FF}', '\u{0178}', '\u{2044}', '\u{20AC}', '\u{2039}', '\u{203A}', '\u{FB01}', '\u{FB02}',
+	'\u{2021}', '\u{00B7}', '\u{201A}', '\u{201E}', '\u{2030}', '\u{00C2}', '\u{00CA}', '\u{00C1}',
+	'\u{00CB}', '\u{00C8}', '\u{00CD}', '\u{00CE}', '\u{00CF}', '\u{00CC}', '\u{00D3}', '\u{00D4}',
+	'\u{F8FF}', '\u{00D2}', '\u{00DA}', '\u{00DB}', '\u{00D9}', '\u{0131}', '\u{02C6}', '\u{02DC}',
+	'\u{00AF}', '\u{02D8}', '\u{02D9}', '\u{02DA}', '\u{00B8}', '\u{02DD}', '\u{02DB}', '\u{02C7}',
+];
 
-pub fn get_i16(buffer: &Vec<u8>, offset: usize) -> i16 {
-	i16::from_be_bytes(buffer[offset..offset + 2].try_into().unwrap())
+pub fn mac_roman_to_char(byte: u8) -> char {
+	if byte < 128 {
+		byte as char
+	} else {
+		MAC_ROMAN[byte as usize - 128]
+	}
 }
 
-pub fn bit_set(byte: u8, bit: u8) -> bool {
-	((byte >> bit) & 1) != 0
+pub fn is_variation_selector(character: char) -> bool {
+	matches!(character, '\u{FE00}'..='\u{FE0F}' | '\u{E0100}'..='\u{E01EF}')
 }
 
 pub fn get_global_path(relative_path: &str) -> PathBuf {
@@ -32,39 +54,86 @@ pub fn get_global_path(relative_path: &str) -> PathBuf {
             .to_path_buf()
     };
     
-    return base_dir.join(relative_path)
+    base_dir.join(relative_path)
 }
 
-pub fn file_bytes(file_path: &str) -> Vec<u8> {
+pub fn file_bytes(file_path: &str) -> Result<Vec<u8>, FontError> {
 	let global_file_path = get_global_path(file_path);
-	let mut file = File::open(global_file_path.to_str().unwrap()).unwrap();
+	let mut file = File::open(global_file_path)?;
 	let mut buffer = Vec::new();
-	file.read_to_end(&mut buffer).unwrap();
-	buffer
+	file.read_to_end(&mut buffer)?;
+	Ok(buffer)
 }
 
-pub fn get_num_tables(buffer: &Vec<u8>) -> u16 {
-	let num_tables = get_u16(&buffer, 4);
-	num_tables
+pub fn get_num_tables(buffer: &[u8]) -> Result<u16, FontError> {
+	Reader::new(buffer).at(4)?.read_u16()
 }
 
-pub fn get_table_details(buffer: &Vec<u8>) -> Vec<TableDirectory>{
-	let num_tables = get_num_tables(&buffer);
+// offsets of every face's table directory, a plain sfnt is a collection of one at offset 0
+pub fn get_face_offsets(buffer: &[u8]) -> Result<Vec<usize>, FontError> {
+	let mut reader = Reader::new(buffer);
+	if &reader.read_tag()? != b"ttcf" {
+		return Ok(vec![0]);
+	}
+	// version 2 headers add DSIG fields after the offsets, nothing here needs them
+	reader.skip(4)?; //skip majorVersion, minorVersion
+	let num_fonts = reader.read_u32()?;
+	let mut face_offsets: Vec<usize> = vec![];
+	for _ in 0..num_fonts {
+		face_offsets.push(reader.read_offset32()?);
+	}
+	Ok(face_offsets)
+}
+
+pub fn get_table_details(buffer: &[u8], face_offset: usize) -> Result<Vec<TableDirectory>, FontError> {
+	let mut reader = Reader::new(buffer).at(face_offset)?;
+	// truetype outlines (0x00010000 or 'true') and cff outlines ('OTTO')
+	let sfnt_version = reader.read_u32()?;
+	if sfnt_version != 0x00010000 && &sfnt_version.to_be_bytes() != b"true" && &sfnt_version.to_be_bytes() != b"OTTO" {
+		return Err(FontError::BadMagic(sfnt_version));
+	}
+
+	let num_tables = reader.read_u16()?;
+	reader.skip(6)?; //skip searchRange, entrySelector, rangeShift
 	let mut tables: Vec<TableDirectory> = vec![];
 
-	for i in 0..num_tables{
-		let tag_bytes = str::from_utf8(&buffer[(12 + i * 16) as usize..(16 + i * 16) as usize]).unwrap();
-		let offset_bytes = get_u32(buffer, (20 + i * 16) as usize);
-		let length_bytes = get_u32(buffer, (24 + i * 16) as usize);
+	for _ in 0..num_tables as usize{
+		let tag_bytes = reader.read_tag()?;
+		reader.skip(4)?; //skip checksum
+		let offset_bytes = reader.read_u32()?;
+		let length_bytes = reader.read_u32()?;
 
 		let table= TableDirectory{
-			tag: String::from_str(tag_bytes).unwrap(),
+			tag: String::from_utf8_lossy(&tag_bytes).into_owned(),
 			offset: offset_bytes,
 			length: length_bytes
 		};
 		tables.push(table);
 	}
-	tables
+	Ok(tables)
+}
+
+// rounded and kept in range, for applying variation deltas to unsigned metrics
+pub fn add_delta(value: u16, delta: f32) -> u16 {
+	(value as f32 + delta).round().clamp(0.0, u16::MAX as f32) as u16
+}
+
+// the header bounds only hold for the default instance, varied glyphs take them from their points
+pub fn update_bounds(glyph: &mut GlyphDescription) {
+	if glyph.coordinates.is_empty() {
+		return;
+	}
+	let (mut xmin, mut ymin, mut xmax, mut ymax) = (f32::MAX, f32::MAX, f32::MIN, f32::MIN);
+	for point in &glyph.coordinates {
+		xmin = xmin.min(point.x);
+		ymin = ymin.min(point.y);
+		xmax = xmax.max(point.x);
+		ymax = ymax.max(point.y);
+	}
+	glyph.xmin = xmin.floor() as i16;
+	glyph.ymin = ymin.floor() as i16;
+	glyph.xmax = xmax.ceil() as i16;
+	glyph.ymax = ymax.ceil() as i16;
 }
 
 pub fn get_indices(glyph: &GlyphDescription) -> Vec<Vec<u32>> {
@@ -80,13 +149,13 @@ pub fn get_indices(glyph: &GlyphDescription) -> Vec<Vec<u32>> {
 
 pub fn scale_points(glyph: &GlyphDescription, size: (u32, u32), offset_x: i32, offset_y: i32) -> Vec<(f32, f32)> {
 	let points = &glyph.coordinates;
-	let xmax = glyph.xmax as i16;
-	let ymax = glyph.ymax as i16;
+	let xmax = glyph.xmax;
+	let ymax = glyph.ymax;
 
 	let scale_factor_x = xmax as f32 / (size.0 * 10) as f32;
 	let scale_factor_y = ymax as f32 / (size.1 * 10) as f32;
 	let scaled_points: Vec<(f32, f32)> = points.iter().map(|point| {
-		(((point.x + offset_x as f32) / xmax as f32) * scale_factor_x as f32 - 1.0, ((point.y + offset_y as f32) / ymax as f32) * scale_factor_y as f32)
+		(((point.x + offset_x as f32) / xmax as f32) * scale_factor_x - 1.0, ((point.y + offset_y as f32) / ymax as f32) * scale_factor_y)
 	}).collect();
 	
 	scaled_points
@@ -104,7 +173,7 @@ pub fn create_outline_vbo(gl: &Context, points: Vec<(f32, f32)>) -> NativeBuffer
     }
 }
 
-pub fn create_outline_ebos(gl: &Context, indices: &Vec<Vec<u32>>) -> Vec<NativeBuffer> {
+pub fn create_outline_ebos(gl: &Context, indices: &[Vec<u32>]) -> Vec<NativeBuffer> {
 
     indices.iter().map(|loop_indices| {
         unsafe {
@@ -153,7 +222,7 @@ pub fn calculate_beziers(start_point: Point, middle_point: Point, end_point: Poi
 pub fn create_text_quads_vao(
     gl: &Context,
     position: (u32, u32),
-    offsets: &Vec<(u32, u32)>,
+    offsets: &[(i32, i32)],
     size: (i16, i16),
     viewport_size: (u32, u32),
     scale: f32
@@ -162,8 +231,8 @@ pub fn create_text_quads_vao(
         return None;
     }
     
-    let normalize_x = |x: u32| (2.0 * x as f32 / viewport_size.0 as f32) - 1.0;
-    let normalize_y = |y: u32| 1.0 - (2.0 * y as f32 / viewport_size.1 as f32);
+    let normalize_x = |x: i32| (2.0 * x as f32 / viewport_size.0 as f32) - 1.0;
+    let normalize_y = |y: i32| 1.0 - (2.0 * y as f32 / viewport_size.1 as f32);
     
     let scaled_width = (size.0 as f32 * scale) as u32;
     let scaled_height = (size.1 as f32 * scale) as u32;
@@ -178,18 +247,18 @@ pub fn create_text_quads_vao(
         scaled_width as f32, 0.0,
     ];
     
-    let instance_data = offsets.iter().map(|&(x, y)| {
-        let scaled_offset_x = (x as f32 * scale) as u32;
-        let scaled_offset_y = (y as f32 * scale) as u32;
+    let instance_data = offsets.iter().flat_map(|&(x, y)| {
+        let scaled_offset_x = (x as f32 * scale) as i32;
+        let scaled_offset_y = (y as f32 * scale) as i32;
         
-        let x_pos = position.0 + scaled_offset_x;
-        let y_pos = position.1 + scaled_offset_y;
+        let x_pos = position.0 as i32 + scaled_offset_x;
+        let y_pos = position.1 as i32 + scaled_offset_y;
         
         let x_normalized = normalize_x(x_pos);
         let y_normalized = normalize_y(y_pos);
         
         [x_normalized, y_normalized]
-    }).flatten().collect::<Vec<f32>>();
+    }).collect::<Vec<f32>>();
     
     unsafe {
         let vbo = gl.create_buffer().unwrap();
@@ -243,6 +312,37 @@ pub fn create_text_quads_vao(
     }
 }
 
+// segment kinds written next to each point in the point ssbo, must match fragmentshader.frag
+pub const SEGMENT_NONE: i32 = 0;
+pub const SEGMENT_LINE: i32 = 1;
+pub const SEGMENT_QUADRATIC: i32 = 2;
+pub const SEGMENT_CUBIC: i32 = 3;
+
+// every on-curve point starts the segment that runs to the next on-curve point,
+// the kind comes from the control points in between
+pub fn get_segment_kinds(glyph: &GlyphDescription) -> Vec<i32> {
+	let mut segment_kinds: Vec<i32> = vec![SEGMENT_NONE; glyph.coordinates.len()];
+	let mut start = 0;
+	for &end in &glyph.end_pts_of_contours {
+		let end = end as usize;
+		for (offset, segment_kind) in segment_kinds[start..=end].iter_mut().enumerate() {
+			let i = start + offset;
+			let next_point = glyph.coordinates[if i == end {start} else {i + 1}];
+			*segment_kind = if !bit_set(glyph.coordinates[i].flags, 0) {
+				SEGMENT_NONE
+			} else if bit_set(next_point.flags, 0) {
+				SEGMENT_LINE
+			} else if next_point.flags & CUBIC_CONTROL_FLAG != 0 {
+				SEGMENT_CUBIC
+			} else {
+				SEGMENT_QUADRATIC
+			};
+		}
+		start = end + 1;
+	}
+	segment_kinds
+}
+
 pub fn create_ssbo(gl: &Context, glyphs: &Vec<GlyphDescription>) -> (NativeBuffer, NativeBuffer, NativeBuffer) {
 	let mut glyph_data = vec![];
 	let mut point_data = vec![];
@@ -261,10 +361,10 @@ pub fn create_ssbo(gl: &Context, glyphs: &Vec<GlyphDescription>) -> (NativeBuffe
 	    glyph_data.push(point_offset);
 	    glyph_data.push(contour_offset);
 
-	    for point in &glyph.coordinates {
+	    for (point, segment_kind) in glyph.coordinates.iter().zip(get_segment_kinds(glyph)) {
 	        point_data.push(point.x as i32);
 	        point_data.push(point.y as i32);
-	        point_data.push(point.flags as i32);
+	        point_data.push(segment_kind);
 	    }
 	    point_offset += glyph.coordinates.len() as i32;
 
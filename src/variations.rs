@@ -0,0 +1,705 @@
+use std::collections::HashMap;
+
+use crate::font_error::FontError;
+use crate::reader::{Reader, Tag};
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RegionAxisCoordinates {
+	pub start_coord: f32,
+	pub peak_coord: f32,
+	pub end_coord: f32,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ItemVariationStore {
+	pub regions: Vec<Vec<RegionAxisCoordinates>>,
+	pub region_indexes: Vec<Vec<u16>>,
+	// [outer][inner][region]
+	pub delta_sets: Vec<Vec<Vec<i32>>>,
+}
+
+impl ItemVariationStore {
+	pub fn parse(store: Reader) -> Result<ItemVariationStore, FontError> {
+		let mut reader = store;
+		let format = reader.read_u16()?;
+		if format != 1 {
+			return Err(FontError::UnsupportedFormat { table: "ItemVariationStore", format: format as u32 });
+		}
+		let region_list_offset = reader.read_offset32()?;
+		let item_variation_data_count = reader.read_u16()?;
+
+		let mut region_list = store.at(region_list_offset)?;
+		let axis_count = region_list.read_u16()?;
+		let region_count = region_list.read_u16()?;
+		let mut regions: Vec<Vec<RegionAxisCoordinates>> = vec![];
+		for _ in 0..region_count {
+			let mut axes: Vec<RegionAxisCoordinates> = vec![];
+			for _ in 0..axis_count {
+				axes.push(RegionAxisCoordinates {
+					start_coord: region_list.read_f2dot14()?,
+					peak_coord: region_list.read_f2dot14()?,
+					end_coord: region_list.read_f2dot14()?,
+				});
+			}
+			regions.push(axes);
+		}
+
+		let mut region_indexes: Vec<Vec<u16>> = vec![];
+		let mut delta_sets: Vec<Vec<Vec<i32>>> = vec![];
+		for _ in 0..item_variation_data_count {
+			let mut item_variation_data = store.at(reader.read_offset32()?)?;
+			let item_count = item_variation_data.read_u16()?;
+			let word_delta_count = item_variation_data.read_u16()?;
+			let region_index_count = item_variation_data.read_u16()?;
+			let mut indexes: Vec<u16> = vec![];
+			for _ in 0..region_index_count {
+				let region_index = item_variation_data.read_u16()?;
+				if region_index >= region_count {
+					return Err(FontError::Malformed("variation data refers to a missing region"));
+				}
+				indexes.push(region_index);
+			}
+			region_indexes.push(indexes);
+
+			// the first word count deltas of each row are the wide ones, 32 bit instead of 16 when LONG_WORDS is set
+			let long_words = word_delta_count & 0x8000 != 0;
+			let word_count = (word_delta_count & 0x7FFF).min(region_index_count);
+			let mut rows: Vec<Vec<i32>> = vec![];
+			for _ in 0..item_count {
+				let mut row: Vec<i32> = vec![];
+				for region in 0..region_index_count {
+					row.push(match (region < word_count, long_words) {
+						(true, true) => item_variation_data.read_i32()?,
+						(true, false) | (false, true) => item_variation_data.read_i16()? as i32,
+						(false, false) => item_variation_data.read_i8()? as i32,
+					});
+				}
+				rows.push(row);
+			}
+			delta_sets.push(rows);
+		}
+
+		Ok(ItemVariationStore {
+			regions,
+			region_indexes,
+			delta_sets,
+		})
+	}
+
+	// how much each region of one item variation data applies at the given normalized coordinates
+	pub fn region_scalars(&self, outer_index: usize, coordinates: &[f32]) -> Result<Vec<f32>, FontError> {
+		let indexes = self.region_indexes.get(outer_index).ok_or(FontError::Malformed("missing item variation data"))?;
+		Ok(indexes.iter().map(|&index| region_scalar(&self.regions[index as usize], coordinates)).collect())
+	}
+
+	// the interpolated adjustment for one delta set, 0 at the default instance
+	pub fn get_delta(&self, outer_index: u16, inner_index: u16, coordinates: &[f32]) -> f32 {
+		let Some(deltas) = self.delta_sets.get(outer_index as usize).and_then(|rows| rows.get(inner_index as usize)) else {
+			return 0.0;
+		};
+		let Ok(scalars) = self.region_scalars(outer_index as usize, coordinates) else {
+			return 0.0;
+		};
+		deltas.iter().zip(scalars).map(|(&delta, scalar)| delta as f32 * scalar).sum()
+	}
+}
+
+pub fn region_scalar(region: &[RegionAxisCoordinates], coordinates: &[f32]) -> f32 {
+	let mut scalar = 1.0;
+	for (i, axis) in region.iter().enumerate() {
+		let (start, peak, end) = (axis.start_coord, axis.peak_coord, axis.end_coord);
+		// invalid or axis-neutral ranges don't limit the region
+		if start > peak || peak > end || (start < 0.0 && end > 0.0) || peak == 0.0 {
+			continue;
+		}
+		let coordinate = coordinates.get(i).copied().unwrap_or(0.0);
+		if coordinate == peak {
+			continue;
+		}
+		if coordinate <= start || coordinate >= end {
+			return 0.0;
+		}
+		scalar *= if coordinate < peak {(coordinate - start) / (peak - start)} else {(end - coordinate) / (end - peak)};
+	}
+	scalar
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VariationAxis {
+	pub tag: Tag,
+	pub min_value: f32,
+	pub default_value: f32,
+	pub max_value: f32,
+	pub flags: u16,
+	pub name_id: u16,
+}
+
+impl VariationAxis {
+	// user coordinates to [-1, 1] with the default at 0, before avar
+	// rounded to F2Dot14 like the coordinates stored in the font
+	pub fn normalize(&self, value: f32) -> f32 {
+		let value = value.clamp(self.min_value, self.max_value);
+		let normalized = if value < self.default_value {
+			(value - self.default_value) / (self.default_value - self.min_value)
+		} else if value > self.default_value {
+			(value - self.default_value) / (self.max_value - self.default_value)
+		} else {
+			0.0
+		};
+		(normalized * 16384.0).round() / 16384.0
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NamedInstance {
+	pub subfamily_name_id: u16,
+	pub flags: u16,
+	pub coordinates: Vec<f32>,
+	pub postscript_name_id: Option<u16>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Fvar {
+	pub axes: Vec<VariationAxis>,
+	pub instances: Vec<NamedInstance>,
+}
+
+impl Fvar {
+	pub fn parse(fvar: Reader) -> Result<Fvar, FontError> {
+		let mut reader = fvar;
+		reader.skip(4)?; //skip version
+		let axes_array_offset = reader.read_offset16()?;
+		reader.skip(2)?; //skip reserved
+		let axis_count = reader.read_u16()?;
+		let axis_size = reader.read_u16()? as usize;
+		let instance_count = reader.read_u16()?;
+		let instance_size = reader.read_u16()? as usize;
+		if axis_size < 20 {
+			return Err(FontError::Malformed("fvar axis records are too small"));
+		}
+
+		// ranges that leave out the default, inverted ones included, are widened to reach it like harfbuzz does
+		let mut axes: Vec<VariationAxis> = vec![];
+		for i in 0..axis_count as usize {
+			let mut axis = fvar.at(axes_array_offset + i * axis_size)?;
+			let tag = axis.read_tag()?;
+			let (min_value, default_value, max_value) = (axis.read_fixed()?, axis.read_fixed()?, axis.read_fixed()?);
+			axes.push(VariationAxis {
+				tag,
+				min_value: 		min_value.min(default_value),
+				default_value,
+				max_value: 		max_value.max(default_value),
+				flags: 			axis.read_u16()?,
+				name_id: 		axis.read_u16()?,
+			});
+		}
+
+		// instances follow the axes, the postscript name id is only there when the record has room for it
+		let instances_offset = axes_array_offset + axis_count as usize * axis_size;
+		let mut instances: Vec<NamedInstance> = vec![];
+		for i in 0..instance_count as usize {
+			let mut instance = fvar.at(instances_offset + i * instance_size)?;
+			let subfamily_name_id = instance.read_u16()?;
+			let flags = instance.read_u16()?;
+			let mut coordinates: Vec<f32> = vec![];
+			for _ in 0..axis_count {
+				coordinates.push(instance.read_fixed()?);
+			}
+			let postscript_name_id = if instance_size >= axis_count as usize * 4 + 6 {Some(instance.read_u16()?)} else {None};
+			instances.push(NamedInstance { subfamily_name_id, flags, coordinates, postscript_name_id });
+		}
+		Ok(Fvar { axes, instances })
+	}
+}
+
+// one piecewise linear map per axis as (from, to) pairs
+#[derive(Debug, Clone, Default)]
+pub struct Avar {
+	pub segment_maps: Vec<Vec<(f32, f32)>>,
+}
+
+impl Avar {
+	// version 2 adds a variation store after the segment maps, only the maps are used here
+	pub fn parse(avar: Reader) -> Result<Avar, FontError> {
+		let mut reader = avar;
+		reader.skip(6)?; //skip version and reserved
+		let axis_count = reader.read_u16()?;
+		let mut segment_maps: Vec<Vec<(f32, f32)>> = vec![];
+		for _ in 0..axis_count {
+			let position_map_count = reader.read_u16()?;
+			let mut segments: Vec<(f32, f32)> = vec![];
+			for _ in 0..position_map_count {
+				segments.push((reader.read_f2dot14()?, reader.read_f2dot14()?));
+			}
+			segment_maps.push(segments);
+		}
+		Ok(Avar { segment_maps })
+	}
+
+	pub fn map(&self, axis_index: usize, coordinate: f32) -> f32 {
+		let Some(segments) = self.segment_maps.get(axis_index) else {
+			return coordinate;
+		};
+		// a map without the required -1, 0 and 1 entries is ignored
+		if segments.len() < 3 {
+			return coordinate;
+		}
+		for pair in segments.windows(2) {
+			let ((from_start, to_start), (from_end, to_end)) = (pair[0], pair[1]);
+			if coordinate >= from_start && coordinate <= from_end {
+				if from_end == from_start {
+					return to_start;
+				}
+				let mapped = to_start + (coordinate - from_start) / (from_end - from_start) * (to_end - to_start);
+				return (mapped * 16384.0).round() / 16384.0;
+			}
+		}
+		coordinate
+	}
+}
+
+// how much a tuple variation applies, intermediate regions are (start, end) per axis
+pub fn tuple_scalar(peak: &[f32], intermediate: Option<&(Vec<f32>, Vec<f32>)>, coordinates: &[f32]) -> f32 {
+	let mut scalar = 1.0;
+	for (i, &peak) in peak.iter().enumerate() {
+		if peak == 0.0 {
+			continue;
+		}
+		let coordinate = coordinates.get(i).copied().unwrap_or(0.0);
+		if coordinate == peak {
+			continue;
+		}
+		if coordinate == 0.0 {
+			return 0.0;
+		}
+		match intermediate {
+			Some((start, end)) => {
+				let (start, end) = (start[i], end[i]);
+				if coordinate < start || coordinate > end {
+					return 0.0;
+				}
+				scalar *= if coordinate < peak {(coordinate - start) / (peak - start)} else {(end - coordinate) / (end - peak)};
+			}
+			None => {
+				if coordinate < peak.min(0.0) || coordinate > peak.max(0.0) {
+					return 0.0;
+				}
+				scalar *= coordinate / peak;
+			}
+		}
+	}
+	scalar
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Gvar {
+	pub axis_count: u16,
+	pub shared_tuples: Vec<Vec<f32>>,
+	// the table is kept so each glyph's variation data can be read once its point count is known
+	pub data: Vec<u8>,
+	pub glyph_ranges: Vec<(usize, usize)>,
+}
+
+impl Gvar {
+	pub fn parse(gvar: Reader) -> Result<Gvar, FontError> {
+		let mut reader = gvar;
+		reader.skip(4)?; //skip version
+		let axis_count = reader.read_u16()?;
+		let shared_tuple_count = reader.read_u16()?;
+		let mut shared_tuple_reader = gvar.at(reader.read_offset32()?)?;
+		let glyph_count = reader.read_u16()?;
+		let flags = reader.read_u16()?;
+		let data_array_offset = reader.read_offset32()?;
+
+		let mut shared_tuples: Vec<Vec<f32>> = vec![];
+		for _ in 0..shared_tuple_count {
+			let mut tuple: Vec<f32> = vec![];
+			for _ in 0..axis_count {
+				tuple.push(shared_tuple_reader.read_f2dot14()?);
+			}
+			shared_tuples.push(tuple);
+		}
+
+		// short offsets are stored divided by two
+		let mut offsets: Vec<usize> = vec![];
+		for _ in 0..=glyph_count {
+			let offset = if flags & 0x0001 != 0 {reader.read_offset32()?} else {reader.read_offset16()? * 2};
+			offsets.push(data_array_offset + offset);
+		}
+		let glyph_ranges = offsets.windows(2).map(|pair| (pair[0], pair[1].max(pair[0]))).collect();
+
+		Ok(Gvar {
+			axis_count,
+			shared_tuples,
+			data: gvar.data().to_vec(),
+			glyph_ranges,
+		})
+	}
+
+	// one (x, y) delta per point, contours are needed to infer the points a tuple leaves out
+	pub fn get_deltas(&self, glyph_index: u16, coordinates: &[f32], points: &[(f32, f32)], end_points: &[u16]) -> Result<Option<Vec<(f32, f32)>>, FontError> {
+		const SHARED_POINT_NUMBERS: u16 = 0x8000;
+		const EMBEDDED_PEAK_TUPLE: u16 = 0x8000;
+		const INTERMEDIATE_REGION: u16 = 0x4000;
+		const PRIVATE_POINT_NUMBERS: u16 = 0x2000;
+
+		let Some(&(start, end)) = self.glyph_ranges.get(glyph_index as usize) else {
+			return Ok(None);
+		};
+		if start == end || coordinates.iter().all(|&coordinate| coordinate == 0.0) {
+			return Ok(None);
+		}
+		let glyph_data = Reader::new(&self.data).sub_reader(start, end - start)?;
+		let mut reader = glyph_data;
+		let tuple_variation_count = reader.read_u16()?;
+		let mut serialized = glyph_data.at(reader.read_offset16()?)?;
+		let shared_points = if tuple_variation_count & SHARED_POINT_NUMBERS != 0 {read_packed_points(&mut serialized)?} else {None};
+
+		let mut deltas: Vec<(f32, f32)> = vec![(0.0, 0.0); points.len()];
+		for _ in 0..tuple_variation_count & 0x0FFF {
+			let variation_data_size = reader.read_u16()? as usize;
+			let tuple_index = reader.read_u16()?;
+			let peak = if tuple_index & EMBEDDED_PEAK_TUPLE != 0 {
+				read_tuple(&mut reader, self.axis_count)?
+			} else {
+				self.shared_tuples.get((tuple_index & 0x0FFF) as usize).cloned().ok_or(FontError::Malformed("gvar refers to a missing shared tuple"))?
+			};
+			let intermediate = if tuple_index & INTERMEDIATE_REGION != 0 {
+				Some((read_tuple(&mut reader, self.axis_count)?, read_tuple(&mut reader, self.axis_count)?))
+			} else {
+				None
+			};
+
+			let mut tuple_data = serialized.sub_reader(serialized.offset(), variation_data_size)?;
+			serialized.skip(variation_data_size)?;
+			let scalar = tuple_scalar(&peak, intermediate.as_ref(), coordinates);
+			if scalar == 0.0 {
+				continue;
+			}
+
+			let tuple_points = if tuple_index & PRIVATE_POINT_NUMBERS != 0 {read_packed_points(&mut tuple_data)?} else {shared_points.clone()};
+			// no point numbers means every point, including the four phantom ones
+			let point_count = tuple_points.as_ref().map_or(points.len(), |tuple_points| tuple_points.len());
+			let x_deltas = read_packed_deltas(&mut tuple_data, point_count)?;
+			let y_deltas = read_packed_deltas(&mut tuple_data, point_count)?;
+
+			match tuple_points {
+				None => {
+					for (delta, (&x, &y)) in deltas.iter_mut().zip(x_deltas.iter().zip(&y_deltas)) {
+						delta.0 += x as f32 * scalar;
+						delta.1 += y as f32 * scalar;
+					}
+				}
+				Some(tuple_points) => {
+					let mut touched: Vec<Option<(f32, f32)>> = vec![None; points.len()];
+					for (i, &point) in tuple_points.iter().enumerate() {
+						if let Some(slot) = touched.get_mut(point as usize) {
+							*slot = Some((x_deltas[i] as f32, y_deltas[i] as f32));
+						}
+					}
+					let inferred = interpolate_untouched(points, end_points, &touched);
+					for (delta, (x, y)) in deltas.iter_mut().zip(inferred) {
+						delta.0 += x * scalar;
+						delta.1 += y * scalar;
+					}
+				}
+			}
+		}
+		Ok(Some(deltas))
+	}
+}
+
+fn read_tuple(reader: &mut Reader, axis_count: u16) -> Result<Vec<f32>, FontError> {
+	let mut tuple: Vec<f32> = vec![];
+	for _ in 0..axis_count {
+		tuple.push(reader.read_f2dot14()?);
+	}
+	Ok(tuple)
+}
+
+// None when the count is 0, which stands for all points
+fn read_packed_points(reader: &mut Reader) -> Result<Option<Vec<u16>>, FontError> {
+	const POINTS_ARE_WORDS: u8 = 0x80;
+	let first = reader.read_u8()?;
+	if first == 0 {
+		return Ok(None);
+	}
+	let count = if first & 0x80 != 0 {((first as u16 & 0x7F) << 8) | reader.read_u8()? as u16} else {first as u16};
+	let mut points: Vec<u16> = vec![];
+	let mut point: u16 = 0;
+	while points.len() < count as usize {
+		let control = reader.read_u8()?;
+		let run_count = (control & 0x7F) as usize + 1;
+		for _ in 0..run_count.min(count as usize - points.len()) {
+			let difference = if control & POINTS_ARE_WORDS != 0 {reader.read_u16()?} else {reader.read_u8()? as u16};
+			point = point.wrapping_add(difference);
+			points.push(point);
+		}
+	}
+	Ok(Some(points))
+}
+
+fn read_packed_deltas(reader: &mut Reader, count: usize) -> Result<Vec<i32>, FontError> {
+	const DELTAS_ARE_ZERO: u8 = 0x80;
+	const DELTAS_ARE_WORDS: u8 = 0x40;
+	let mut deltas: Vec<i32> = vec![];
+	while deltas.len() < count {
+		let control = reader.read_u8()?;
+		let run_count = ((control & 0x3F) as usize + 1).min(count - deltas.len());
+		for _ in 0..run_count {
+			deltas.push(match (control & DELTAS_ARE_ZERO != 0, control & DELTAS_ARE_WORDS != 0) {
+				(true, true) => reader.read_i32()?,
+				(true, false) => 0,
+				(false, true) => reader.read_i16()? as i32,
+				(false, false) => reader.read_i8()? as i32,
+			});
+		}
+	}
+	Ok(deltas)
+}
+
+// IUP: points a tuple leaves out move with the touched points around them on the same contour
+fn interpolate_untouched(points: &[(f32, f32)], end_points: &[u16], touched: &[Option<(f32, f32)>]) -> Vec<(f32, f32)> {
+	let mut deltas: Vec<(f32, f32)> = touched.iter().map(|delta| delta.unwrap_or((0.0, 0.0))).collect();
+	let mut start = 0;
+	for &end in end_points {
+		let end = end as usize;
+		if end >= points.len() || end < start {
+			break;
+		}
+		let touched_indices: Vec<usize> = (start..=end).filter(|&i| touched[i].is_some()).collect();
+		if touched_indices.len() == 1 {
+			let delta = deltas[touched_indices[0]];
+			deltas[start..=end].fill(delta);
+		} else if touched_indices.len() > 1 {
+			for (i, &previous) in touched_indices.iter().enumerate() {
+				let next = touched_indices[(i + 1) % touched_indices.len()];
+				// walk from one touched point to the next, wrapping around the end of the contour
+				let mut point = if previous == end {start} else {previous + 1};
+				while point != next {
+					deltas[point] = (
+						interpolate(points[point].0, points[previous].0, points[next].0, deltas[previous].0, deltas[next].0),
+						interpolate(points[point].1, points[previous].1, points[next].1, deltas[previous].1, deltas[next].1),
+					);
+					point = if point == end {start} else {point + 1};
+				}
+			}
+		}
+		start = end + 1;
+	}
+	deltas
+}
+
+fn interpolate(point: f32, first: f32, second: f32, first_delta: f32, second_delta: f32) -> f32 {
+	if first == second {
+		return if first_delta == second_delta {first_delta} else {0.0};
+	}
+	let ((low, low_delta), (high, high_delta)) = if first < second {((first, first_delta), (second, second_delta))} else {((second, second_delta), (first, first_delta))};
+	if point <= low {
+		low_delta
+	} else if point >= high {
+		high_delta
+	} else {
+		low_delta + (point - low) / (high - low) * (high_delta - low_delta)
+	}
+}
+
+// maps glyph ids or other indices to (outer, inner) delta set indices
+#[derive(Debug, Clone, Default)]
+pub struct DeltaSetIndexMap {
+	pub entries: Vec<(u16, u16)>,
+}
+
+impl DeltaSetIndexMap {
+	pub fn parse(map: Reader) -> Result<DeltaSetIndexMap, FontError> {
+		let mut reader = map;
+		let format = reader.read_u8()?;
+		let entry_format = reader.read_u8()?;
+		let map_count = match format {
+			0 => reader.read_u16()? as u32,
+			1 => reader.read_u32()?,
+			_ => return Err(FontError::UnsupportedFormat { table: "DeltaSetIndexMap", format: format as u32 }),
+		};
+		let entry_size = ((entry_format & 0x30) >> 4) as usize + 1;
+		let inner_bit_count = (entry_format & 0x0F) as u32 + 1;
+		let mut entries: Vec<(u16, u16)> = vec![];
+		for _ in 0..map_count {
+			let mut entry: u32 = 0;
+			for &byte in reader.read_bytes(entry_size)? {
+				entry = (entry << 8) | byte as u32;
+			}
+			entries.push(((entry >> inner_bit_count) as u16, (entry & ((1 << inner_bit_count) - 1)) as u16));
+		}
+		Ok(DeltaSetIndexMap { entries })
+	}
+
+	// indices past the end use the last entry
+	pub fn get(&self, index: u32) -> Option<(u16, u16)> {
+		self.entries.get(index as usize).or(self.entries.last()).copied()
+	}
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Hvar {
+	pub store: ItemVariationStore,
+	pub advance_width_mapping: Option<DeltaSetIndexMap>,
+	pub lsb_mapping: Option<DeltaSetIndexMap>,
+	pub rsb_mapping: Option<DeltaSetIndexMap>,
+}
+
+impl Hvar {
+	pub fn parse(hvar: Reader) -> Result<Hvar, FontError> {
+		let mut reader = hvar;
+		reader.skip(4)?; //skip version
+		let store = ItemVariationStore::parse(hvar.slice_from(reader.read_offset32()?)?)?;
+		let mut read_mapping = || -> Result<Option<DeltaSetIndexMap>, FontError> {
+			match reader.read_offset32()? {
+				0 => Ok(None),
+				offset => Ok(Some(DeltaSetIndexMap::parse(hvar.slice_from(offset)?)?)),
+			}
+		};
+		Ok(Hvar {
+			advance_width_mapping: read_mapping()?,
+			lsb_mapping: read_mapping()?,
+			rsb_mapping: read_mapping()?,
+			store,
+		})
+	}
+
+	// without a mapping the glyph id is the inner index of the first delta set
+	pub fn get_advance_delta(&self, glyph_index: u16, coordinates: &[f32]) -> f32 {
+		let (outer, inner) = match &self.advance_width_mapping {
+			Some(mapping) => mapping.get(glyph_index as u32).unwrap_or((0, glyph_index)),
+			None => (0, glyph_index),
+		};
+		self.store.get_delta(outer, inner, coordinates)
+	}
+}
+
+// font wide metrics like 'hasc' or 'undo', each pointing at a delta set
+#[derive(Debug, Clone, Default)]
+pub struct Mvar {
+	pub store: ItemVariationStore,
+	pub records: HashMap<Tag, (u16, u16)>,
+}
+
+impl Mvar {
+	pub fn parse(mvar: Reader) -> Result<Mvar, FontError> {
+		let mut reader = mvar;
+		reader.skip(6)?; //skip version and reserved
+		let value_record_size = reader.read_u16()? as usize;
+		let value_record_count = reader.read_u16()?;
+		let store_offset = reader.read_offset16()?;
+		// a table without records may leave the store out
+		if value_record_count == 0 || store_offset == 0 {
+			return Ok(Mvar::default());
+		}
+		let store = ItemVariationStore::parse(mvar.slice_from(store_offset)?)?;
+		let mut records: HashMap<Tag, (u16, u16)> = HashMap::new();
+		for i in 0..value_record_count as usize {
+			let mut record = mvar.at(reader.offset() + i * value_record_size)?;
+			records.insert(record.read_tag()?, (record.read_u16()?, record.read_u16()?));
+		}
+		Ok(Mvar { store, records })
+	}
+
+	pub fn get_delta(&self, tag: Tag, coordinates: &[f32]) -> f32 {
+		self.records.get(&tag).map_or(0.0, |&(outer, inner)| self.store.get_delta(outer, inner, coordinates))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn fixed(value: f32) -> [u8; 4] {
+		((value * 65536.0) as i32).to_be_bytes()
+	}
+
+	// one wght axis and no instances
+	fn fvar(min_value: f32, default_value: f32, max_value: f32) -> Vec<u8> {
+		let mut fvar: Vec<u8> = vec![];
+		fvar.extend(0x00010000u32.to_be_bytes());
+		for value in [16u16, 2, 1, 20, 0, 8] {
+			fvar.extend(value.to_be_bytes());
+		}
+		fvar.extend(b"wght");
+		for value in [min_value, default_value, max_value] {
+			fvar.extend(fixed(value));
+		}
+		fvar.extend([0, 0, 1, 0]);
+		fvar
+	}
+
+	#[test]
+	fn normalize_around_the_default() {
+		let fvar = Fvar::parse(Reader::new(&fvar(100.0, 400.0, 900.0))).unwrap();
+		let axis = fvar.axes[0];
+		assert_eq!([50.0, 250.0, 400.0, 650.0, 2000.0].map(|value| axis.normalize(value)), [-1.0, -0.5, 0.0, 0.5, 1.0]);
+	}
+
+	#[test]
+	fn inverted_axis_range_is_widened_to_the_default() {
+		let fvar = Fvar::parse(Reader::new(&fvar(900.0, 400.0, 100.0))).unwrap();
+		let axis = fvar.axes[0];
+		assert_eq!((axis.min_value, axis.default_value, axis.max_value), (400.0, 400.0, 400.0));
+		assert_eq!(axis.normalize(700.0), 0.0);
+	}
+
+	#[test]
+	fn avar_maps_between_segments() {
+		let avar = Avar { segment_maps: vec![vec![(-1.0, -1.0), (0.0, 0.0), (0.5, 0.75), (1.0, 1.0)], vec![(0.0, 0.0), (1.0, 0.5)]] };
+		assert_eq!([-0.5, 0.25, 0.75, 1.0].map(|coordinate| avar.map(0, coordinate)), [-0.5, 0.375, 0.875, 1.0]);
+		// without -1, 0 and 1 and past the last axis the coordinate is left alone
+		assert_eq!(avar.map(1, 0.5), 0.5);
+		assert_eq!(avar.map(2, 0.5), 0.5);
+	}
+
+	#[test]
+	fn packed_point_runs() {
+		// three points in a byte run and a word run, each a difference from the one before
+		let data = [3, 0x00, 5, 0x81, 0x01, 0x00, 0x00, 0x02];
+		assert_eq!(read_packed_points(&mut Reader::new(&data)).unwrap(), Some(vec![5, 261, 263]));
+		// a two byte count
+		let data = [0x80, 0x02, 0x01, 1, 1];
+		assert_eq!(read_packed_points(&mut Reader::new(&data)).unwrap(), Some(vec![1, 2]));
+		assert_eq!(read_packed_points(&mut Reader::new(&[0])).unwrap(), None);
+	}
+
+	#[test]
+	fn packed_delta_runs() {
+		// bytes, a zero run, words and 32 bit longs
+		let data = [0x01, 0xFF, 0x05, 0x81, 0x40, 0x01, 0x00, 0xC0, 0x00, 0x01, 0x00, 0x00];
+		assert_eq!(read_packed_deltas(&mut Reader::new(&data), 6).unwrap(), vec![-1, 5, 0, 0, 256, 65536]);
+	}
+
+	#[test]
+	fn untouched_points_follow_their_neighbours() {
+		let points = [(0.0, 0.0), (50.0, 0.0), (100.0, 0.0), (150.0, 0.0), (0.0, 0.0), (10.0, 10.0)];
+		let touched = [Some((10.0, 0.0)), None, Some((20.0, 4.0)), None, Some((3.0, 3.0)), None];
+		// between the touched points, past them on the wrap around, and one touched point moving its whole contour
+		// the first contour is flat, so its touched points' different y deltas give the others none
+		let deltas = interpolate_untouched(&points, &[3, 5], &touched);
+		assert_eq!(deltas, vec![(10.0, 0.0), (15.0, 0.0), (20.0, 4.0), (20.0, 0.0), (3.0, 3.0), (3.0, 3.0)]);
+	}
+
+	#[test]
+	fn shared_tuple_with_private_points() {
+		// one tuple at shared peak wght 1, points 0 and 2 move right by 10 and 20, point 1 is inferred
+		let mut data: Vec<u8> = vec![];
+		for value in [1u16, 8, 8, 0x2000] {
+			data.extend(value.to_be_bytes());
+		}
+		data.extend([2, 0x01, 0, 2]);
+		data.extend([0x01, 10, 20, 0x81]);
+		let gvar = Gvar { axis_count: 1, shared_tuples: vec![vec![1.0]], glyph_ranges: vec![(0, data.len())], data };
+		let points = [(0.0, 0.0), (50.0, 0.0), (100.0, 0.0)];
+		let deltas = gvar.get_deltas(0, &[0.5], &points, &[2]).unwrap().unwrap();
+		assert_eq!(deltas, vec![(5.0, 0.0), (7.5, 0.0), (10.0, 0.0)]);
+		assert_eq!(gvar.get_deltas(0, &[0.0], &points, &[2]).unwrap(), None);
+		assert_eq!(gvar.get_deltas(0, &[-0.5], &points, &[2]).unwrap(), Some(vec![(0.0, 0.0); 3]));
+
+		let missing = Gvar { shared_tuples: vec![], ..gvar };
+		assert!(missing.get_deltas(0, &[0.5], &points, &[2]).is_err());
+	}
+}
@@ -5,7 +5,7 @@ use winit::keyboard::Key;
 use winit::platform::modifier_supplement::KeyEventExtModifierSupplement;
 use winit::window::{Window, WindowId};
 use crate::render_manager::{self, RenderManager};
-use crate::font_loader::FontFile;
+use font_render::font_loader::FontFile;
 use glow::HasContext;
 
 pub struct App {
@@ -26,8 +26,14 @@ impl Default for App {
 
 impl ApplicationHandler for App {
 	fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+		// a font without a usable name table still opens, just under the default title
+		let font_file = self.font_file.as_mut().unwrap();
+		let title = match font_file.get_name_table() {
+			Ok(()) => font_file.full_name().or(font_file.family_name()).unwrap_or("Font Viewer").to_string(),
+			Err(_) => "Font Viewer".to_string(),
+		};
 		let window_attributes = Window::default_attributes()
-			.with_title("Font Viewer")
+			.with_title(title)
 			.with_visible(false);
 		let window = event_loop.create_window(window_attributes).unwrap();
 		window.set_visible(true);
@@ -36,8 +42,10 @@ impl ApplicationHandler for App {
 		self.window = Some(window);
 
 		let font_file = self.font_file.as_mut().unwrap();
-		font_file.get_glyphs();
-		font_file.get_unicode_to_glyph_index_map();
+		if let Err(e) = font_file.load_layout_tables() {
+			eprintln!("Error: {}", e);
+			event_loop.exit();
+		}
 	}
 	
 	fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
@@ -67,6 +75,32 @@ impl ApplicationHandler for App {
 						Key::Named(winit::keyboard::NamedKey::Escape) => {
 							event_loop.exit();
 						},
+						// compare kerned and unkerned layout
+						Key::Named(winit::keyboard::NamedKey::F1) => {
+							let font_file = self.font_file.as_mut().unwrap();
+							font_file.set_feature(*b"kern", (font_file.get_feature_value(*b"kern") == 0) as u32);
+							self.window.as_ref().unwrap().request_redraw();
+						},
+						// and with and without ligatures
+						Key::Named(winit::keyboard::NamedKey::F2) => {
+							let font_file = self.font_file.as_mut().unwrap();
+							for feature in [*b"liga", *b"calt"] {
+								font_file.set_feature(feature, (font_file.get_feature_value(feature) == 0) as u32);
+							}
+							self.window.as_ref().unwrap().request_redraw();
+						},
+						// and hinted at the size it's drawn at
+						Key::Named(winit::keyboard::NamedKey::F3) => {
+							let font_file = self.font_file.as_mut().unwrap();
+							let ppem = match (&font_file.hinting, font_file.units_per_em()) {
+								(None, Ok(units_per_em)) => Some((units_per_em as f32 * render_manager::TEXT_SCALE).round() as u16),
+								_ => None,
+							};
+							if let Err(e) = font_file.set_hinting(ppem) {
+								eprintln!("Error: can't hint {}", e);
+							}
+							self.window.as_ref().unwrap().request_redraw();
+						},
 						_ => (),
 					}
 					let letter = event.logical_key.to_text();
@@ -0,0 +1,684 @@
+use std::io::Read;
+
+use flate2::read::ZlibDecoder;
+
+use crate::font_error::FontError;
+use crate::reader::{Reader, Tag};
+
+const WOFF_SIGNATURE: u32 = 0x774F4646; // 'wOFF'
+const WOFF2_SIGNATURE: u32 = 0x774F4632; // 'wOF2'
+const COLLECTION_FLAVOR: u32 = 0x74746366; // 'ttcf'
+
+// tags for the 6 bit known table index in the woff2 table directory
+const WOFF2_KNOWN_TAGS: [&[u8; 4]; 63] = [
+	b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm", b"glyf", b"loca", b"prep",
+	b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern", b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE",
+	b"GDEF", b"GPOS", b"GSUB", b"EBSC", b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt",
+	b"avar", b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty", b"just", b"lcar",
+	b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat", b"Gloc", b"Feat", b"Sill",
+];
+
+// web fonts are unpacked into a plain sfnt so the rest of the loader never sees the container
+pub fn decode_font_container(buffer: Vec<u8>) -> Result<Vec<u8>, FontError> {
+	match Reader::new(&buffer).read_u32()? {
+		WOFF_SIGNATURE => decode_woff(&buffer),
+		WOFF2_SIGNATURE => decode_woff2(&buffer),
+		_ => Ok(buffer),
+	}
+}
+
+pub fn decode_woff(buffer: &[u8]) -> Result<Vec<u8>, FontError> {
+	let mut reader = Reader::new(buffer);
+	reader.skip(4)?; //skip signature
+	let flavor = reader.read_u32()?;
+	reader.skip(4)?; //skip length
+	let num_tables = reader.read_u16()?;
+	reader.seek(44)?; //skip the rest of the header
+
+	let mut tables: Vec<(Tag, Vec<u8>)> = vec![];
+	for _ in 0..num_tables {
+		let tag = reader.read_tag()?;
+		let offset = reader.read_offset32()?;
+		let comp_length = reader.read_u32()? as usize;
+		let orig_length = reader.read_u32()? as usize;
+		reader.skip(4)?; //skip origChecksum
+
+		let data = Reader::new(buffer).sub_reader(offset, comp_length)?.data();
+		// tables that didn't get smaller are stored as they are
+		let table = if comp_length == orig_length {
+			data.to_vec()
+		} else if comp_length < orig_length {
+			inflate(data, orig_length)?
+		} else {
+			return Err(FontError::Malformed("WOFF table is larger compressed than uncompressed"));
+		};
+		tables.push((tag, table));
+	}
+	Ok(build_sfnt(flavor, tables))
+}
+
+fn inflate(data: &[u8], expected_length: usize) -> Result<Vec<u8>, FontError> {
+	let mut table: Vec<u8> = vec![];
+	// never read past the declared size, a bad stream shouldn't be able to fill memory
+	ZlibDecoder::new(data).take(expected_length as u64 + 1).read_to_end(&mut table)?;
+	if table.len() != expected_length {
+		return Err(FontError::Malformed("WOFF table does not decompress to its declared size"));
+	}
+	Ok(table)
+}
+
+struct Woff2Table {
+	tag: Tag,
+	orig_length: usize,
+	transform_length: usize,
+	transformed: bool,
+}
+
+pub fn decode_woff2(buffer: &[u8]) -> Result<Vec<u8>, FontError> {
+	let mut reader = Reader::new(buffer);
+	reader.skip(4)?; //skip signature
+	let flavor = reader.read_u32()?;
+	reader.skip(4)?; //skip length
+	let num_tables = reader.read_u16()?;
+	reader.skip(6)?; //skip reserved, totalSfntSize
+	let total_compressed_size = reader.read_u32()? as usize;
+	reader.seek(48)?; //skip version and metadata
+
+	if flavor == COLLECTION_FLAVOR {
+		return Err(FontError::Malformed("WOFF2 font collections are not supported"));
+	}
+
+	let mut tables: Vec<Woff2Table> = vec![];
+	for _ in 0..num_tables {
+		let flags = reader.read_u8()?;
+		let tag = match flags & 0x3F {
+			0x3F => reader.read_tag()?,
+			index => *WOFF2_KNOWN_TAGS[index as usize],
+		};
+		let orig_length = read_uint_base128(&mut reader)? as usize;
+		// glyf and loca are transformed unless version 3, every other table only with version 1 (hmtx)
+		let transform_version = flags >> 6;
+		let transformed = if &tag == b"glyf" || &tag == b"loca" {transform_version != 3} else {transform_version != 0};
+		let transform_length = if transformed {read_uint_base128(&mut reader)? as usize} else {orig_length};
+		tables.push(Woff2Table { tag, orig_length, transform_length, transformed });
+	}
+
+	// every table sits back to back in one brotli stream
+	let expected_length = tables.iter().try_fold(0usize, |length, table| length.checked_add(table.transform_length))
+		.ok_or(FontError::Malformed("WOFF2 tables are too large"))?;
+	let compressed = reader.read_bytes(total_compressed_size)?;
+	let mut decompressed: Vec<u8> = vec![];
+	brotli_decompressor::Decompressor::new(compressed, 4096).take(expected_length as u64 + 1).read_to_end(&mut decompressed)?;
+	if decompressed.len() != expected_length {
+		return Err(FontError::Malformed("WOFF2 data does not decompress to the size of its tables"));
+	}
+
+	let mut table_data: Vec<&[u8]> = vec![];
+	let mut offset = 0;
+	for table in &tables {
+		table_data.push(&decompressed[offset..offset + table.transform_length]);
+		offset += table.transform_length;
+	}
+	let find_table = |tag: &[u8; 4]| tables.iter().position(|table| &table.tag == tag);
+
+	let mut sfnt_tables: Vec<(Tag, Vec<u8>)> = vec![];
+	let mut glyph_xmins: Vec<i16> = vec![];
+	if let Some(glyf_index) = find_table(b"glyf").filter(|&index| tables[index].transformed) {
+		let loca_index = find_table(b"loca").ok_or(FontError::MissingTable("loca".to_string()))?;
+		let (glyf, loca, xmins) = reconstruct_glyf(table_data[glyf_index])?;
+		if loca.len() != tables[loca_index].orig_length {
+			return Err(FontError::Malformed("reconstructed loca does not match its declared size"));
+		}
+		sfnt_tables.push((*b"glyf", glyf));
+		sfnt_tables.push((*b"loca", loca));
+		glyph_xmins = xmins;
+	}
+
+	for (table, data) in tables.iter().zip(&table_data) {
+		if sfnt_tables.iter().any(|(tag, _)| *tag == table.tag) {
+			continue;
+		}
+		let data = if &table.tag == b"hmtx" && table.transformed {
+			let hhea = find_table(b"hhea").map(|index| table_data[index]).ok_or(FontError::MissingTable("hhea".to_string()))?;
+			let maxp = find_table(b"maxp").map(|index| table_data[index]).ok_or(FontError::MissingTable("maxp".to_string()))?;
+			reconstruct_hmtx(data, hhea, maxp, &glyph_xmins)?
+		} else if table.transformed {
+			return Err(FontError::UnsupportedFormat { table: "WOFF2 transform", format: 1 });
+		} else {
+			data.to_vec()
+		};
+		sfnt_tables.push((table.tag, data));
+	}
+	Ok(build_sfnt(flavor, sfnt_tables))
+}
+
+fn read_uint_base128(reader: &mut Reader) -> Result<u32, FontError> {
+	let mut value: u32 = 0;
+	for i in 0..5 {
+		let byte = reader.read_u8()?;
+		if i == 0 && byte == 0x80 {
+			return Err(FontError::Malformed("UIntBase128 with leading zeros"));
+		}
+		if value & 0xFE000000 != 0 {
+			return Err(FontError::Malformed("UIntBase128 overflows 32 bits"));
+		}
+		value = (value << 7) | (byte & 0x7F) as u32;
+		if byte & 0x80 == 0 {
+			return Ok(value);
+		}
+	}
+	Err(FontError::Malformed("UIntBase128 is longer than 5 bytes"))
+}
+
+fn read_255_uint16(reader: &mut Reader) -> Result<u16, FontError> {
+	match reader.read_u8()? {
+		253 => reader.read_u16(),
+		254 => Ok(reader.read_u8()? as u16 + 506),
+		255 => Ok(reader.read_u8()? as u16 + 253),
+		code => Ok(code as u16),
+	}
+}
+
+// the transformed glyf table splits every glyph across separate streams
+struct GlyfStreams<'a> {
+	n_contours: Reader<'a>,
+	n_points: Reader<'a>,
+	flags: Reader<'a>,
+	glyphs: Reader<'a>,
+	composites: Reader<'a>,
+	bbox_bitmap: &'a [u8],
+	bboxes: Reader<'a>,
+	instructions: Reader<'a>,
+	overlap_bitmap: Option<&'a [u8]>,
+}
+
+fn bitmap_bit(bitmap: &[u8], index: usize) -> bool {
+	bitmap.get(index / 8).is_some_and(|byte| byte & (0x80 >> (index % 8)) != 0)
+}
+
+// rebuilt glyf and loca tables plus every glyph's xMin for the hmtx transform
+type ReconstructedGlyf = (Vec<u8>, Vec<u8>, Vec<i16>);
+
+fn reconstruct_glyf(data: &[u8]) -> Result<ReconstructedGlyf, FontError> {
+	let mut reader = Reader::new(data);
+	reader.skip(2)?; //skip reserved
+	let option_flags = reader.read_u16()?;
+	let num_glyphs = reader.read_u16()? as usize;
+	let index_format = reader.read_u16()?;
+
+	let mut stream_sizes = [0usize; 7];
+	for size in stream_sizes.iter_mut() {
+		*size = reader.read_u32()? as usize;
+	}
+	let mut next_stream = |size: usize| -> Result<Reader, FontError> {
+		Ok(Reader::new(reader.read_bytes(size)?))
+	};
+	let n_contours = next_stream(stream_sizes[0])?;
+	let n_points = next_stream(stream_sizes[1])?;
+	let flags = next_stream(stream_sizes[2])?;
+	let glyphs = next_stream(stream_sizes[3])?;
+	let composites = next_stream(stream_sizes[4])?;
+	let mut bboxes = next_stream(stream_sizes[5])?;
+	let instructions = next_stream(stream_sizes[6])?;
+	let bbox_bitmap = bboxes.read_bytes(num_glyphs.div_ceil(32) * 4)?;
+	let overlap_bitmap = if option_flags & 1 != 0 {Some(reader.read_bytes(num_glyphs.div_ceil(8))?)} else {None};
+
+	let mut streams = GlyfStreams { n_contours, n_points, flags, glyphs, composites, bbox_bitmap, bboxes, instructions, overlap_bitmap };
+	let mut glyf: Vec<u8> = vec![];
+	let mut locations: Vec<usize> = vec![0];
+	let mut xmins: Vec<i16> = vec![];
+	for glyph_index in 0..num_glyphs {
+		let (glyph, xmin) = reconstruct_glyph(&mut streams, glyph_index)?;
+		glyf.extend(glyph);
+		// keep every glyph 4 byte aligned so short loca offsets stay exact
+		glyf.resize(glyf.len().next_multiple_of(4), 0);
+		locations.push(glyf.len());
+		xmins.push(xmin);
+	}
+
+	let mut loca: Vec<u8> = vec![];
+	for location in locations {
+		if index_format == 0 {
+			let location = u16::try_from(location / 2).map_err(|_| FontError::Malformed("glyf is too large for short loca offsets"))?;
+			loca.extend(location.to_be_bytes());
+		} else {
+			loca.extend((location as u32).to_be_bytes());
+		}
+	}
+	Ok((glyf, loca, xmins))
+}
+
+fn reconstruct_glyph(streams: &mut GlyfStreams, glyph_index: usize) -> Result<(Vec<u8>, i16), FontError> {
+	let number_of_contours = streams.n_contours.read_i16()?;
+	let has_bbox = bitmap_bit(streams.bbox_bitmap, glyph_index);
+	let mut glyph: Vec<u8> = vec![];
+
+	if number_of_contours == 0 {
+		if has_bbox {
+			return Err(FontError::Malformed("empty WOFF2 glyph has a bounding box"));
+		}
+		return Ok((glyph, 0));
+	}
+
+	if number_of_contours < 0 {
+		// composites copy their component records through, the bounding box is always explicit
+		if !has_bbox {
+			return Err(FontError::Malformed("composite WOFF2 glyph has no bounding box"));
+		}
+		let bbox = streams.bboxes.read_bytes(8)?;
+		let (component_data, have_instructions) = read_composite_components(&mut streams.composites)?;
+		glyph.extend((-1i16).to_be_bytes());
+		glyph.extend(bbox);
+		glyph.extend(component_data);
+		if have_instructions {
+			let instruction_length = read_255_uint16(&mut streams.glyphs)?;
+			glyph.extend(instruction_length.to_be_bytes());
+			glyph.extend(streams.instructions.read_bytes(instruction_length as usize)?);
+		}
+		return Ok((glyph, i16::from_be_bytes([bbox[0], bbox[1]])));
+	}
+
+	let mut end_pts_of_contours: Vec<u16> = vec![];
+	let mut num_points: u32 = 0;
+	for _ in 0..number_of_contours {
+		num_points += read_255_uint16(&mut streams.n_points)? as u32;
+		if num_points == 0 || num_points > u16::MAX as u32 {
+			return Err(FontError::Malformed("invalid WOFF2 contour point count"));
+		}
+		end_pts_of_contours.push((num_points - 1) as u16);
+	}
+
+	let mut points: Vec<(i32, i32, bool)> = vec![];
+	let (mut x, mut y) = (0i32, 0i32);
+	for _ in 0..num_points {
+		let flag = streams.flags.read_u8()?;
+		let (dx, dy) = read_triplet(&mut streams.glyphs, flag & 0x7F)?;
+		x += dx;
+		y += dy;
+		points.push((x, y, flag & 0x80 == 0));
+	}
+	let instruction_length = read_255_uint16(&mut streams.glyphs)?;
+	let instructions = streams.instructions.read_bytes(instruction_length as usize)?;
+
+	let (xmin, ymin, xmax, ymax) = if has_bbox {
+		(streams.bboxes.read_i16()?, streams.bboxes.read_i16()?, streams.bboxes.read_i16()?, streams.bboxes.read_i16()?)
+	} else {
+		let clamp = |value: i32| value.clamp(i16::MIN as i32, i16::MAX as i32) as i16;
+		(
+			clamp(points.iter().map(|point| point.0).min().unwrap_or(0)),
+			clamp(points.iter().map(|point| point.1).min().unwrap_or(0)),
+			clamp(points.iter().map(|point| point.0).max().unwrap_or(0)),
+			clamp(points.iter().map(|point| point.1).max().unwrap_or(0)),
+		)
+	};
+
+	glyph.extend(number_of_contours.to_be_bytes());
+	for value in [xmin, ymin, xmax, ymax] {
+		glyph.extend(value.to_be_bytes());
+	}
+	for end_point in end_pts_of_contours {
+		glyph.extend(end_point.to_be_bytes());
+	}
+	glyph.extend(instruction_length.to_be_bytes());
+	glyph.extend(instructions);
+
+	// write the points back in the plain glyf encoding, one flag per point without repeats
+	let overlap_simple = streams.overlap_bitmap.is_some_and(|bitmap| bitmap_bit(bitmap, glyph_index));
+	let mut flags: Vec<u8> = vec![];
+	let mut xcoordinates: Vec<u8> = vec![];
+	let mut ycoordinates: Vec<u8> = vec![];
+	let (mut previous_x, mut previous_y) = (0i32, 0i32);
+	for (i, &(x, y, on_curve)) in points.iter().enumerate() {
+		let mut flag = on_curve as u8;
+		if i == 0 && overlap_simple {
+			flag |= 0x40;
+		}
+		flag |= encode_coordinate(x - previous_x, 1, 4, &mut xcoordinates)?;
+		flag |= encode_coordinate(y - previous_y, 2, 5, &mut ycoordinates)?;
+		flags.push(flag);
+		(previous_x, previous_y) = (x, y);
+	}
+	glyph.extend(flags);
+	glyph.extend(xcoordinates);
+	glyph.extend(ycoordinates);
+	Ok((glyph, xmin))
+}
+
+fn encode_coordinate(delta: i32, short_bit: u8, same_bit: u8, coordinates: &mut Vec<u8>) -> Result<u8, FontError> {
+	if delta == 0 {
+		Ok(1 << same_bit)
+	} else if delta.abs() < 256 {
+		coordinates.push(delta.unsigned_abs() as u8);
+		Ok((1 << short_bit) | if delta > 0 {1 << same_bit} else {0})
+	} else {
+		let delta = i16::try_from(delta).map_err(|_| FontError::Malformed("WOFF2 glyph coordinate out of range"))?;
+		coordinates.extend(delta.to_be_bytes());
+		Ok(0)
+	}
+}
+
+// point deltas are packed in 1 to 4 bytes, the low 7 bits of the flag pick the layout and signs
+fn read_triplet(reader: &mut Reader, flag: u8) -> Result<(i32, i32), FontError> {
+	let with_sign = |flag: u8, value: i32| if flag & 1 != 0 {value} else {-value};
+	let flag_value = flag as i32;
+	Ok(match flag {
+		0..=9 => {
+			let b0 = reader.read_u8()? as i32;
+			(0, with_sign(flag, ((flag_value & 14) << 7) + b0))
+		}
+		10..=19 => {
+			let b0 = reader.read_u8()? as i32;
+			(with_sign(flag, (((flag_value - 10) & 14) << 7) + b0), 0)
+		}
+		20..=83 => {
+			let b0 = flag_value - 20;
+			let b1 = reader.read_u8()? as i32;
+			(with_sign(flag, 1 + (b0 & 0x30) + (b1 >> 4)), with_sign(flag >> 1, 1 + ((b0 & 0x0C) << 2) + (b1 & 0x0F)))
+		}
+		84..=119 => {
+			let b0 = flag_value - 84;
+			let (b1, b2) = (reader.read_u8()? as i32, reader.read_u8()? as i32);
+			(with_sign(flag, 1 + ((b0 / 12) << 8) + b1), with_sign(flag >> 1, 1 + (((b0 % 12) >> 2) << 8) + b2))
+		}
+		120..=123 => {
+			let (b1, b2, b3) = (reader.read_u8()? as i32, reader.read_u8()? as i32, reader.read_u8()? as i32);
+			(with_sign(flag, (b1 << 4) + (b2 >> 4)), with_sign(flag >> 1, ((b2 & 0x0F) << 8) + b3))
+		}
+		_ => {
+			let (dx, dy) = (reader.read_u16()? as i32, reader.read_u16()? as i32);
+			(with_sign(flag, dx), with_sign(flag >> 1, dy))
+		}
+	})
+}
+
+// walks the component records to find where the composite ends, returns its bytes and whether instructions follow
+fn read_composite_components<'a>(reader: &mut Reader<'a>) -> Result<(&'a [u8], bool), FontError> {
+	const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+	const WE_HAVE_A_SCALE: u16 = 0x0008;
+	const MORE_COMPONENTS: u16 = 0x0020;
+	const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+	const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+	const WE_HAVE_INSTRUCTIONS: u16 = 0x0100;
+
+	let start = reader.offset();
+	let mut have_instructions = false;
+	loop {
+		let flags = reader.read_u16()?;
+		let mut size = 2 + if flags & ARG_1_AND_2_ARE_WORDS != 0 {4} else {2};
+		if flags & WE_HAVE_A_SCALE != 0 {
+			size += 2;
+		} else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+			size += 4;
+		} else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+			size += 8;
+		}
+		reader.skip(size)?;
+		have_instructions |= flags & WE_HAVE_INSTRUCTIONS != 0;
+		if flags & MORE_COMPONENTS == 0 {
+			break;
+		}
+	}
+	let end = reader.offset();
+	Ok((&reader.data()[start..end], have_instructions))
+}
+
+fn reconstruct_hmtx(data: &[u8], hhea: &[u8], maxp: &[u8], glyph_xmins: &[i16]) -> Result<Vec<u8>, FontError> {
+	let number_of_h_metrics = Reader::new(hhea).at(34)?.read_u16()? as usize;
+	let num_glyphs = Reader::new(maxp).at(4)?.read_u16()? as usize;
+	if number_of_h_metrics > num_glyphs || glyph_xmins.len() < num_glyphs {
+		return Err(FontError::Malformed("transformed hmtx does not match the glyph count"));
+	}
+
+	// left side bearings equal to the glyph's xMin may be left out
+	let mut reader = Reader::new(data);
+	let flags = reader.read_u8()?;
+	let mut advance_widths: Vec<u16> = vec![];
+	for _ in 0..number_of_h_metrics {
+		advance_widths.push(reader.read_u16()?);
+	}
+	let mut lsbs: Vec<i16> = vec![];
+	for (glyph_index, &xmin) in glyph_xmins.iter().enumerate().take(num_glyphs) {
+		let has_lsb = if glyph_index < number_of_h_metrics {flags & 1 == 0} else {flags & 2 == 0};
+		lsbs.push(if has_lsb {reader.read_i16()?} else {xmin});
+	}
+
+	let mut hmtx: Vec<u8> = vec![];
+	for (glyph_index, lsb) in lsbs.iter().enumerate() {
+		if let Some(advance_width) = advance_widths.get(glyph_index) {
+			hmtx.extend(advance_width.to_be_bytes());
+		}
+		hmtx.extend(lsb.to_be_bytes());
+	}
+	Ok(hmtx)
+}
+
+fn table_checksum(data: &[u8]) -> u32 {
+	data.chunks(4).fold(0u32, |sum, chunk| {
+		let mut word = [0u8; 4];
+		word[..chunk.len()].copy_from_slice(chunk);
+		sum.wrapping_add(u32::from_be_bytes(word))
+	})
+}
+
+pub fn build_sfnt(flavor: u32, mut tables: Vec<(Tag, Vec<u8>)>) -> Vec<u8> {
+	tables.sort_by_key(|table| table.0);
+	let num_tables = tables.len() as u32;
+	let entry_selector = if num_tables == 0 {0} else {num_tables.ilog2()};
+	let search_range = (1 << entry_selector) * 16;
+
+	let mut sfnt: Vec<u8> = vec![];
+	sfnt.extend(flavor.to_be_bytes());
+	sfnt.extend((num_tables as u16).to_be_bytes());
+	sfnt.extend((search_range as u16).to_be_bytes());
+	sfnt.extend((entry_selector as u16).to_be_bytes());
+	sfnt.extend(((num_tables * 16 - search_range) as u16).to_be_bytes());
+
+	let mut offset = 12 + tables.len() * 16;
+	for (tag, data) in &tables {
+		sfnt.extend(tag);
+		sfnt.extend(table_checksum(data).to_be_bytes());
+		sfnt.extend((offset as u32).to_be_bytes());
+		sfnt.extend((data.len() as u32).to_be_bytes());
+		offset += data.len().next_multiple_of(4);
+	}
+	for (_, data) in tables {
+		sfnt.extend(&data);
+		sfnt.resize(sfnt.len().next_multiple_of(4), 0);
+	}
+	sfnt
+}
+
+#[cfg(test)]
+mod tests {
+	use std::io::Write;
+	use std::sync::Arc;
+
+	use flate2::write::ZlibEncoder;
+	use flate2::Compression;
+
+	use super::*;
+	use crate::font_loader::{FontFile, GlyphDescription};
+	use crate::sfnt_builder::*;
+
+	// wraps an sfnt in WOFF 1.0, tables that don't shrink are stored as they are
+	fn encode_woff(sfnt: &[u8]) -> Vec<u8> {
+		let mut reader = Reader::new(sfnt);
+		let flavor = reader.read_u32().unwrap();
+		let num_tables = reader.read_u16().unwrap();
+		reader.seek(12).unwrap();
+
+		let mut directory: Vec<u8> = vec![];
+		let mut data: Vec<u8> = vec![];
+		let data_start = 44 + 20 * num_tables as usize;
+		for _ in 0..num_tables {
+			let tag = reader.read_tag().unwrap();
+			let checksum = reader.read_u32().unwrap();
+			let offset = reader.read_offset32().unwrap();
+			let length = reader.read_u32().unwrap() as usize;
+			let table = &sfnt[offset..offset + length];
+
+			let mut encoder = ZlibEncoder::new(vec![], Compression::best());
+			encoder.write_all(table).unwrap();
+			let compressed = encoder.finish().unwrap();
+			let stored = if compressed.len() < length {compressed} else {table.to_vec()};
+
+			directory.extend(tag);
+			directory.extend(((data_start + data.len()) as u32).to_be_bytes());
+			directory.extend((stored.len() as u32).to_be_bytes());
+			directory.extend((length as u32).to_be_bytes());
+			directory.extend(checksum.to_be_bytes());
+			data.extend(stored);
+			data.resize(data.len().next_multiple_of(4), 0);
+		}
+
+		let mut woff: Vec<u8> = vec![];
+		woff.extend(WOFF_SIGNATURE.to_be_bytes());
+		woff.extend(flavor.to_be_bytes());
+		woff.extend(((data_start + data.len()) as u32).to_be_bytes());
+		woff.extend(num_tables.to_be_bytes());
+		woff.extend([0; 2]); // reserved
+		woff.extend((sfnt.len() as u32).to_be_bytes());
+		woff.extend([0; 24]); // version, metadata and private data
+		woff.extend(directory);
+		woff.extend(data);
+		woff
+	}
+
+	fn load(sfnt: Vec<u8>) -> FontFile {
+		let mut font_file = FontFile::from_buffer(Arc::new(sfnt), 0).unwrap();
+		font_file.get_glyphs().unwrap();
+		font_file.get_unicode_to_glyph_index_map().unwrap();
+		font_file
+	}
+
+	#[test]
+	fn woff_decodes_to_the_same_font() {
+		// enough repeated outlines that glyf compresses while the small tables are stored raw
+		let square: Contour = vec![(0, 0, true), (400, 0, true), (400, 600, true), (0, 600, true)];
+		let bowl: Contour = vec![(50, 0, true), (300, -20, false), (350, 300, true), (300, 620, false), (50, 600, true)];
+		let mut glyphs = vec![vec![]];
+		for _ in 0..20 {
+			glyphs.push(encode_simple_glyph(&[square.clone(), bowl.clone()]));
+		}
+		let sfnt = minimal_font(&glyphs, true, cmap_format_4(&[(0x41, 0x54, -0x40, None)]));
+		let woff = encode_woff(&sfnt);
+		assert!(woff.len() < sfnt.len());
+
+		let decoded = decode_font_container(woff).unwrap();
+		let (original, decoded) = (load(sfnt), load(decoded));
+		for tag in ["head", "hhea", "maxp", "hmtx", "loca", "glyf", "cmap"] {
+			assert_eq!(original.table_reader(tag).unwrap().data(), decoded.table_reader(tag).unwrap().data(), "{}", tag);
+		}
+		assert_eq!(original.glyphs.len(), decoded.glyphs.len());
+		for (original, decoded) in original.glyphs.iter().zip(&decoded.glyphs) {
+			assert_eq!(original.end_pts_of_contours, decoded.end_pts_of_contours);
+			assert!(original.coordinates.iter().zip(&decoded.coordinates).all(|(a, b)| (a.x, a.y, a.flags) == (b.x, b.y, b.flags)));
+		}
+		assert_eq!(original.unicode_to_glyph_index_map, decoded.unicode_to_glyph_index_map);
+		assert_eq!(decoded.unicode_to_glyph_index_map.get(&0x41), Some(&1));
+	}
+
+	#[test]
+	fn woff_table_that_inflates_past_its_size() {
+		let sfnt = minimal_font(&[vec![]], false, cmap_format_4(&[]));
+		let mut woff = encode_woff(&sfnt);
+		// claim a larger compressed size than the original for the first table
+		let orig_length = u32::from_be_bytes(woff[56..60].try_into().unwrap());
+		woff[52..56].copy_from_slice(&(orig_length + 1).to_be_bytes());
+		assert!(decode_font_container(woff).is_err());
+	}
+
+	#[test]
+	fn woff2_glyf_and_hmtx_transforms() {
+		// an empty glyph and a square starting at x 20, neither with an explicit bounding box
+		let mut glyf: Vec<u8> = vec![];
+		glyf.extend([0, 0, 0, 0]); // reserved, optionFlags
+		glyf.extend(2u16.to_be_bytes()); // numGlyphs
+		glyf.extend(0u16.to_be_bytes()); // indexFormat
+		let n_contours = [0, 0, 0, 1];
+		let n_points = [4];
+		// +x, +y, -x with 8 bit deltas
+		let flags = [11, 11, 1, 10];
+		let glyph_stream = [20, 100, 100, 100, 0];
+		let bbox_stream = [0; 4];
+		for size in [n_contours.len(), n_points.len(), flags.len(), glyph_stream.len(), 0, bbox_stream.len(), 0] {
+			glyf.extend((size as u32).to_be_bytes());
+		}
+		for stream in [&n_contours[..], &n_points, &flags, &glyph_stream, &bbox_stream] {
+			glyf.extend(stream);
+		}
+
+		let (glyf, loca, xmins) = reconstruct_glyf(&glyf).unwrap();
+		assert_eq!(xmins, vec![0, 20]);
+		let offsets: Vec<usize> = loca.chunks_exact(2).map(|offset| u16::from_be_bytes([offset[0], offset[1]]) as usize * 2).collect();
+		assert_eq!(offsets.len(), 3);
+		let glyphs = vec![glyf[offsets[0]..offsets[1]].to_vec(), glyf[offsets[1]..offsets[2]].to_vec()];
+		let square: Contour = vec![(20, 0, true), (120, 0, true), (120, 100, true), (20, 100, true)];
+		let decoded = load(minimal_font(&glyphs, false, cmap_format_4(&[])));
+		let expected = load(minimal_font(&[vec![], encode_simple_glyph(&[square])], false, cmap_format_4(&[])));
+		assert!(decoded.glyphs[0].coordinates.is_empty());
+		// the flag bytes differ, the plain encoder folds repeats where the reconstruction doesn't
+		let points = |glyph: &GlyphDescription| glyph.coordinates.iter().map(|point| (point.x, point.y, point.flags & 1)).collect::<Vec<_>>();
+		assert_eq!(points(&decoded.glyphs[1]), points(&expected.glyphs[1]));
+		assert_eq!((decoded.glyphs[1].xmin, decoded.glyphs[1].ymax), (20, 100));
+
+		// both left side bearing arrays left out, they come from the xMins
+		let mut hhea = vec![0; 36];
+		hhea[34..36].copy_from_slice(&2u16.to_be_bytes());
+		let mut maxp = vec![0; 6];
+		maxp[4..6].copy_from_slice(&2u16.to_be_bytes());
+		let hmtx = [3, 0x01, 0xF4, 0x02, 0x58];
+		let hmtx = reconstruct_hmtx(&hmtx, &hhea, &maxp, &xmins).unwrap();
+		assert_eq!(hmtx, [0x01, 0xF4, 0, 0, 0x02, 0x58, 0, 20]);
+	}
+
+	#[test]
+	fn triplet_encodings() {
+		// (flag, bytes, delta) across every layout, bit 0 of the flag is the x sign and bit 1 the y sign where both move
+		let cases: [(u8, &[u8], (i32, i32)); 12] = [
+			(0, &[5], (0, -5)),
+			(1, &[5], (0, 5)),
+			(3, &[5], (0, 261)),
+			(10, &[7], (-7, 0)),
+			(13, &[7], (263, 0)),
+			(20, &[0x23], (-3, -4)),
+			(23, &[0x23], (3, 4)),
+			(24, &[0x00], (-1, -17)),
+			(84, &[1, 2], (-2, -3)),
+			(96, &[0, 0], (-257, -1)),
+			(120, &[0x12, 0x34, 0x56], (-0x123, -0x456)),
+			(127, &[0x03, 0xE8, 0x07, 0xD0], (1000, 2000)),
+		];
+		for (flag, bytes, delta) in cases {
+			let mut reader = Reader::new(bytes);
+			assert_eq!(read_triplet(&mut reader, flag).unwrap(), delta, "flag {}", flag);
+			assert_eq!(reader.remaining(), 0, "flag {}", flag);
+		}
+	}
+
+	#[test]
+	fn uint16_255_encodings() {
+		let cases: [(&[u8], u16); 6] = [
+			(&[0], 0),
+			(&[252], 252),
+			(&[255, 0], 253),
+			(&[255, 252], 505),
+			(&[254, 0], 506),
+			(&[253, 0x12, 0x34], 0x1234),
+		];
+		for (bytes, value) in cases {
+			assert_eq!(read_255_uint16(&mut Reader::new(bytes)).unwrap(), value, "{:?}", bytes);
+		}
+		assert!(read_255_uint16(&mut Reader::new(&[253, 0x12])).is_err());
+	}
+
+	#[test]
+	fn uint_base128_encodings() {
+		assert_eq!(read_uint_base128(&mut Reader::new(&[0x3F])).unwrap(), 63);
+		assert_eq!(read_uint_base128(&mut Reader::new(&[0x81, 0x00])).unwrap(), 128);
+		assert_eq!(read_uint_base128(&mut Reader::new(&[0x8F, 0xFF, 0xFF, 0xFF, 0x7F])).unwrap(), u32::MAX);
+		assert!(read_uint_base128(&mut Reader::new(&[0x80, 0x01])).is_err());
+		assert!(read_uint_base128(&mut Reader::new(&[0x90, 0x80, 0x80, 0x80, 0x00])).is_err());
+		assert!(read_uint_base128(&mut Reader::new(&[0x81, 0x80, 0x80, 0x80, 0x80, 0x00])).is_err());
+	}
+}
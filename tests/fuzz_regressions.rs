@@ -0,0 +1,54 @@
+// every file in tests/fuzz_regressions once crashed or hung FontFile::parse
+// it has to come back with a font or an error, and quickly, the same as the fuzz target checks
+// new crashes from fuzz/artifacts/parse are copied in under a name that says what they broke
+
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use font_render::font_loader::FontFile;
+use font_render::shaping::{shape, Direction, DEFAULT_SCRIPT};
+
+// far above what a real font needs in a debug build, far below the hangs these files used to cause
+const TIME_LIMIT: Duration = Duration::from_secs(10);
+
+fn parse_and_shape(data: &[u8]) -> bool {
+	let Ok(mut font_file) = FontFile::parse(data) else {
+		return false;
+	};
+	let features = font_file.get_feature_settings();
+	shape(&font_file, "fi Ta\u{301}", DEFAULT_SCRIPT, None, Direction::LeftToRight, &features);
+	shape(&font_file, "AV", DEFAULT_SCRIPT, None, Direction::RightToLeft, &features);
+	// hinting errors are fine, like in the fuzz target only a panic or a hang fails
+	let _ = font_file.set_hinting(Some(12));
+	true
+}
+
+#[test]
+fn fuzz_regressions() {
+	let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("fuzz_regressions");
+	for entry in std::fs::read_dir(directory).unwrap() {
+		let path = entry.unwrap().path();
+		let data = std::fs::read(&path).unwrap();
+		let start = Instant::now();
+		parse_and_shape(&data);
+		assert!(start.elapsed() < TIME_LIMIT, "{} took {:?}", path.display(), start.elapsed());
+	}
+}
+
+// the fuzz corpus is seeded from fonts/, so every seed has to get through parse whole
+#[test]
+fn bundled_fonts_parse() {
+	let directory = Path::new(env!("CARGO_MANIFEST_DIR")).join("fonts");
+	for entry in std::fs::read_dir(directory).unwrap() {
+		let path = entry.unwrap().path();
+		assert!(parse_and_shape(&std::fs::read(&path).unwrap()), "{} didn't parse", path.display());
+	}
+}
+
+#[test]
+fn truncated_fonts() {
+	let data = std::fs::read(Path::new(env!("CARGO_MANIFEST_DIR")).join("fonts").join("Atkinson.ttf")).unwrap();
+	for length in (0..data.len()).step_by(97) {
+		parse_and_shape(&data[..length]);
+	}
+}
@@ -0,0 +1,172 @@
+// renders a sample string per bundled font through the cpu rasterizer and compares it with tests/golden/<name>.png
+// run with UPDATE_GOLDEN=1 to write the references again after an intended change
+// mismatches leave <name>.actual.png and <name>.diff.png in cargo's test tmp directory
+
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use flate2::read::ZlibDecoder;
+use font_render::cli::apply_variation;
+use font_render::font_loader::FontFile;
+use font_render::image_writer::{colorize, write_image, ImageFormat};
+use font_render::rasterizer::FillRule;
+use font_render::text_renderer::render_text;
+
+// how far a channel may drift before the pixel counts as different, covers float differences between platforms
+const PIXEL_TOLERANCE: u8 = 8;
+const PADDING: u32 = 4;
+
+struct Case {
+	name: &'static str,
+	font: &'static str,
+	text: &'static str,
+	ppem: f32,
+	variation: &'static str,
+	hinting: bool,
+	fill_rule: FillRule,
+}
+
+impl Default for Case {
+	fn default() -> Self {
+		Self {
+			name: "",
+			font: "",
+			text: "The quick brown fox jumps over the lazy dog 0123456789",
+			ppem: 24.0,
+			variation: "",
+			hinting: false,
+			// variable fonts like bahnschrift overlap their contours, which leaves holes under even-odd
+			fill_rule: FillRule::NonZero,
+		}
+	}
+}
+
+fn golden_directory() -> PathBuf {
+	Path::new(env!("CARGO_MANIFEST_DIR")).join("tests").join("golden")
+}
+
+fn render(case: &Case) -> (u32, u32, Vec<u8>) {
+	let mut font_file = FontFile::new(case.font).unwrap_or_else(|e| panic!("failed to load {}: {}", case.font, e));
+	apply_variation(&mut font_file, case.variation);
+	font_file.load_layout_tables().unwrap();
+	font_file.get_os2_table().unwrap();
+	if case.hinting {
+		font_file.set_hinting(Some(case.ppem as u16)).unwrap();
+	}
+	let bitmap = render_text(&font_file, case.text, case.ppem, case.fill_rule, PADDING).unwrap();
+	(bitmap.width, bitmap.height, colorize(&bitmap, [0, 0, 0], [255, 255, 255]))
+}
+
+// only what write_png produces, 8 bit rgb with every row unfiltered
+fn read_png(path: &Path) -> (u32, u32, Vec<u8>) {
+	let data = std::fs::read(path).unwrap_or_else(|e| panic!("missing reference {}, run with UPDATE_GOLDEN=1 to create it: {}", path.display(), e));
+	let (mut width, mut height) = (0, 0);
+	let mut compressed: Vec<u8> = vec![];
+	let mut offset = 8;
+	while offset + 8 <= data.len() {
+		let length = u32::from_be_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+		let chunk = &data[offset + 8..offset + 8 + length];
+		match &data[offset + 4..offset + 8] {
+			b"IHDR" => {
+				width = u32::from_be_bytes(chunk[0..4].try_into().unwrap());
+				height = u32::from_be_bytes(chunk[4..8].try_into().unwrap());
+				assert_eq!(&chunk[8..10], &[8, 2], "{} isn't 8 bit rgb", path.display());
+			},
+			b"IDAT" => compressed.extend_from_slice(chunk),
+			_ => {}
+		}
+		offset += 12 + length;
+	}
+
+	let mut rows: Vec<u8> = vec![];
+	ZlibDecoder::new(&compressed[..]).read_to_end(&mut rows).unwrap();
+	let stride = width as usize * 3;
+	let mut rgb: Vec<u8> = vec![];
+	for row in rows.chunks_exact(stride + 1) {
+		assert_eq!(row[0], 0, "{} uses png row filters", path.display());
+		rgb.extend_from_slice(&row[1..]);
+	}
+	(width, height, rgb)
+}
+
+// pixels past the tolerance are red, the rest is the actual render faded out
+fn diff_image(actual: &[u8], expected: &[u8]) -> (Vec<u8>, usize) {
+	let mut mismatches = 0;
+	let diff = actual.chunks_exact(3).zip(expected.chunks_exact(3)).flat_map(|(actual, expected)| {
+		if actual.iter().zip(expected).any(|(a, e)| a.abs_diff(*e) > PIXEL_TOLERANCE) {
+			mismatches += 1;
+			[255, 0, 0]
+		} else {
+			[192 + actual[0] / 4, 192 + actual[1] / 4, 192 + actual[2] / 4]
+		}
+	}).collect();
+	(diff, mismatches)
+}
+
+fn check(case: Case) {
+	let (width, height, actual) = render(&case);
+	let reference = golden_directory().join(format!("{}.png", case.name));
+	if std::env::var_os("UPDATE_GOLDEN").is_some() {
+		std::fs::create_dir_all(golden_directory()).unwrap();
+		write_image(&reference, ImageFormat::Png, width, height, &actual, 72.0).unwrap();
+		return;
+	}
+
+	let (expected_width, expected_height, expected) = read_png(&reference);
+	let output = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("golden");
+	std::fs::create_dir_all(&output).unwrap();
+	let actual_path = output.join(format!("{}.actual.png", case.name));
+	if (width, height) != (expected_width, expected_height) {
+		write_image(&actual_path, ImageFormat::Png, width, height, &actual, 72.0).unwrap();
+		panic!("{}: rendered {}x{} but the reference is {}x{}, see {}", case.name, width, height, expected_width, expected_height, actual_path.display());
+	}
+
+	let (diff, mismatches) = diff_image(&actual, &expected);
+	if mismatches > 0 {
+		let diff_path = output.join(format!("{}.diff.png", case.name));
+		write_image(&actual_path, ImageFormat::Png, width, height, &actual, 72.0).unwrap();
+		write_image(&diff_path, ImageFormat::Png, width, height, &diff, 72.0).unwrap();
+		panic!("{}: {} pixels differ from the reference, see {}", case.name, mismatches, diff_path.display());
+	}
+}
+
+#[test]
+fn atkinson() {
+	check(Case { name: "atkinson", font: "fonts/Atkinson.ttf", ..Default::default() });
+}
+
+// atkinson has no overlapping contours, so it renders the same either way
+#[test]
+fn atkinson_evenodd() {
+	check(Case { name: "atkinson_evenodd", font: "fonts/Atkinson.ttf", fill_rule: FillRule::EvenOdd, ..Default::default() });
+}
+
+#[test]
+fn bahnschrift() {
+	check(Case { name: "bahnschrift", font: "fonts/BAHNSCHRIFT.TTF", ..Default::default() });
+}
+
+#[test]
+fn bahnschrift_bold_condensed() {
+	check(Case { name: "bahnschrift_bold_condensed", font: "fonts/BAHNSCHRIFT.TTF", variation: "wght=700,wdth=75", ..Default::default() });
+}
+
+#[test]
+fn consola() {
+	check(Case { name: "consola", font: "fonts/CONSOLA.TTF", text: "fn main() { let x = [0x1F, 42]; } // ~@#$%&*", ..Default::default() });
+}
+
+#[test]
+fn consola_hinted() {
+	check(Case { name: "consola_hinted", font: "fonts/CONSOLA.TTF", ppem: 12.0, hinting: true, ..Default::default() });
+}
+
+#[test]
+fn itc_blackadder() {
+	check(Case { name: "itc_blackadder", font: "fonts/ITCBLKAD.TTF", text: "Blackadder ITC Quietly Judges", ppem: 32.0, ..Default::default() });
+}
+
+#[test]
+fn jetbrains() {
+	check(Case { name: "jetbrains", font: "fonts/JETBRAINS.TTF", text: "let value = items.iter().sum::<u32>(); // 0O1lI", ..Default::default() });
+}